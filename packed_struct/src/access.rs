@@ -0,0 +1,89 @@
+//! Access semantics of fields within hardware registers.
+
+use crate::internal_prelude::v1::*;
+use crate::packing::{PackedStruct, PackingResult};
+use crate::types_bits::ByteArray;
+
+/// How a field behaves when its register is read from or written to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum FieldAccess {
+    /// Read and write.
+    #[default]
+    ReadWrite,
+    /// Read only, writes have no effect.
+    ReadOnly,
+    /// Write only, reads return an undefined value.
+    WriteOnly,
+    /// Writing a one clears the bit, writing a zero has no effect.
+    WriteOneToClear,
+    /// Reading clears the field, writes have no effect.
+    ReadToClear,
+}
+
+impl FieldAccess {
+    /// All the access modes.
+    pub const ALL: [FieldAccess; 5] = [
+        FieldAccess::ReadWrite,
+        FieldAccess::ReadOnly,
+        FieldAccess::WriteOnly,
+        FieldAccess::WriteOneToClear,
+        FieldAccess::ReadToClear,
+    ];
+
+    /// Can a register with this access be read?
+    pub fn is_readable(&self) -> bool {
+        *self != FieldAccess::WriteOnly
+    }
+
+    /// Can a register with this access be written?
+    pub fn is_writable(&self) -> bool {
+        !matches!(*self, FieldAccess::ReadOnly | FieldAccess::ReadToClear)
+    }
+
+    /// The attribute value of this access mode, for instance "w1c".
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            FieldAccess::ReadWrite => "rw",
+            FieldAccess::ReadOnly => "ro",
+            FieldAccess::WriteOnly => "wo",
+            FieldAccess::WriteOneToClear => "w1c",
+            FieldAccess::ReadToClear => "rc",
+        }
+    }
+}
+
+impl Display for FieldAccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// A structure whose fields carry access semantics, implemented by `#[derive(PackedStruct)]`.
+pub trait PackedStructAccess: PackedStruct {
+    /// The bits of all the fields with the given access mode.
+    fn access_mask(access: FieldAccess) -> Self::ByteArray;
+
+    /// Packs the structure into a value that can be written back into its register.
+    ///
+    /// Read-only and read-to-clear fields keep the bits of `current`, the register's
+    /// present contents. Write-1-to-clear fields are written as zeroes, so that
+    /// writing back a value that was read doesn't clear any pending bits.
+    fn pack_write(&self, current: &Self::ByteArray) -> PackingResult<Self::ByteArray> {
+        let mut packed = self.pack()?;
+        let keep = Self::access_mask(FieldAccess::ReadOnly);
+        let keep_rc = Self::access_mask(FieldAccess::ReadToClear);
+        let w1c = Self::access_mask(FieldAccess::WriteOneToClear);
+
+        let current = current.as_bytes_slice();
+        let keep = keep.as_bytes_slice();
+        let keep_rc = keep_rc.as_bytes_slice();
+        let w1c = w1c.as_bytes_slice();
+
+        for (i, byte) in packed.as_mut_bytes_slice().iter_mut().enumerate() {
+            let keep = keep[i] | keep_rc[i];
+            *byte = (*byte & !(keep | w1c[i])) | (current[i] & keep);
+        }
+
+        Ok(packed)
+    }
+}
@@ -0,0 +1,151 @@
+//! Incremental, byte-at-a-time decoding of packed structures.
+
+use crate::packing::{PackedStruct, PackingError, PackingResult};
+use crate::types_bits::ByteArray;
+
+/// Determines the total length of a frame from the bytes received so far.
+/// Returns `None` while there isn't enough data to decide.
+pub type FrameLengthHook = fn(received: &[u8]) -> Option<usize>;
+
+/// Collects bytes one at a time, for example from a UART interrupt handler,
+/// and unpacks the structure as soon as its byte array is complete.
+///
+/// The internal buffer is the structure's own `ByteArray`, so the accumulator
+/// doesn't allocate and can be used in `no_std` environments.
+///
+/// ```rust
+/// use packed_struct::prelude::*;
+/// use packed_struct::PackedAccumulator;
+///
+/// #[derive(PackedStruct, Debug, PartialEq)]
+/// #[packed_struct(endian="msb")]
+/// pub struct Frame {
+///     id: u8,
+///     value: u16
+/// }
+///
+/// fn main() -> Result<(), PackingError> {
+///     let mut acc = PackedAccumulator::<Frame>::new();
+///     assert_eq!(None, acc.push(0x01)?);
+///     assert_eq!(None, acc.push(0x12)?);
+///     assert_eq!(Some(Frame { id: 1, value: 0x1234 }), acc.push(0x34)?);
+///     Ok(())
+/// }
+/// ```
+pub struct PackedAccumulator<T: PackedStruct> {
+    buffer: T::ByteArray,
+    received: usize,
+    timeout_ticks: Option<u32>,
+    idle_ticks: u32,
+    frame_length: Option<FrameLengthHook>,
+}
+
+impl<T: PackedStruct> PackedAccumulator<T> {
+    /// An empty accumulator, without a timeout or a frame length hook.
+    pub fn new() -> Self {
+        PackedAccumulator {
+            buffer: <T::ByteArray as ByteArray>::new(0),
+            received: 0,
+            timeout_ticks: None,
+            idle_ticks: 0,
+            frame_length: None,
+        }
+    }
+
+    /// Discard a partially received frame after `ticks` calls to `tick` without
+    /// a new byte arriving.
+    pub fn with_timeout(mut self, ticks: u32) -> Self {
+        self.timeout_ticks = Some(ticks);
+        self
+    }
+
+    /// Use a hook to complete frames that are shorter than the structure's byte array.
+    /// The bytes that weren't received are set to zero before unpacking.
+    pub fn with_frame_length(mut self, hook: FrameLengthHook) -> Self {
+        self.frame_length = Some(hook);
+        self
+    }
+
+    /// Number of bytes that the structure's byte array holds.
+    pub fn capacity(&self) -> usize {
+        <T::ByteArray as ByteArray>::len()
+    }
+
+    /// Number of bytes received for the current frame.
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    /// The bytes received for the current frame.
+    pub fn received_bytes(&self) -> &[u8] {
+        &self.buffer.as_bytes_slice()[..self.received]
+    }
+
+    /// Discard the current frame.
+    pub fn reset(&mut self) {
+        self.buffer = <T::ByteArray as ByteArray>::new(0);
+        self.received = 0;
+        self.idle_ticks = 0;
+    }
+
+    /// Add a single byte to the current frame. Returns the unpacked structure
+    /// once the frame is complete, after which the accumulator starts a new frame.
+    pub fn push(&mut self, byte: u8) -> PackingResult<Option<T>> {
+        let capacity = self.capacity();
+        if self.received >= capacity {
+            self.reset();
+            return Err(PackingError::BufferTooSmall);
+        }
+
+        self.buffer.as_mut_bytes_slice()[self.received] = byte;
+        self.received += 1;
+        self.idle_ticks = 0;
+
+        let expected = match self.frame_length {
+            Some(hook) => match hook(self.received_bytes()) {
+                Some(len) => len,
+                None => capacity,
+            },
+            None => capacity,
+        };
+
+        if expected > capacity {
+            self.reset();
+            return Err(PackingError::BufferSizeMismatch {
+                expected: capacity,
+                actual: expected,
+            });
+        }
+
+        if self.received < expected {
+            return Ok(None);
+        }
+
+        let unpacked = T::unpack(&self.buffer);
+        self.reset();
+        unpacked.map(Some)
+    }
+
+    /// Advance the timeout counter by one tick, for example from a timer interrupt.
+    /// Returns `true` if a partially received frame was discarded.
+    pub fn tick(&mut self) -> bool {
+        if self.received == 0 {
+            return false;
+        }
+
+        self.idle_ticks = self.idle_ticks.saturating_add(1);
+        match self.timeout_ticks {
+            Some(timeout) if self.idle_ticks >= timeout => {
+                self.reset();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<T: PackedStruct> Default for PackedAccumulator<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
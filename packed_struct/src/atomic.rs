@@ -0,0 +1,291 @@
+//! Lock-free sharing of small packed structures, for instance status words
+//! shared between interrupt handlers and tasks.
+//!
+//! The `atomic_fields` attribute of the derive names a trait with per-field
+//! methods, `set_<field>` and `update_<field>`, that compare-exchange only
+//! the field's bits.
+
+use crate::internal_prelude::v1::*;
+use crate::packing::{PackedStruct, PackingResult};
+use crate::types_bits::ByteArray;
+
+pub use core::sync::atomic::Ordering;
+use core::ops::{BitAnd, BitOr, Not};
+#[cfg(target_has_atomic = "8")]
+use core::sync::atomic::AtomicU8;
+#[cfg(target_has_atomic = "16")]
+use core::sync::atomic::AtomicU16;
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::AtomicU32;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+
+/// A native atomic integer that can hold a packed byte array.
+pub trait AtomicStorage {
+    type Value: Copy + PartialEq + BitAnd<Output = Self::Value> + BitOr<Output = Self::Value> + Not<Output = Self::Value>;
+
+    fn new(value: Self::Value) -> Self;
+    fn load(&self, order: Ordering) -> Self::Value;
+    fn store(&self, value: Self::Value, order: Ordering);
+    fn swap(&self, value: Self::Value, order: Ordering) -> Self::Value;
+    fn compare_exchange_weak(&self, current: Self::Value, new: Self::Value, success: Ordering, failure: Ordering) -> Result<Self::Value, Self::Value>;
+}
+
+/// A byte array that fits into a native atomic integer. The first byte of the
+/// array is the most significant byte of the integer.
+pub trait AtomicByteArray: ByteArray + Sized {
+    type Storage: AtomicStorage;
+
+    fn to_atomic_value(&self) -> <Self::Storage as AtomicStorage>::Value;
+    fn from_atomic_value(value: <Self::Storage as AtomicStorage>::Value) -> Self;
+}
+
+macro_rules! atomic_storage {
+    ($cfg: tt, $A: ty, $T: ty) => {
+        #[cfg(target_has_atomic = $cfg)]
+        impl AtomicStorage for $A {
+            type Value = $T;
+
+            #[inline]
+            fn new(value: $T) -> Self {
+                <$A>::new(value)
+            }
+
+            #[inline]
+            fn load(&self, order: Ordering) -> $T {
+                <$A>::load(self, order)
+            }
+
+            #[inline]
+            fn store(&self, value: $T, order: Ordering) {
+                <$A>::store(self, value, order)
+            }
+
+            #[inline]
+            fn swap(&self, value: $T, order: Ordering) -> $T {
+                <$A>::swap(self, value, order)
+            }
+
+            #[inline]
+            fn compare_exchange_weak(&self, current: $T, new: $T, success: Ordering, failure: Ordering) -> Result<$T, $T> {
+                <$A>::compare_exchange_weak(self, current, new, success, failure)
+            }
+        }
+    };
+}
+
+atomic_storage!("8", AtomicU8, u8);
+atomic_storage!("16", AtomicU16, u16);
+atomic_storage!("32", AtomicU32, u32);
+atomic_storage!("64", AtomicU64, u64);
+
+macro_rules! atomic_byte_array {
+    ($cfg: tt, $N: expr, $A: ty, $T: ty) => {
+        #[cfg(target_has_atomic = $cfg)]
+        impl AtomicByteArray for [u8; $N] {
+            type Storage = $A;
+
+            #[inline]
+            fn to_atomic_value(&self) -> $T {
+                let mut bytes = [0; mem::size_of::<$T>()];
+                bytes[(mem::size_of::<$T>() - $N)..].copy_from_slice(self);
+                <$T>::from_be_bytes(bytes)
+            }
+
+            #[inline]
+            fn from_atomic_value(value: $T) -> Self {
+                let mut bytes = [0; $N];
+                bytes.copy_from_slice(&value.to_be_bytes()[(mem::size_of::<$T>() - $N)..]);
+                bytes
+            }
+        }
+    };
+}
+
+atomic_byte_array!("8", 1, AtomicU8, u8);
+atomic_byte_array!("16", 2, AtomicU16, u16);
+atomic_byte_array!("32", 3, AtomicU32, u32);
+atomic_byte_array!("32", 4, AtomicU32, u32);
+atomic_byte_array!("64", 5, AtomicU64, u64);
+atomic_byte_array!("64", 6, AtomicU64, u64);
+atomic_byte_array!("64", 7, AtomicU64, u64);
+atomic_byte_array!("64", 8, AtomicU64, u64);
+
+/// The atomic integer that backs the packed structure `T`.
+pub type AtomicStorageOf<T> = <<T as PackedStruct>::ByteArray as AtomicByteArray>::Storage;
+
+/// A packed structure of up to 64 bits, stored in a native atomic integer.
+///
+/// Single fields are written with the methods of the trait named by the
+/// `atomic_fields` attribute. They only replace the field's bits, the other
+/// fields keep their concurrently written values. `fetch_update` changes
+/// several fields at once.
+///
+/// ```rust
+/// use packed_struct::prelude::*;
+/// use packed_struct::atomic::AtomicPacked;
+/// use core::sync::atomic::{AtomicU8, Ordering};
+///
+/// #[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+/// #[packed_struct(bit_numbering="msb0", atomic_fields="StatusFields")]
+/// pub struct Status {
+///     #[packed_field(bits="0")]
+///     busy: bool,
+///     #[packed_field(bits="4:7")]
+///     pending: Integer<u8, packed_bits::Bits4>
+/// }
+///
+/// static STATUS: AtomicPacked<Status> = AtomicPacked::from_atomic(AtomicU8::new(0));
+///
+/// fn main() -> Result<(), PackingError> {
+///     STATUS.set_pending(3.into(), Ordering::SeqCst)?;
+///     STATUS.set_busy(true, Ordering::SeqCst)?;
+///     let previous = STATUS.update_pending(Ordering::SeqCst, Ordering::SeqCst, |p| (*p + 1).into())?;
+///     assert_eq!(3, *previous);
+///     assert_eq!(Status { busy: true, pending: 4.into() }, STATUS.load(Ordering::SeqCst)?);
+///
+///     STATUS.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |s| {
+///         s.busy = false;
+///         s.pending = 0.into();
+///     })?;
+///     assert_eq!(Status { busy: false, pending: 0.into() }, STATUS.load(Ordering::SeqCst)?);
+///     Ok(())
+/// }
+/// ```
+pub struct AtomicPacked<T>
+    where T: PackedStruct, T::ByteArray: AtomicByteArray
+{
+    storage: AtomicStorageOf<T>,
+    packed: PhantomData<T>,
+}
+
+impl<T> AtomicPacked<T>
+    where T: PackedStruct, T::ByteArray: AtomicByteArray
+{
+    /// Wrap an atomic integer that already holds the packed bytes, for instance in a `static`.
+    pub const fn from_atomic(storage: AtomicStorageOf<T>) -> Self {
+        AtomicPacked {
+            storage,
+            packed: PhantomData,
+        }
+    }
+
+    /// Store the packed structure.
+    pub fn new(value: &T) -> PackingResult<Self> {
+        Ok(Self::from_bytes(&value.pack()?))
+    }
+
+    /// Store the raw bytes of a packed structure.
+    pub fn from_bytes(bytes: &T::ByteArray) -> Self {
+        Self::from_atomic(AtomicStorage::new(bytes.to_atomic_value()))
+    }
+
+    /// The underlying atomic integer.
+    pub fn as_atomic(&self) -> &AtomicStorageOf<T> {
+        &self.storage
+    }
+
+    /// Load the raw bytes of the structure.
+    pub fn load_bytes(&self, order: Ordering) -> T::ByteArray {
+        T::ByteArray::from_atomic_value(self.storage.load(order))
+    }
+
+    /// Load and unpack the structure.
+    pub fn load(&self, order: Ordering) -> PackingResult<T> {
+        T::unpack(&self.load_bytes(order))
+    }
+
+    /// Pack and store the structure.
+    pub fn store(&self, value: &T, order: Ordering) -> PackingResult<()> {
+        self.storage.store(value.pack()?.to_atomic_value(), order);
+        Ok(())
+    }
+
+    /// Store the structure, returning the previous one.
+    pub fn swap(&self, value: &T, order: Ordering) -> PackingResult<T> {
+        let previous = self.storage.swap(value.pack()?.to_atomic_value(), order);
+        T::unpack(&T::ByteArray::from_atomic_value(previous))
+    }
+
+    /// Modify the structure with a compare-exchange loop. The closure might be
+    /// called multiple times if other writers interfere. Returns the previous value.
+    pub fn fetch_update<F>(&self, set_order: Ordering, fetch_order: Ordering, mut f: F) -> PackingResult<T>
+        where F: FnMut(&mut T)
+    {
+        let mut current = self.storage.load(fetch_order);
+        loop {
+            let mut value = T::unpack(&T::ByteArray::from_atomic_value(current))?;
+            f(&mut value);
+            let new = value.pack()?.to_atomic_value();
+
+            match self.storage.compare_exchange_weak(current, new, set_order, fetch_order) {
+                Ok(previous) => return T::unpack(&T::ByteArray::from_atomic_value(previous)),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Replace the bits selected by the mask with the ones returned by the
+    /// closure, which gets the current bytes. The other bits keep their
+    /// concurrently written values. Returns the previous bytes. Used by the
+    /// generated per-field methods.
+    pub fn fetch_update_bits<F>(&self, mask: &T::ByteArray, set_order: Ordering, fetch_order: Ordering, mut f: F) -> PackingResult<T::ByteArray>
+        where F: FnMut(&T::ByteArray) -> PackingResult<T::ByteArray>
+    {
+        let mask = mask.to_atomic_value();
+        let mut current = self.storage.load(fetch_order);
+        loop {
+            let bits = f(&T::ByteArray::from_atomic_value(current))?.to_atomic_value();
+            let new = (current & !mask) | (bits & mask);
+
+            match self.storage.compare_exchange_weak(current, new, set_order, fetch_order) {
+                Ok(previous) => return Ok(T::ByteArray::from_atomic_value(previous)),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Replace the bits selected by the mask. Returns the previous bytes.
+    pub fn store_bits(&self, mask: &T::ByteArray, bits: &T::ByteArray, order: Ordering) -> T::ByteArray {
+        let (mask, bits) = (mask.to_atomic_value(), bits.to_atomic_value());
+        let fetch_order = load_ordering(order);
+        let mut current = self.storage.load(fetch_order);
+        loop {
+            let new = (current & !mask) | (bits & mask);
+
+            match self.storage.compare_exchange_weak(current, new, order, fetch_order) {
+                Ok(previous) => return T::ByteArray::from_atomic_value(previous),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+/// The strongest ordering that is valid for the load of a compare-exchange.
+fn load_ordering(order: Ordering) -> Ordering {
+    match order {
+        Ordering::Release => Ordering::Relaxed,
+        Ordering::AcqRel => Ordering::Acquire,
+        o => o,
+    }
+}
+
+impl<T> Debug for AtomicPacked<T>
+    where T: PackedStruct + Debug, T::ByteArray: AtomicByteArray
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.load(Ordering::SeqCst) {
+            Ok(value) => f.debug_tuple("AtomicPacked").field(&value).finish(),
+            Err(e) => f.debug_tuple("AtomicPacked").field(&e).finish(),
+        }
+    }
+}
+
+impl<T> Default for AtomicPacked<T>
+    where T: PackedStruct, T::ByteArray: AtomicByteArray
+{
+    /// All the bits are zero.
+    fn default() -> Self {
+        Self::from_bytes(&<T::ByteArray as ByteArray>::new(0))
+    }
+}
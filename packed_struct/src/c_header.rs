@@ -0,0 +1,373 @@
+//! C headers with the layout of packed structures, for firmware written in C
+//! that shares a wire format with the Rust code.
+//!
+//! The header has `#define`s with the byte offset, byte count and width of
+//! every field, `static inline` get and set accessors that work on the packed
+//! `uint8_t` array, and a C enum for every primitive enum field. Fields that
+//! fit in 64 bits and cover one run of bits in big-endian or little-endian
+//! byte order also get a mask and a shift. Apply them to that field's bytes,
+//! read as an integer with the same byte order. Other fields, like
+//! little-endian fields of LSB0 structures whose width isn't a multiple of 8,
+//! are accessed bit by bit.
+//!
+//! Nested structures and fields wider than 64 bits are read and written as
+//! byte arrays. The value's bits are right-aligned and the most significant
+//! byte comes first, so the header of a nested structure works on them as
+//! is. Reserved fields have no accessors.
+//!
+//! Names are the structure's name in snake case, followed by the field's
+//! name. Macros and enum constants are in upper case. Array elements like
+//! `data[1]` become `data_1`.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::layout::PackedStructLayout;
+//!
+//! #[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+//! pub enum Mode {
+//!     Idle = 0,
+//!     Active = 2
+//! }
+//!
+//! #[derive(PackedStruct)]
+//! #[packed_struct(bit_numbering="msb0", size_bytes="3", endian="msb")]
+//! pub struct MotorControl {
+//!     #[packed_field(bits="0:1", ty="enum")]
+//!     mode: Mode,
+//!     /// Target speed, in rpm
+//!     #[packed_field(bits="4:15")]
+//!     speed: Integer<u16, packed_bits::Bits12>,
+//!     #[packed_field(bytes="2")]
+//!     torque: i8
+//! }
+//!
+//! let header = MotorControl::c_header();
+//! assert!(header.contains("#define MOTOR_CONTROL_SPEED_MASK 0xFFFu\n"));
+//! assert!(header.contains("static inline uint16_t motor_control_get_speed(const uint8_t buf[MOTOR_CONTROL_SIZE])"));
+//! assert!(header.contains("    MOTOR_CONTROL_MODE_ACTIVE = 2\n} motor_control_mode_t;"));
+//! ```
+
+use crate::internal_prelude::v1::*;
+use crate::export::{is_signed, FieldPlacement, SnakeCase};
+use crate::layout::{FieldInfo, StructLayout};
+
+impl StructLayout {
+    /// Write a C header with the structure's defines, accessors and enums.
+    /// See the [module documentation](crate::c_header).
+    pub fn write_c_header<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        let upper = SnakeCase(self.name, true);
+        writeln!(w, "/* The layout of the {} packed structure. Generated, don't edit. */", self.name)?;
+        writeln!(w, "#ifndef {}_H", upper)?;
+        writeln!(w, "#define {}_H", upper)?;
+        writeln!(w)?;
+        writeln!(w, "#include <stdbool.h>")?;
+        writeln!(w, "#include <stdint.h>")?;
+        writeln!(w)?;
+        writeln!(w, "#define {}_SIZE {}", upper, self.packed_bytes)?;
+
+        for field in self.fields {
+            writeln!(w)?;
+            self.write_c_field(w, field)?;
+        }
+
+        writeln!(w)?;
+        writeln!(w, "#endif /* {}_H */", upper)
+    }
+
+    fn write_c_field<W: fmt::Write + ?Sized>(&self, w: &mut W, field: &FieldInfo) -> fmt::Result {
+        let prefix = CField(self.name, field.name, true);
+        let value = CValue::of(self, field);
+        let placement = FieldPlacement::of(|| field.bit_map(self.bit_numbering), field.width);
+
+        if let CValue::Enum { signed } = value {
+            writeln!(w, "typedef enum {{")?;
+            for (i, variant) in field.variants.iter().enumerate() {
+                let separator = if i + 1 < field.variants.len() { "," } else { "" };
+                writeln!(w, "    {}_{} = {}{}", prefix, SnakeCase(variant.name, true), variant.value, separator)?;
+            }
+            writeln!(w, "}} {};", value.type_name(self, field, signed))?;
+            writeln!(w)?;
+        }
+
+        write_c_comment(w, field.doc)?;
+        if let Some(nested) = self.nested(field.name) {
+            writeln!(w, "/* The packed bytes of {}. */", nested.name)?;
+        }
+        writeln!(w, "#define {}_BYTE_OFFSET {}", prefix, placement.offset)?;
+        writeln!(w, "#define {}_BYTES {}", prefix, placement.bytes)?;
+        writeln!(w, "#define {}_WIDTH {}", prefix, field.width)?;
+        if let FieldPlacement { window: Some((little_endian, shift)), .. } = placement {
+            let mask = (((1u128 << field.width) - 1) << shift) as u64;
+            let suffix = if placement.bytes > 4 { "ull" } else { "u" };
+            if little_endian {
+                writeln!(w, "/* The mask and shift apply to the little-endian bytes. */")?;
+            }
+            writeln!(w, "#define {}_SHIFT {}", prefix, shift)?;
+            writeln!(w, "#define {}_MASK 0x{:X}{}", prefix, mask, suffix)?;
+        }
+
+        if field.reserved.is_some() {
+            return Ok(());
+        }
+
+        writeln!(w)?;
+        match value {
+            CValue::Bytes => self.write_c_byte_accessors(w, field, &placement),
+            _ => self.write_c_accessors(w, field, &placement, value)
+        }
+    }
+
+    fn write_c_accessors<W: fmt::Write + ?Sized>(&self, w: &mut W, field: &FieldInfo, placement: &FieldPlacement, value: CValue) -> fmt::Result {
+        let (upper, lower) = (SnakeCase(self.name, true), SnakeCase(self.name, false));
+        let (prefix, name) = (CField(self.name, field.name, true), SnakeCase(field.name, false));
+        let ty = value.type_name(self, field, value.signed(field));
+
+        writeln!(w, "static inline {} {}_get_{}(const uint8_t buf[{}_SIZE]) {{", ty, lower, name, upper)?;
+        match placement.window {
+            Some(_) => {
+                let window = window_type(placement.bytes);
+                writeln!(w, "    {} w = {};", window, WindowLoad(placement))?;
+                writeln!(w, "    {} raw = ({})((w & {}_MASK) >> {}_SHIFT);", window, window, prefix, prefix)?;
+            },
+            None => {
+                self.write_c_bit_table(w, field)?;
+                writeln!(w, "    uint64_t raw = 0;")?;
+                writeln!(w, "    for (unsigned i = 0; i < sizeof bits / sizeof bits[0]; i++) {{")?;
+                writeln!(w, "        raw |= (uint64_t)((buf[bits[i][1] / 8] >> (7 - bits[i][1] % 8)) & 1u) << bits[i][0];")?;
+                writeln!(w, "    }}")?;
+            }
+        }
+        match value {
+            CValue::Bool => writeln!(w, "    return raw != 0;")?,
+            _ if value.signed(field) && field.width < 64 => {
+                let sign = 1u64 << (field.width - 1);
+                writeln!(w, "    return ({})((int64_t)(raw ^ 0x{:X}ull) - (int64_t)0x{:X}ull);", ty, sign, sign)?
+            },
+            _ => writeln!(w, "    return ({})raw;", ty)?
+        }
+        writeln!(w, "}}")?;
+        writeln!(w)?;
+
+        writeln!(w, "static inline void {}_set_{}(uint8_t buf[{}_SIZE], {} value) {{", lower, name, upper, ty)?;
+        match *placement {
+            FieldPlacement { window: Some(_), bytes, .. } => {
+                let window = window_type(bytes);
+                writeln!(w, "    {} w = {};", window, WindowLoad(placement))?;
+                writeln!(w, "    w = ({})((w & ({})~{}_MASK) | ((({})value << {}_SHIFT) & {}_MASK));", window, window, prefix, window, prefix, prefix)?;
+                for i in 0..bytes {
+                    let shift = if placement.little_endian() { i * 8 } else { (bytes - 1 - i) * 8 };
+                    match shift {
+                        0 => writeln!(w, "    buf[{}] = (uint8_t)w;", placement.offset + i)?,
+                        _ => writeln!(w, "    buf[{}] = (uint8_t)(w >> {});", placement.offset + i, shift)?
+                    }
+                }
+            },
+            _ => {
+                self.write_c_bit_table(w, field)?;
+                writeln!(w, "    uint64_t raw = (uint64_t)value;")?;
+                writeln!(w, "    for (unsigned i = 0; i < sizeof bits / sizeof bits[0]; i++) {{")?;
+                writeln!(w, "        uint8_t bit = (uint8_t)(0x80u >> (bits[i][1] % 8));")?;
+                writeln!(w, "        if ((raw >> bits[i][0]) & 1u) {{")?;
+                writeln!(w, "            buf[bits[i][1] / 8] |= bit;")?;
+                writeln!(w, "        }} else {{")?;
+                writeln!(w, "            buf[bits[i][1] / 8] &= (uint8_t)~bit;")?;
+                writeln!(w, "        }}")?;
+                writeln!(w, "    }}")?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    fn write_c_byte_accessors<W: fmt::Write + ?Sized>(&self, w: &mut W, field: &FieldInfo, placement: &FieldPlacement) -> fmt::Result {
+        let (upper, lower) = (SnakeCase(self.name, true), SnakeCase(self.name, false));
+        let name = SnakeCase(field.name, false);
+        let num_bytes = field.width.div_ceil(8);
+        let aligned = placement.window == Some((false, 0)) && field.width.is_multiple_of(8);
+
+        writeln!(w, "static inline void {}_get_{}(const uint8_t buf[{}_SIZE], uint8_t value[{}]) {{", lower, name, upper, num_bytes)?;
+        if aligned {
+            writeln!(w, "    for (unsigned i = 0; i < {}; i++) {{", num_bytes)?;
+            writeln!(w, "        value[i] = buf[{} + i];", placement.offset)?;
+            writeln!(w, "    }}")?;
+        } else {
+            self.write_c_bit_table(w, field)?;
+            writeln!(w, "    for (unsigned i = 0; i < {}; i++) {{", num_bytes)?;
+            writeln!(w, "        value[i] = 0;")?;
+            writeln!(w, "    }}")?;
+            writeln!(w, "    for (unsigned i = 0; i < sizeof bits / sizeof bits[0]; i++) {{")?;
+            writeln!(w, "        if ((buf[bits[i][1] / 8] >> (7 - bits[i][1] % 8)) & 1u) {{")?;
+            writeln!(w, "            value[{} - bits[i][0] / 8] |= (uint8_t)(1u << (bits[i][0] % 8));", num_bytes - 1)?;
+            writeln!(w, "        }}")?;
+            writeln!(w, "    }}")?;
+        }
+        writeln!(w, "}}")?;
+        writeln!(w)?;
+
+        writeln!(w, "static inline void {}_set_{}(uint8_t buf[{}_SIZE], const uint8_t value[{}]) {{", lower, name, upper, num_bytes)?;
+        if aligned {
+            writeln!(w, "    for (unsigned i = 0; i < {}; i++) {{", num_bytes)?;
+            writeln!(w, "        buf[{} + i] = value[i];", placement.offset)?;
+            writeln!(w, "    }}")?;
+        } else {
+            self.write_c_bit_table(w, field)?;
+            writeln!(w, "    for (unsigned i = 0; i < sizeof bits / sizeof bits[0]; i++) {{")?;
+            writeln!(w, "        uint8_t bit = (uint8_t)(0x80u >> (bits[i][1] % 8));")?;
+            writeln!(w, "        if ((value[{} - bits[i][0] / 8] >> (bits[i][0] % 8)) & 1u) {{", num_bytes - 1)?;
+            writeln!(w, "            buf[bits[i][1] / 8] |= bit;")?;
+            writeln!(w, "        }} else {{")?;
+            writeln!(w, "            buf[bits[i][1] / 8] &= (uint8_t)~bit;")?;
+            writeln!(w, "        }}")?;
+            writeln!(w, "    }}")?;
+        }
+        writeln!(w, "}}")
+    }
+
+    /// The `(value bit, MSB0 position)` pairs of a field accessed bit by bit.
+    fn write_c_bit_table<W: fmt::Write + ?Sized>(&self, w: &mut W, field: &FieldInfo) -> fmt::Result {
+        let count = field.bit_map(self.bit_numbering).count();
+        write!(w, "    static const uint16_t bits[{}][2] = {{", count)?;
+        for (i, (value_bit, position)) in field.bit_map(self.bit_numbering).enumerate() {
+            if i > 0 {
+                w.write_str(", ")?;
+            }
+            write!(w, "{{{}, {}}}", value_bit, position)?;
+        }
+        writeln!(w, "}};")
+    }
+}
+
+/// Loads the bytes of a field's window into an integer.
+struct WindowLoad<'a>(&'a FieldPlacement);
+
+impl<'a> Display for WindowLoad<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let FieldPlacement { offset, bytes, .. } = *self.0;
+        if bytes == 1 {
+            return write!(f, "buf[{}]", offset);
+        }
+
+        let window = window_type(bytes);
+        write!(f, "({})(", window)?;
+        for i in 0..bytes {
+            if i > 0 {
+                f.write_str(" | ")?;
+            }
+            let shift = if self.0.little_endian() { i * 8 } else { (bytes - 1 - i) * 8 };
+            match shift {
+                0 => write!(f, "({})buf[{}]", window, offset + i)?,
+                _ => write!(f, "({})buf[{}] << {}", window, offset + i, shift)?
+            }
+        }
+        f.write_char(')')
+    }
+}
+
+/// The C representation of a field's value.
+#[derive(Copy, Clone)]
+enum CValue {
+    Bool,
+    Integer,
+    Enum { signed: bool },
+    Bytes
+}
+
+impl CValue {
+    fn of(layout: &StructLayout, field: &FieldInfo) -> Self {
+        if layout.nested(field.name).is_some() || field.width > 64 {
+            CValue::Bytes
+        } else if !field.variants.is_empty() {
+            CValue::Enum { signed: field.variants.iter().any(|v| v.value < 0) }
+        } else if field.type_name == "bool" {
+            CValue::Bool
+        } else {
+            CValue::Integer
+        }
+    }
+
+    fn signed(&self, field: &FieldInfo) -> bool {
+        match *self {
+            CValue::Enum { signed } => signed,
+            CValue::Integer => is_signed(field),
+            _ => false
+        }
+    }
+
+    fn type_name<'a>(&self, layout: &'a StructLayout, field: &'a FieldInfo, signed: bool) -> CType<'a> {
+        match *self {
+            CValue::Bool => CType::Bool,
+            CValue::Enum { .. } => CType::Enum(CField(layout.name, field.name, false)),
+            _ => CType::Integer { signed, width: field.width }
+        }
+    }
+}
+
+enum CType<'a> {
+    Bool,
+    Integer { signed: bool, width: usize },
+    Enum(CField<'a>)
+}
+
+impl<'a> Display for CType<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CType::Bool => f.write_str("bool"),
+            CType::Integer { signed, width } => {
+                let bits = match width {
+                    0..=8 => 8,
+                    9..=16 => 16,
+                    17..=32 => 32,
+                    _ => 64
+                };
+                write!(f, "{}int{}_t", if *signed { "" } else { "u" }, bits)
+            },
+            CType::Enum(name) => write!(f, "{}_t", name)
+        }
+    }
+}
+
+fn window_type(bytes: usize) -> CType<'static> {
+    CType::Integer { signed: false, width: bytes * 8 }
+}
+
+/// The C name of a structure's field, prefixed with the structure's name.
+#[derive(Copy, Clone)]
+struct CField<'a>(&'a str, &'a str, bool);
+
+impl<'a> Display for CField<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}_{}", SnakeCase(self.0, self.2), SnakeCase(self.1, self.2))
+    }
+}
+
+fn write_c_comment<W: fmt::Write + ?Sized>(w: &mut W, doc: &str) -> fmt::Result {
+    let line = |w: &mut W, text: &str| -> fmt::Result {
+        for (i, part) in text.split("*/").enumerate() {
+            if i > 0 {
+                w.write_str("* /")?;
+            }
+            w.write_str(part)?;
+        }
+        Ok(())
+    };
+
+    match doc.lines().count() {
+        0 => Ok(()),
+        1 => {
+            w.write_str("/* ")?;
+            line(w, doc)?;
+            writeln!(w, " */")
+        },
+        _ => {
+            writeln!(w, "/*")?;
+            for text in doc.lines() {
+                w.write_str(" *")?;
+                if !text.is_empty() {
+                    w.write_char(' ')?;
+                    line(w, text)?;
+                }
+                writeln!(w)?;
+            }
+            writeln!(w, " */")
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! CSV export and import of packed structures, one column per field.
+//!
+//! Array elements get their own columns, named like `data[1]`. Integers,
+//! booleans and primitive enums are written as their integer values, other
+//! fields, like nested structures, as the hex value of their raw bits. The
+//! reader accepts the same values, as well as `true` and `false`, `0b` binary
+//! and negative numbers, and rejects values out of the range of the field's
+//! type. Columns missing from the input keep the bits of `T::default()`, which
+//! makes it easy to write test stimuli by hand.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::csv::{PackedCsvReader, PackedCsvWriter};
+//!
+//! #[derive(PackedStruct, Debug, Default, PartialEq)]
+//! #[packed_struct(bit_numbering="msb0", endian="msb")]
+//! pub struct Sample {
+//!     #[packed_field(bits="0")]
+//!     valid: bool,
+//!     #[packed_field(bits="4:7")]
+//!     channel: Integer<u8, packed_bits::Bits4>,
+//!     #[packed_field(bytes="1:2")]
+//!     value: i16
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut writer = PackedCsvWriter::new(String::new());
+//! writer.write_record(&Sample { valid: true, channel: 3.into(), value: -5 })?;
+//! assert_eq!("valid,channel,value\r\n1,3,-5\r\n", writer.into_inner());
+//!
+//! let samples: Vec<Sample> = PackedCsvReader::new("value,channel\n1000,2\n").collect::<Result<_, _>>()?;
+//! assert_eq!(vec![Sample { valid: false, channel: 2.into(), value: 1000 }], samples);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::internal_prelude::v1::*;
+use crate::debug_fmt::{DebugRawBits, DisplayRadix};
+use crate::packing::{PackedStruct, PackingError, PackingResult};
+use crate::types_bits::ByteArray;
+use crate::visit::{bit_positions, IntegerEndianness, IntegerValue, LsbFrame, PackedStructVisit, VisitContext, VisitField, Visitor};
+
+/// Writes packed structures as CSV records, with a header row before the first record.
+pub struct PackedCsvWriter<W, T> {
+    w: W,
+    delimiter: char,
+    line_ending: &'static str,
+    header_written: bool,
+    records: PhantomData<T>
+}
+
+impl<W: fmt::Write, T: PackedStructVisit> PackedCsvWriter<W, T> {
+    pub fn new(w: W) -> Self {
+        PackedCsvWriter {
+            w,
+            delimiter: ',',
+            line_ending: "\r\n",
+            header_written: false,
+            records: PhantomData
+        }
+    }
+
+    /// The column delimiter, `,` by default.
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// The terminator of every row, `\r\n` by default.
+    pub fn with_line_ending(mut self, line_ending: &'static str) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Write a row with the record's fields.
+    pub fn write_record(&mut self, record: &T) -> Result<(), CsvWriteError> {
+        let packed = record.pack().map_err(CsvWriteError::Packing)?;
+        let ctx = VisitContext::new(packed.as_bytes_slice());
+
+        if !self.header_written {
+            record.visit_fields(&ctx, None, &mut CsvRow { w: &mut self.w, delimiter: self.delimiter, header: true, first: true })?;
+            self.w.write_str(self.line_ending)?;
+            self.header_written = true;
+        }
+
+        record.visit_fields(&ctx, None, &mut CsvRow { w: &mut self.w, delimiter: self.delimiter, header: false, first: true })?;
+        self.w.write_str(self.line_ending)?;
+        Ok(())
+    }
+
+    /// Write a row for every record.
+    pub fn write_records<'r, I>(&mut self, records: I) -> Result<(), CsvWriteError> where I: IntoIterator<Item = &'r T>, T: 'r {
+        for record in records {
+            self.write_record(record)?;
+        }
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+}
+
+/// The failure of `PackedCsvWriter::write_record`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CsvWriteError {
+    /// The record couldn't be packed.
+    Packing(PackingError),
+    /// The underlying writer failed.
+    Write(fmt::Error)
+}
+
+impl From<fmt::Error> for CsvWriteError {
+    fn from(e: fmt::Error) -> Self {
+        CsvWriteError::Write(e)
+    }
+}
+
+impl Display for CsvWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CsvWriteError::Packing(e) => write!(f, "Failed to pack the record: {}", e),
+            CsvWriteError::Write(e) => write!(f, "Failed to write the record: {}", e)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for CsvWriteError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            CsvWriteError::Packing(e) => Some(e),
+            CsvWriteError::Write(e) => Some(e)
+        }
+    }
+}
+
+/// Writes the names or values of the outermost fields.
+struct CsvRow<'w, W> {
+    w: &'w mut W,
+    delimiter: char,
+    header: bool,
+    first: bool
+}
+
+impl<'w, W: fmt::Write> Visitor for CsvRow<'w, W> {
+    type Error = fmt::Error;
+
+    fn visit_field(&mut self, field: &VisitField) -> fmt::Result {
+        if field.depth > 0 {
+            return Ok(());
+        }
+
+        if !self.first {
+            self.w.write_char(self.delimiter)?;
+        }
+        self.first = false;
+
+        match (self.header, field.integer) {
+            (true, _) => self.w.write_str(field.name),
+            (false, Some(integer)) => write!(self.w, "{}", integer),
+            (false, None) => write!(self.w, "{}", DebugRawBits { bits: &field.bits, lsb_frame: field.lsb_frame.as_ref(), slice: field.packed, radix: DisplayRadix::Hex })
+        }
+    }
+}
+
+/// A column of the reader, as found on `T::default()`.
+struct CsvColumn {
+    name: &'static str,
+    bits: Range<usize>,
+    lsb_frame: Option<LsbFrame>,
+    endianness: Option<IntegerEndianness>,
+    integer: bool,
+    /// Signed integers, or primitive enums with a signed primitive.
+    signed: bool
+}
+
+/// The columns of the outermost fields.
+struct CsvLayout(Vec<CsvColumn>);
+
+impl Visitor for CsvLayout {
+    type Error = core::convert::Infallible;
+
+    fn visit_field(&mut self, field: &VisitField) -> Result<(), Self::Error> {
+        if field.depth == 0 {
+            self.0.push(CsvColumn {
+                name: field.name,
+                bits: field.bits.clone(),
+                lsb_frame: field.lsb_frame.clone(),
+                endianness: field.endianness,
+                integer: field.integer.is_some(),
+                signed: matches!(field.integer, Some(IntegerValue::Signed(_)))
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reads packed structures from CSV text. The first line is the header, with
+/// the field names in any order. Empty lines are skipped.
+pub struct PackedCsvReader<'a, T: PackedStruct> {
+    lines: core::str::Lines<'a>,
+    delimiter: char,
+    /// The field of every column of the input.
+    columns: Option<Vec<usize>>,
+    layout: Vec<CsvColumn>,
+    defaults: PackingResult<T::ByteArray>
+}
+
+impl<'a, T: PackedStructVisit + Default> PackedCsvReader<'a, T> where T::ByteArray: Copy {
+    pub fn new(csv: &'a str) -> Self {
+        let defaults = T::default();
+        let mut layout = CsvLayout(vec![]);
+        let packed = defaults.pack();
+        if let Ok(ref packed) = packed {
+            match defaults.visit_fields(&VisitContext::new(packed.as_bytes_slice()), None, &mut layout) {
+                Ok(()) => (),
+                Err(e) => match e {}
+            }
+        }
+
+        PackedCsvReader {
+            lines: csv.lines(),
+            delimiter: ',',
+            columns: None,
+            layout: layout.0,
+            defaults: packed
+        }
+    }
+
+    /// The column delimiter, `,` by default.
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    fn cells(&self, line: &'a str) -> impl Iterator<Item = &'a str> {
+        line.split(self.delimiter).map(|c| c.trim().trim_matches('"').trim())
+    }
+
+    fn read_header(&self, line: &'a str) -> PackingResult<Vec<usize>> {
+        self.cells(line).map(|name| {
+            self.layout.iter().position(|c| c.name == name).ok_or(PackingError::InvalidValue)
+        }).collect()
+    }
+
+    fn read_record(&self, columns: &[usize], line: &'a str) -> PackingResult<T> {
+        let mut packed = self.defaults?;
+        let mut num_cells = 0;
+        for cell in self.cells(line) {
+            if let Some(column) = columns.get(num_cells) {
+                set_cell(packed.as_mut_bytes_slice(), &self.layout[*column], cell)?;
+            }
+            num_cells += 1;
+        }
+
+        if num_cells != columns.len() {
+            return Err(PackingError::BufferSizeMismatch { expected: columns.len(), actual: num_cells });
+        }
+
+        T::unpack(&packed)
+    }
+}
+
+impl<'a, T: PackedStructVisit + Default> Iterator for PackedCsvReader<'a, T> where T::ByteArray: Copy {
+    type Item = PackingResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let columns = match self.columns.take() {
+                Some(columns) => columns,
+                None => {
+                    match self.read_header(line) {
+                        Ok(columns) => self.columns = Some(columns),
+                        Err(e) => return Some(Err(e))
+                    }
+                    continue;
+                }
+            };
+
+            let record = self.read_record(&columns, line);
+            self.columns = Some(columns);
+            return Some(record);
+        }
+    }
+}
+
+/// Parse the cell and store its raw bits in the column's bits.
+fn set_cell(packed: &mut [u8], column: &CsvColumn, cell: &str) -> PackingResult<()> {
+    // the bits from the least significant one
+    let positions: Vec<usize> = bit_positions(&column.bits, column.lsb_frame.as_ref()).collect();
+    let mut positions = positions.into_iter().rev();
+    let num_bits = positions.len();
+    let cell = cell.replace('_', "");
+
+    if num_bits > 128 {
+        // only raw hex, aligned to the least significant bit
+        let digits = cell.strip_prefix("0x").or_else(|| cell.strip_prefix("0X")).ok_or(PackingError::InvalidValue)?;
+        for c in digits.chars().rev() {
+            let digit = c.to_digit(16).ok_or(PackingError::InvalidValue)?;
+            for b in 0..4 {
+                let bit = (digit >> b) & 1 == 1;
+                match positions.next() {
+                    Some(index) => set_bit(packed, index, bit),
+                    None if bit => return Err(PackingError::InvalidValue),
+                    None => ()
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    let mut raw = match parse_integer(&cell)? {
+        IntegerValue::Unsigned(v) if column.signed && v >> (num_bits - 1) == 0 => v,
+        IntegerValue::Unsigned(v) if !column.signed && (num_bits == 128 || v >> num_bits == 0) => v,
+        IntegerValue::Signed(v) if column.signed && (num_bits == 128 || v >= -(1i128 << (num_bits - 1))) => (v as u128) & mask(num_bits),
+        _ => return Err(PackingError::InvalidValue)
+    };
+
+    // little-endian fields of sawtooth-numbered structures are in order in their frame
+    if column.integer && column.endianness == Some(IntegerEndianness::Lsb) && column.lsb_frame.is_none() {
+        let num_bytes = num_bits.div_ceil(8);
+        raw = (raw.swap_bytes() >> (128 - num_bytes * 8)) & mask(num_bits);
+    }
+
+    for (i, index) in positions.enumerate() {
+        set_bit(packed, index, (raw >> i) & 1 == 1);
+    }
+    Ok(())
+}
+
+fn parse_integer(cell: &str) -> PackingResult<IntegerValue> {
+    let (digits, radix) = match cell {
+        "true" => return Ok(IntegerValue::Unsigned(1)),
+        "false" => return Ok(IntegerValue::Unsigned(0)),
+        _ if cell.starts_with("0x") || cell.starts_with("0X") => (&cell[2..], 16),
+        _ if cell.starts_with("0b") || cell.starts_with("0B") => (&cell[2..], 2),
+        _ => (cell, 10)
+    };
+
+    if digits.starts_with('-') && radix == 10 {
+        i128::from_str_radix(digits, radix).map(IntegerValue::Signed).map_err(|_| PackingError::InvalidValue)
+    } else {
+        u128::from_str_radix(digits, radix).map(IntegerValue::Unsigned).map_err(|_| PackingError::InvalidValue)
+    }
+}
+
+fn mask(num_bits: usize) -> u128 {
+    if num_bits >= 128 { u128::MAX } else { (1 << num_bits) - 1 }
+}
+
+fn set_bit(packed: &mut [u8], index: usize, bit: bool) {
+    let mask = 1 << (7 - (index % 8));
+    if bit {
+        packed[index / 8] |= mask;
+    } else {
+        packed[index / 8] &= !mask;
+    }
+}
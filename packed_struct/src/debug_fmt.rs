@@ -1,11 +1,26 @@
 //! Helper structures for runtime packing visualization.
+//!
+//! `PackedStructFmt` and `write_fields` only need `core::fmt` and don't allocate,
+//! so they can be used to log decoded structures on bare-metal targets. The
+//! `Display` implementations and diffs require `alloc` or `std`.
 
 use crate::internal_prelude::v1::*;
+use crate::FieldAccess;
+#[cfg(any(feature="alloc", feature="std"))]
+use crate::packing::PackedStruct;
+use crate::types_bits::ByteArray;
+use crate::visit::{bit_positions, LsbFrame, PackedStructVisit, VisitContext, VisitField, Visitor};
 
 #[cfg(any(feature="alloc", feature="std"))]
 pub trait PackedStructDebug {
-    fn fmt_fields(&self, fmt: &mut Formatter) -> Result<(), FmtError>;
+    fn fmt_fields(&self, fmt: &mut fmt::Formatter) -> Result<(), FmtError>;
     fn packed_struct_display_header() -> &'static str;
+    /// The fields of this value, with their bits and formatted values. Empty
+    /// by default, the field table is then written with `fmt_fields` and the
+    /// compact format shows the packed bytes in hex.
+    fn debug_fields(&self) -> Vec<DebugBitField<'static>> {
+        Vec::new()
+    }
 }
 
 pub struct DebugBinaryByteSlice<'a> {
@@ -14,7 +29,7 @@ pub struct DebugBinaryByteSlice<'a> {
 }
 
 impl<'a> fmt::Binary for DebugBinaryByteSlice<'a> {
-    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         for i in self.bits.start..(self.bits.end + 1) {
             let byte = i / 8;
             let bit = i % 8;
@@ -31,63 +46,751 @@ impl<'a> fmt::Binary for DebugBinaryByteSlice<'a> {
     }
 }
 
+/// A field of the runtime formatter. Built with `new` and the `with_*`
+/// methods, new metadata can be added without breaking manual implementations
+/// of `PackedStructDebug`.
+#[cfg(any(feature="alloc", feature="std"))]
+#[non_exhaustive]
 pub struct DebugBitField<'a> { 
 	pub name: Cow<'a, str>,
 	pub bits: Range<usize>,
-	pub display_value: Cow<'a, str>
+	/// The actual bits of little-endian fields of sawtooth-numbered structures.
+	pub lsb_frame: Option<LsbFrame>,
+	pub display_value: Cow<'a, str>,
+	pub access: FieldAccess,
+	/// Reserved bits, always packed as zeroes or ones.
+	pub reserved: bool,
+	/// Fields of a nested packed structure, with bits relative to the outer structure.
+	pub nested: Vec<DebugBitField<'a>>
 }
 
+#[cfg(any(feature="alloc", feature="std"))]
+impl<'a> DebugBitField<'a> {
+    /// A read-write field over the MSB0 inclusive bit range.
+    pub fn new<N: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>>(name: N, bits: Range<usize>, display_value: V) -> Self {
+        DebugBitField {
+            name: name.into(),
+            bits,
+            lsb_frame: None,
+            display_value: display_value.into(),
+            access: FieldAccess::ReadWrite,
+            reserved: false,
+            nested: vec![]
+        }
+    }
+
+    pub fn with_lsb_frame(mut self, lsb_frame: Option<LsbFrame>) -> Self {
+        self.lsb_frame = lsb_frame;
+        self
+    }
 
-pub fn packable_fmt_fields(f: &mut Formatter, packed_bytes: &[u8], fields: &[DebugBitField]) -> fmt::Result {
-    if fields.len() == 0 {
-		return Ok(());
-	}
+    pub fn with_access(mut self, access: FieldAccess) -> Self {
+        self.access = access;
+        self
+    }
 
-    let max_field_length_name = fields.iter().map(|x| x.name.len()).max().unwrap();
-	let max_bit_width = fields.iter().map(|x| x.bits.len()).max().unwrap();
+    pub fn with_reserved(mut self, reserved: bool) -> Self {
+        self.reserved = reserved;
+        self
+    }
 
-    if max_bit_width > 32 {
-        for field in fields {
-            write!(f, "{name:>0$} | {base_value:?}\r\n",
-                        max_field_length_name + 1,
-                        base_value = field.display_value,
-                        name = field.name
-                        )?;
+    pub fn with_nested(mut self, nested: Vec<DebugBitField<'a>>) -> Self {
+        self.nested = nested;
+        self
+    }
+}
+
+/// The fields of a packed structure, collected with a `Visitor`. Used by the
+/// generated `PackedStructDebug` implementation.
+#[cfg(any(feature="alloc", feature="std"))]
+pub fn collect_debug_fields<T: PackedStructVisit>(value: &T) -> Vec<DebugBitField<'static>> {
+    // the bytes aren't part of the collected fields, so a structure that can't be packed still has them
+    let packed = value.pack().unwrap_or_else(|_| T::ByteArray::new(0));
+    let mut collector = DebugFieldsCollector { levels: vec![vec![]] };
+    match value.visit_fields(&VisitContext::new(packed.as_bytes_slice()), None, &mut collector) {
+        Ok(()) => collector.levels.pop().unwrap_or_default(),
+        Err(e) => match e {}
+    }
+}
+
+/// Builds the tree of fields, with one level per nested structure that is being visited.
+#[cfg(any(feature="alloc", feature="std"))]
+struct DebugFieldsCollector {
+    levels: Vec<Vec<DebugBitField<'static>>>
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+impl Visitor for DebugFieldsCollector {
+    type Error = core::convert::Infallible;
+
+    fn visit_field(&mut self, field: &VisitField) -> Result<(), Self::Error> {
+        if let Some(level) = self.levels.last_mut() {
+            level.push(DebugBitField::new(field.name, field.bits.clone(), format!("{:?}", field.value))
+                .with_lsb_frame(field.lsb_frame.clone())
+                .with_access(field.access)
+                .with_reserved(field.reserved));
+        }
+        Ok(())
+    }
+
+    fn enter_nested(&mut self, _field: &VisitField) -> Result<(), Self::Error> {
+        self.levels.push(vec![]);
+        Ok(())
+    }
+
+    fn leave_nested(&mut self, _field: &VisitField) -> Result<(), Self::Error> {
+        let nested = self.levels.pop().unwrap_or_default();
+        if let Some(parent) = self.levels.last_mut().and_then(|l| l.last_mut()) {
+            parent.nested = nested;
+        }
+        Ok(())
+    }
+}
+
+/// Fields wider than this are shown in hex instead of binary.
+pub const MAX_BINARY_FIELD_BITS: usize = 32;
+
+/// The radix of the raw field values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DisplayRadix {
+    /// Binary, or hex for fields wider than `MAX_BINARY_FIELD_BITS`.
+    #[default]
+    Binary,
+    /// Unsigned decimal, or hex for fields wider than 128 bits.
+    Decimal,
+    Hex
+}
+
+/// The raw bits of a field, MSB0 inclusive range.
+pub struct DebugRawBits<'a> {
+    pub bits: &'a Range<usize>,
+    /// The actual bits of little-endian fields of sawtooth-numbered structures.
+    pub lsb_frame: Option<&'a LsbFrame>,
+    pub slice: &'a [u8],
+    pub radix: DisplayRadix
+}
+
+impl<'a> DebugRawBits<'a> {
+    fn num_bits(&self) -> usize {
+        self.positions().count()
+    }
+
+    fn positions(&self) -> impl Iterator<Item = usize> + 'a {
+        bit_positions(self.bits, self.lsb_frame)
+    }
+
+    fn is_hex(&self) -> bool {
+        match self.radix {
+            DisplayRadix::Binary => self.num_bits() > MAX_BINARY_FIELD_BITS,
+            DisplayRadix::Decimal => self.num_bits() > 128,
+            DisplayRadix::Hex => true
+        }
+    }
+
+    /// Number of characters that the representation occupies.
+    pub fn width(&self) -> usize {
+        if self.is_hex() {
+            2 + self.num_bits().div_ceil(4)
+        } else if self.radix == DisplayRadix::Decimal {
+            let mut value = self.value();
+            let mut digits = 1;
+            while value >= 10 {
+                value /= 10;
+                digits += 1;
+            }
+            digits
+        } else {
+            2 + self.num_bits()
+        }
+    }
+
+    fn value(&self) -> u128 {
+        self.positions().fold(0, |v, i| (v << 1) | (self.bit(i) as u128))
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        (self.slice[i / 8] & (1 << (7 - (i % 8)))) != 0
+    }
+}
+
+impl<'a> fmt::Display for DebugRawBits<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if !self.is_hex() && self.radix == DisplayRadix::Decimal {
+            return write!(fmt, "{}", self.value());
+        } else if !self.is_hex() {
+            fmt.write_str("0b")?;
+            for i in self.positions() {
+                fmt.write_str(if self.bit(i) { "1" } else { "0" })?;
+            }
+            return Ok(());
+        }
+
+        // nibbles are aligned to the field's least significant bit
+        fmt.write_str("0x")?;
+        let num_bits = self.num_bits();
+        let mut nibble = 0;
+        let mut nibble_bits = 4 - (num_bits % 4);
+        if nibble_bits == 4 {
+            nibble_bits = 0;
+        }
+        for i in self.positions() {
+            nibble = (nibble << 1) | (self.bit(i) as u8);
+            nibble_bits += 1;
+            if nibble_bits == 4 {
+                write!(fmt, "{:X}", nibble)?;
+                nibble = 0;
+                nibble_bits = 0;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A structure that can write its fields to any `core::fmt::Write` sink without
+/// allocating. Implemented for every structure with `PackedStructVisit`.
+pub trait PackedStructFmt {
+    /// Write a table of the fields, one per line, with their bits and values.
+    fn write_fields<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        self.write_fields_with(w, &FieldsFormat::default())
+    }
+
+    /// Write a table of the fields with the given options. Fields of nested
+    /// structures are prefixed with the names of their outer fields, like `header.flags`.
+    fn write_fields_with<W: fmt::Write + ?Sized>(&self, w: &mut W, format: &FieldsFormat) -> fmt::Result;
+}
+
+impl<T: PackedStructVisit> PackedStructFmt for T {
+    fn write_fields_with<W: fmt::Write + ?Sized>(&self, w: &mut W, format: &FieldsFormat) -> fmt::Result {
+        let packed = match self.pack() {
+            Ok(packed) => packed,
+            Err(e) => return write!(w, "Error while packing: {:?}{}", e, format.line_ending)
+        };
+        let ctx = VisitContext::new(packed.as_bytes_slice());
+
+        // the first pass measures the columns, the second one writes the rows
+        let mut columns = FieldColumns::new(ctx.packed, format);
+        self.visit_fields(&ctx, None, &mut columns)?;
+        self.visit_fields(&ctx, None, &mut FieldRows { columns: &columns, w })
+    }
+}
+
+impl<'a> Visitor for FieldColumns<'a> {
+    type Error = fmt::Error;
+
+    fn visit_field(&mut self, field: &VisitField) -> fmt::Result {
+        if self.format.includes(field.root().name) {
+            self.add(field.path_len(), &field.bits, field.lsb_frame.as_ref(), field.access);
+        }
+        Ok(())
+    }
+}
+
+struct FieldRows<'a, 'b, W: ?Sized> {
+    columns: &'b FieldColumns<'a>,
+    w: &'b mut W
+}
+
+impl<'a, 'b, W: fmt::Write + ?Sized> Visitor for FieldRows<'a, 'b, W> {
+    type Error = fmt::Error;
+
+    fn visit_field(&mut self, field: &VisitField) -> fmt::Result {
+        if !self.columns.format.includes(field.root().name) {
+            return Ok(());
+        }
+
+        write!(self.w, "{:1$}", "", self.columns.max_field_length_name + 1 - field.path_len())?;
+        field.write_path(self.w)?;
+        self.columns.write_columns(self.w, &field.bits, field.lsb_frame.as_ref(), field.access, field.value)
+    }
+}
+
+/// Options of the field table.
+#[derive(Debug, Copy, Clone)]
+pub struct FieldsFormat<'a> {
+    pub radix: DisplayRadix,
+    pub line_ending: &'a str,
+    /// Only show these fields. Array fields are matched by their name or by the element's name, like `data[1]`.
+    pub only_fields: Option<&'a [&'a str]>
+}
+
+impl<'a> FieldsFormat<'a> {
+    /// Should the field be shown?
+    pub fn includes(&self, name: &str) -> bool {
+        match self.only_fields {
+            Some(only) => only.iter().any(|f| *f == name || (name.starts_with(f) && name[f.len()..].starts_with('['))),
+            None => true
+        }
+    }
+}
+
+impl<'a> Default for FieldsFormat<'a> {
+    fn default() -> Self {
+        FieldsFormat {
+            radix: DisplayRadix::Binary,
+            line_ending: "\r\n",
+            only_fields: None
+        }
+    }
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+pub fn packable_fmt_fields(f: &mut fmt::Formatter, packed_bytes: &[u8], fields: &[DebugBitField]) -> fmt::Result {
+    packable_fmt_fields_with(f, packed_bytes, fields, &FieldsFormat::default())
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+pub fn packable_fmt_fields_with(f: &mut fmt::Formatter, packed_bytes: &[u8], fields: &[DebugBitField], format: &FieldsFormat) -> fmt::Result {
+    let fields: Vec<_> = fields.iter().filter(|x| format.includes(&x.name)).collect();
+    fmt_fields_table(f, packed_bytes, &fields, format)
+}
+
+/// Nested structures are expanded into sub-tables, indented by two spaces per
+/// level. The columns are measured once over all the levels, so that they line up.
+#[cfg(any(feature="alloc", feature="std"))]
+fn fmt_fields_table(f: &mut fmt::Formatter, packed_bytes: &[u8], fields: &[&DebugBitField], format: &FieldsFormat) -> fmt::Result {
+    let mut columns = FieldColumns::new(packed_bytes, format);
+    let mut names = NameColumn::default();
+    measure_fields_table(&mut columns, &mut names, fields.iter().copied(), 0);
+    write_fields_table(f, &columns, &names, fields.iter().copied(), 0)
+}
+
+/// Width of the name column of a table with sub-tables. The names of a level
+/// end two characters after those of its outer level.
+#[cfg(any(feature="alloc", feature="std"))]
+#[derive(Default)]
+struct NameColumn {
+    /// Where the names of the outermost level end.
+    end: usize,
+    max_depth: usize
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+fn measure_fields_table<'a, 'b: 'a, I>(columns: &mut FieldColumns, names: &mut NameColumn, fields: I, depth: usize)
+    where I: Iterator<Item = &'a DebugBitField<'b>>
+{
+    for field in fields {
+        columns.add(field.name.len(), &field.bits, field.lsb_frame.as_ref(), field.access);
+        names.end = max(names.end, (field.name.len() + 1).saturating_sub(depth * 2));
+        names.max_depth = max(names.max_depth, depth);
+        measure_fields_table(columns, names, field.nested.iter(), depth + 1);
+    }
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+fn write_fields_table<'a, 'b: 'a, I>(f: &mut fmt::Formatter, columns: &FieldColumns, names: &NameColumn, fields: I, depth: usize) -> fmt::Result
+    where I: Iterator<Item = &'a DebugBitField<'b>>
+{
+    for field in fields {
+        write!(f, "{:1$}", "", names.end + depth * 2 - field.name.len())?;
+        f.write_str(&field.name)?;
+        write!(f, "{:1$}", "", (names.max_depth - depth) * 2)?;
+        columns.write_columns(f, &field.bits, field.lsb_frame.as_ref(), field.access, &field.display_value)?;
+        write_fields_table(f, columns, names, field.nested.iter(), depth + 1)?;
+    }
+
+    Ok(())
+}
+
+/// All the fields on a single line, `name=raw (value)`, without a line ending.
+#[cfg(any(feature="alloc", feature="std"))]
+pub fn packable_fmt_fields_compact(f: &mut fmt::Formatter, packed_bytes: &[u8], fields: &[DebugBitField], format: &FieldsFormat) -> fmt::Result {
+    for (i, field) in fields.iter().filter(|x| format.includes(&x.name)).enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+
+        let raw = DebugRawBits {
+            bits: &field.bits,
+            lsb_frame: field.lsb_frame.as_ref(),
+            slice: packed_bytes,
+            radix: format.radix
+        };
+        write!(f, "{}={} ({})", field.name, raw, field.display_value)?;
+    }
+
+    Ok(())
+}
+
+/// Column widths of a field table.
+struct FieldColumns<'a> {
+    packed_bytes: &'a [u8],
+    format: FieldsFormat<'a>,
+    max_field_length_name: usize,
+    max_raw_width: usize,
+    show_access: bool
+}
+
+impl<'a> FieldColumns<'a> {
+    fn new(packed_bytes: &'a [u8], format: &FieldsFormat<'a>) -> Self {
+        FieldColumns {
+            packed_bytes,
+            format: *format,
+            max_field_length_name: 0,
+            max_raw_width: 0,
+            show_access: false
+        }
+    }
+
+    fn add(&mut self, name_len: usize, bits: &Range<usize>, lsb_frame: Option<&LsbFrame>, access: FieldAccess) {
+        self.max_field_length_name = max(self.max_field_length_name, name_len);
+        self.max_raw_width = max(self.max_raw_width, self.raw(bits, lsb_frame).width());
+        self.show_access |= access != FieldAccess::ReadWrite;
+    }
+
+    fn raw<'b>(&self, bits: &'b Range<usize>, lsb_frame: Option<&'b LsbFrame>) -> DebugRawBits<'b> where 'a: 'b {
+        DebugRawBits {
+            bits,
+            lsb_frame,
+            slice: self.packed_bytes,
+            radix: self.format.radix
+        }
+    }
+
+    /// The columns after the name.
+    fn write_columns<W: fmt::Write + ?Sized>(&self, w: &mut W, bits: &Range<usize>, lsb_frame: Option<&LsbFrame>, access: FieldAccess, value: &dyn Debug) -> fmt::Result {
+        if self.show_access {
+            write!(w, " | {:<3}", access)?;
+        }
+
+        let raw = self.raw(bits, lsb_frame);
+
+        write!(w, " | bits {bits_start:>3}:{bits_end:<3} | {raw}{dummy:>0spaces$} | {base_value:?}{line_ending}",
+                    base_value = value,
+                    raw = raw,
+                    dummy = "",
+                    bits_start = bits.start,
+                    bits_end = bits.end,
+                    spaces = self.max_raw_width - raw.width(),
+                    line_ending = self.format.line_ending
+                    )
+    }
+}
+
+/// Bytes per row of `fmt_hexdump`.
+pub const HEXDUMP_ROW_BYTES: usize = 16;
+
+/// Offset-annotated hexdump, 16 bytes per row, each one ended with `line_ending`.
+pub fn fmt_hexdump<W: fmt::Write + ?Sized>(f: &mut W, bytes: &[u8], line_ending: &str) -> fmt::Result {
+    fmt_byte_rows(f, bytes, HEXDUMP_ROW_BYTES, line_ending, |f, b| write!(f, "{:02X}", b))
+}
+
+fn fmt_byte_rows<W, F>(f: &mut W, bytes: &[u8], row_bytes: usize, line_ending: &str, fmt_byte: F) -> fmt::Result
+    where W: fmt::Write + ?Sized, F: Fn(&mut W, u8) -> fmt::Result
+{
+    for (row, chunk) in bytes.chunks(row_bytes).enumerate() {
+        write!(f, "{:04X}:", row * row_bytes)?;
+        for b in chunk {
+            f.write_str(" ")?;
+            fmt_byte(f, *b)?;
         }
-    } else {    
+        f.write_str(line_ending)?;
+    }
+
+    Ok(())
+}
+
+/// Foreground colors of the bit map, cycled through by the fields.
+pub const ANSI_FIELD_COLORS: [&str; 12] = [
+    "\x1b[31m", "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m",
+    "\x1b[91m", "\x1b[92m", "\x1b[93m", "\x1b[94m", "\x1b[95m", "\x1b[96m"
+];
+/// Reserved bits and bits that don't belong to any field.
+pub const ANSI_DIM: &str = "\x1b[2m";
+pub const ANSI_RESET: &str = "\x1b[0m";
+
+/// Bytes per row of the bit map.
+pub const BIT_MAP_ROW_BYTES: usize = 4;
+
+/// The packed bits as a grid, four bytes per row, with each field in its own
+/// ANSI color, followed by a legend. Fields of nested structures get their own
+/// colors, reserved bits and bits that don't belong to any field are dimmed.
+#[cfg(any(feature="alloc", feature="std"))]
+pub struct DebugBitMap<'a> {
+    pub packed_bytes: &'a [u8],
+    pub fields: &'a [DebugBitField<'a>],
+    pub line_ending: &'a str
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+impl<'a> DebugBitMap<'a> {
+    pub fn new(packed_bytes: &'a [u8], fields: &'a [DebugBitField<'a>]) -> Self {
+        DebugBitMap {
+            packed_bytes,
+            fields,
+            line_ending: "\r\n"
+        }
+    }
+
+    pub fn with_line_ending(mut self, line_ending: &'a str) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// The innermost fields, with the names of their outer fields.
+    fn leaves(fields: &'a [DebugBitField<'a>], prefix: &str, leaves: &mut Vec<(String, &'a DebugBitField<'a>)>) {
         for field in fields {
+            let name = format!("{}{}", prefix, field.name);
+            if field.nested.is_empty() {
+                leaves.push((name, field));
+            } else {
+                Self::leaves(&field.nested, &format!("{}.", name), leaves);
+            }
+        }
+    }
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+impl<'a> fmt::Display for DebugBitMap<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut leaves = vec![];
+        Self::leaves(self.fields, "", &mut leaves);
+
+        // the color of every bit, `None` for dimmed bits
+        let mut colors = vec![None; self.packed_bytes.len() * 8];
+        let mut legend = vec![];
+        let mut next_color = 0;
+        for (name, field) in &leaves {
+            let color = if field.reserved {
+                None
+            } else {
+                next_color += 1;
+                Some(ANSI_FIELD_COLORS[(next_color - 1) % ANSI_FIELD_COLORS.len()])
+            };
+            for bit in bit_positions(&field.bits, field.lsb_frame.as_ref()) {
+                colors[bit] = color;
+            }
+            legend.push((name, field, color));
+        }
+
+        for (row, bytes) in self.packed_bytes.chunks(BIT_MAP_ROW_BYTES).enumerate() {
+            write!(f, "{:04X}:", row * BIT_MAP_ROW_BYTES)?;
+            for (i, byte) in bytes.iter().enumerate() {
+                f.write_str(" ")?;
+                for bit in 0..8 {
+                    let color = colors[(row * BIT_MAP_ROW_BYTES + i) * 8 + bit].unwrap_or(ANSI_DIM);
+                    let value = (byte >> (7 - bit)) & 1;
+                    write!(f, "{}{}{}", color, value, ANSI_RESET)?;
+                }
+            }
+            f.write_str(self.line_ending)?;
+        }
+
+        let max_name = legend.iter().map(|(name, _, _)| name.len()).max().unwrap_or(0);
+        for (name, field, color) in legend {
+            write!(f, "  {color}{name:<width$}{reset} | bits {start:>3}:{end:<3}{line_ending}",
+                color = color.unwrap_or(ANSI_DIM),
+                name = name,
+                reset = ANSI_RESET,
+                width = max_name,
+                start = field.bits.start,
+                end = field.bits.end,
+                line_ending = self.line_ending
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+/// A field whose packed bits or value differ between two instances.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff<'a> {
+    pub name: Cow<'a, str>,
+    pub bits: Range<usize>,
+    /// The actual bits of little-endian fields of sawtooth-numbered structures.
+    pub lsb_frame: Option<LsbFrame>,
+    pub old_value: Cow<'a, str>,
+    pub new_value: Cow<'a, str>,
+    pub access: FieldAccess
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+/// Field-level difference between two packed values of the same structure.
+/// The fields of nested structures are compared one by one, and named with
+/// their outer fields, like `flags[0].ready`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedDiff<'a> {
+    pub old_packed: Vec<u8>,
+    pub new_packed: Vec<u8>,
+    pub fields: Vec<FieldDiff<'a>>,
+    pub line_ending: &'a str
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+impl<'a> PackedDiff<'a> {
+    /// Compare the fields of two instances, as described by their debug fields.
+    pub fn new(old_packed: &[u8], old_fields: &[DebugBitField<'a>], new_packed: &[u8], new_fields: &[DebugBitField<'a>]) -> Self {
+        let mut fields = vec![];
+        Self::diff_fields(old_packed, old_fields, new_packed, new_fields, "", &mut fields);
+
+        PackedDiff {
+            old_packed: old_packed.to_vec(),
+            new_packed: new_packed.to_vec(),
+            fields,
+            line_ending: "\r\n"
+        }
+    }
+
+    /// Line ending of the table, `"\r\n"` by default.
+    pub fn with_line_ending(mut self, line_ending: &'a str) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    fn diff_fields(old_packed: &[u8], old_fields: &[DebugBitField<'a>], new_packed: &[u8], new_fields: &[DebugBitField<'a>], prefix: &str, diffs: &mut Vec<FieldDiff<'a>>) {
+        let bits_differ = |field: &DebugBitField| {
+            bit_positions(&field.bits, field.lsb_frame.as_ref()).any(|i| {
+                let mask = 1 << (7 - (i % 8));
+                (old_packed[i / 8] & mask) != (new_packed[i / 8] & mask)
+            })
+        };
+
+        for (old, new) in old_fields.iter().zip(new_fields) {
+            if !old.nested.is_empty() && old.nested.len() == new.nested.len() {
+                let prefix = format!("{}{}.", prefix, old.name);
+                Self::diff_fields(old_packed, &old.nested, new_packed, &new.nested, &prefix, diffs);
+                continue;
+            }
+            if !bits_differ(old) && old.display_value == new.display_value {
+                continue;
+            }
 
-            let debug_binary = DebugBinaryByteSlice {
+            let name = match prefix {
+                "" => old.name.clone(),
+                _ => Cow::Owned(format!("{}{}", prefix, old.name))
+            };
+            diffs.push(FieldDiff {
+                name,
+                bits: old.bits.clone(),
+                lsb_frame: old.lsb_frame.clone(),
+                old_value: old.display_value.clone(),
+                new_value: new.display_value.clone(),
+                access: old.access
+            });
+        }
+    }
+
+    /// Are the two values identical?
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Is the field with this name among the changed ones?
+    pub fn contains(&self, name: &str) -> bool {
+        self.fields.iter().any(|f| f.name == name)
+    }
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+impl<'a> fmt::Display for PackedDiff<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+
+        let max_field_length_name = self.fields.iter().map(|x| x.name.len()).max().unwrap();
+        let max_raw_width = self.fields.iter().map(|x| DebugRawBits { bits: &x.bits, lsb_frame: x.lsb_frame.as_ref(), slice: &self.old_packed, radix: DisplayRadix::Binary }.width()).max().unwrap();
+        let show_access = self.fields.iter().any(|x| x.access != FieldAccess::ReadWrite);
+
+        for field in &self.fields {
+            write!(f, "{name:>0$}", max_field_length_name + 1, name = field.name)?;
+            if show_access {
+                write!(f, " | {:<3}", field.access)?;
+            }
+
+            let old_raw = DebugRawBits {
+                bits: &field.bits,
+                lsb_frame: field.lsb_frame.as_ref(),
+                slice: &self.old_packed,
+                radix: DisplayRadix::Binary
+            };
+            let new_raw = DebugRawBits {
                 bits: &field.bits,
-                slice: packed_bytes
+                lsb_frame: field.lsb_frame.as_ref(),
+                slice: &self.new_packed,
+                radix: DisplayRadix::Binary
             };
 
-            write!(f, "{name:>0$} | bits {bits_start:>3}:{bits_end:<3} | 0b{binary_value:>0width_bits$b}{dummy:>0spaces$} | {base_value:?}\r\n",
-                        max_field_length_name + 1,
-                        base_value = field.display_value,
-                        binary_value = debug_binary,
+            write!(f, " | bits {bits_start:>3}:{bits_end:<3} | {old_raw}{dummy:>0spaces$} -> {new_raw}{dummy:>0spaces$}",
+                        old_raw = old_raw,
+                        new_raw = new_raw,
                         dummy = "",
                         bits_start = field.bits.start,
                         bits_end = field.bits.end,
-                        width_bits = field.bits.len(),
-                        spaces = (max_bit_width - field.bits.len()) as usize,
-                        name = field.name
+                        spaces = max_raw_width - old_raw.width()
+                        )?;
+
+            write!(f, " | {old_value:?} -> {new_value:?}{line_ending}",
+                        old_value = field.old_value,
+                        new_value = field.new_value,
+                        line_ending = self.line_ending
                         )?;
         }
+
+        Ok(())
     }
+}
 
-    Ok(())
+#[cfg(any(feature="alloc", feature="std"))]
+/// Field-level comparison of two values, implemented by `#[derive(PackedStruct)]`.
+pub trait PackedStructDiff: PackedStruct {
+    /// The fields that differ between `self` and `other`.
+    fn diff(&self, other: &Self) -> PackingResult<PackedDiff<'static>>;
+
+    /// Compare two raw buffers, interpreted through the structure's layout.
+    /// The bits are compared as they are in the buffers, reserved ones included.
+    fn diff_bytes(old: &[u8], new: &[u8]) -> Result<PackedDiff<'static>, DiffBytesError> where Self: PackedStructSlice + PackedStructDebug {
+        let old_value = Self::unpack_from_slice(old).map_err(DiffBytesError::Old)?;
+        let new_value = Self::unpack_from_slice(new).map_err(DiffBytesError::New)?;
+        Ok(PackedDiff::new(old, &old_value.debug_fields(), new, &new_value.debug_fields()))
+    }
 }
 
+#[cfg(any(feature="alloc", feature="std"))]
+/// The buffer of `PackedStructDiff::diff_bytes` that couldn't be unpacked.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DiffBytesError {
+    Old(PackingError),
+    New(PackingError)
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
+impl Display for DiffBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiffBytesError::Old(e) => write!(f, "Failed to unpack the old buffer: {}", e),
+            DiffBytesError::New(e) => write!(f, "Failed to unpack the new buffer: {}", e)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for DiffBytesError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            DiffBytesError::Old(e) | DiffBytesError::New(e) => Some(e)
+        }
+    }
+}
+
+#[cfg(any(feature="alloc", feature="std"))]
 pub struct PackedStructDisplay<'a, P: 'a> {
     pub packed_struct: &'a P,
     pub header: bool,
     pub raw_decimal: bool,
     pub raw_hex: bool,
     pub raw_binary: bool,
-    pub fields: bool
+    pub fields: bool,
+    /// Header and fields on a single line, without the raw bytes.
+    pub compact: bool,
+    /// Show an ANSI-colored bit map of the fields.
+    pub colors: bool,
+    pub format: FieldsFormat<'a>
 }
 
+#[cfg(any(feature="alloc", feature="std"))]
 impl<'a, P> PackedStructDisplay<'a, P> {
     pub fn new(packed_struct: &'a P) -> Self {
         PackedStructDisplay {
@@ -96,16 +799,72 @@ impl<'a, P> PackedStructDisplay<'a, P> {
             raw_decimal: true,
             raw_hex: true,
             raw_binary: true,
-            fields: true
+            fields: true,
+            compact: false,
+            colors: false,
+            format: FieldsFormat::default()
         }
     }
+
+    /// Show the structure's name and size.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Show the packed bytes in decimal, hex and binary.
+    pub fn with_raw(mut self, decimal: bool, hex: bool, binary: bool) -> Self {
+        self.raw_decimal = decimal;
+        self.raw_hex = hex;
+        self.raw_binary = binary;
+        self
+    }
+
+    /// Show the table of fields.
+    pub fn with_fields(mut self, fields: bool) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Single line format, `name=raw (value)` for every field, or the packed
+    /// bytes in hex if the structure doesn't describe its fields.
+    pub fn compact(mut self) -> Self {
+        self.compact = true;
+        self
+    }
+
+    /// Show the packed bits as a grid with each field in its own ANSI color,
+    /// followed by a legend. Reserved and unused bits are dimmed.
+    pub fn with_colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// The radix of the raw field values.
+    pub fn with_radix(mut self, radix: DisplayRadix) -> Self {
+        self.format.radix = radix;
+        self
+    }
+
+    /// Line ending of the table format, `"\r\n"` by default.
+    pub fn with_line_ending(mut self, line_ending: &'a str) -> Self {
+        self.format.line_ending = line_ending;
+        self
+    }
+
+    /// Only show these fields.
+    pub fn with_only_fields(mut self, fields: &'a [&'a str]) -> Self {
+        self.format.only_fields = Some(fields);
+        self
+    }
 }
 
-use crate::packing::PackedStruct;
-use crate::types_bits::ByteArray;
+#[cfg(any(feature="alloc", feature="std"))]
+use crate::packing::{PackedStructSlice, PackingError, PackingResult};
 
+#[cfg(any(feature="alloc", feature="std"))]
 impl<'a, P> fmt::Display for PackedStructDisplay<'a, P> where P: PackedStruct + PackedStructDebug {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let packed = match self.packed_struct.pack() {
             Ok(packed) => packed,
             Err(e) => {
@@ -114,16 +873,44 @@ impl<'a, P> fmt::Display for PackedStructDisplay<'a, P> where P: PackedStruct +
         };
         let packed = packed.as_bytes_slice();
         let l = packed.len();
+        let line_ending = self.format.line_ending;
+
+        if self.compact {
+            if self.header {
+                write!(f, "{}: ", P::packed_struct_display_header())?;
+            }
+            let fields = self.packed_struct.debug_fields();
+            if fields.is_empty() {
+                // without described fields, the packed bytes are all there is to show
+                for (i, b) in packed.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{:02X}", b)?;
+                }
+                return Ok(());
+            }
+            return packable_fmt_fields_compact(f, packed, &fields, &self.format);
+        }
+
+        // larger structures are printed in rows, prefixed by the offset
+        let rows = l > HEXDUMP_ROW_BYTES;
 
         if self.header {
             f.write_str(P::packed_struct_display_header())?;
-            f.write_str("\r\n")?;
-            f.write_str("\r\n")?;
+            f.write_str(line_ending)?;
+            f.write_str(line_ending)?;
         }
 
         // decimal
-        if self.raw_decimal {
-            f.write_str("Decimal\r\n")?;
+        if self.raw_decimal && rows {
+            f.write_str("Decimal")?;
+            f.write_str(line_ending)?;
+            fmt_byte_rows(f, packed, HEXDUMP_ROW_BYTES, line_ending, |f, b| write!(f, "{:>3}", b))?;
+            f.write_str(line_ending)?;
+        } else if self.raw_decimal {
+            f.write_str("Decimal")?;
+            f.write_str(line_ending)?;
             f.write_str("[")?;
             for i in 0..l {
                 write!(f, "{}", packed[i])?;
@@ -133,13 +920,19 @@ impl<'a, P> fmt::Display for PackedStructDisplay<'a, P> where P: PackedStruct +
             }
             f.write_str("]")?;
 
-            f.write_str("\r\n")?;
-            f.write_str("\r\n")?;
+            f.write_str(line_ending)?;
+            f.write_str(line_ending)?;
         }
                         
         // hex
-        if self.raw_hex {
-            f.write_str("Hex\r\n")?;
+        if self.raw_hex && rows {
+            f.write_str("Hex")?;
+            f.write_str(line_ending)?;
+            fmt_byte_rows(f, packed, HEXDUMP_ROW_BYTES, line_ending, |f, b| write!(f, "{:02X}", b))?;
+            f.write_str(line_ending)?;
+        } else if self.raw_hex {
+            f.write_str("Hex")?;
+            f.write_str(line_ending)?;
             f.write_str("[")?;
             for i in 0..l {
                 write!(f, "0x{:X}", packed[i])?;
@@ -148,12 +941,18 @@ impl<'a, P> fmt::Display for PackedStructDisplay<'a, P> where P: PackedStruct +
                 }
             }
             f.write_str("]")?;
-            f.write_str("\r\n")?;
-            f.write_str("\r\n")?;
+            f.write_str(line_ending)?;
+            f.write_str(line_ending)?;
         }
 
-        if self.raw_binary {
-            f.write_str("Binary\r\n")?;
+        if self.raw_binary && rows {
+            f.write_str("Binary")?;
+            f.write_str(line_ending)?;
+            fmt_byte_rows(f, packed, 8, line_ending, |f, b| write!(f, "{:08b}", b))?;
+            f.write_str(line_ending)?;
+        } else if self.raw_binary {
+            f.write_str("Binary")?;
+            f.write_str(line_ending)?;
             f.write_str("[")?;
             for i in 0..l {
                 write!(f, "0b{:08b}", packed[i])?;
@@ -162,12 +961,23 @@ impl<'a, P> fmt::Display for PackedStructDisplay<'a, P> where P: PackedStruct +
                 }
             }
             f.write_str("]")?;
-            f.write_str("\r\n")?;
-            f.write_str("\r\n")?;
+            f.write_str(line_ending)?;
+            f.write_str(line_ending)?;
+        }
+
+        let fields = self.packed_struct.debug_fields();
+
+        if self.colors {
+            f.write_str("Bit map")?;
+            f.write_str(line_ending)?;
+            DebugBitMap::new(packed, &fields).with_line_ending(line_ending).fmt(f)?;
+            f.write_str(line_ending)?;
         }
 
-        if self.fields {
+        if self.fields && fields.is_empty() {
             self.packed_struct.fmt_fields(f)?;
+        } else if self.fields {
+            packable_fmt_fields_with(f, packed, &fields, &self.format)?;
         }
     
         Ok(())
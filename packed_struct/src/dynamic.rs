@@ -0,0 +1,374 @@
+//! Dynamic access to the fields of packed structures by their names, for
+//! scripting consoles and test benches that handle structures they don't know
+//! at compile time.
+//!
+//! Array elements are named like `data[1]`, fields of nested structures are
+//! reached with a dotted path, like `flags.ready`.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::dynamic::{PackedDynamic, Value};
+//!
+//! #[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+//! pub enum Mode {
+//!     Idle = 0,
+//!     Active = 1
+//! }
+//!
+//! #[derive(PackedStruct, Debug, Default, PartialEq)]
+//! #[packed_struct(bit_numbering="msb0")]
+//! pub struct Control {
+//!     #[packed_field(bits="0")]
+//!     enabled: bool,
+//!     #[packed_field(bits="1", ty="enum")]
+//!     mode: Mode,
+//!     #[packed_field(bits="4:7")]
+//!     gain: Integer<u8, packed_bits::Bits4>,
+//!     #[packed_field(bytes="1:2")]
+//!     values: [u8; 2]
+//! }
+//!
+//! # impl Default for Mode { fn default() -> Self { Mode::Idle } }
+//! # fn main() -> Result<(), PackingError> {
+//! let mut control = Control::default();
+//! control.set_field("mode", Value::from("Active"))?;
+//! control.set_field("gain", Value::from(9u8))?;
+//! control.set_field("values[1]", Value::from(0x55u8))?;
+//!
+//! assert_eq!(Value::from("Active"), control.get_field("mode")?);
+//! assert_eq!(Value::from(false), control.get_field("enabled")?);
+//! assert_eq!(0x55, control.values[1]);
+//! assert_eq!(Err(PackingError::InvalidValue), control.set_field("gain", Value::from(16u8)));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::internal_prelude::v1::*;
+use crate::layout::PackedStructLayout;
+use crate::packing::{PackedStruct, PackingError, PackingResult};
+use crate::primitive_enum::{PrimitiveEnum, PrimitiveEnumDynamicStr};
+use crate::text::write_hex_bytes;
+use crate::types_bits::{ByteArray, NumberOfBits};
+use crate::types_num::Integer;
+use crate::visit::IntegerValue;
+
+/// The value of a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(IntegerValue),
+    Bool(bool),
+    /// The variant name of a primitive enum.
+    Enum(Cow<'static, str>),
+    /// The packed bytes of structures that don't support dynamic access.
+    Bytes(Vec<u8>),
+    /// The fields of a nested structure, in their declared order.
+    Nested(Vec<(Cow<'static, str>, Value)>),
+}
+
+impl Value {
+    /// The field of a nested value.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match *self {
+            Value::Nested(ref fields) => fields.iter().find(|f| f.0 == name).map(|f| &f.1),
+            _ => None
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Integer(ref v) => Display::fmt(v, f),
+            Value::Bool(v) => Display::fmt(&v, f),
+            Value::Enum(ref name) => f.write_str(name),
+            Value::Bytes(ref bytes) => write_hex_bytes(f, bytes),
+            Value::Nested(ref fields) => {
+                f.write_str("{ ")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+}
+
+macro_rules! value_from_integer {
+    ($variant: ident, $as: ty, $($T: ty),*) => {
+        $(
+            impl From<$T> for Value {
+                fn from(v: $T) -> Self {
+                    Value::Integer(IntegerValue::$variant(v as $as))
+                }
+            }
+        )*
+    };
+}
+
+value_from_integer!(Unsigned, u128, u8, u16, u32, u64, u128);
+value_from_integer!(Signed, i128, i8, i16, i32, i64, i128);
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Bool(v)
+    }
+}
+
+impl From<&'static str> for Value {
+    fn from(name: &'static str) -> Self {
+        Value::Enum(name.into())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Value::Bytes(bytes)
+    }
+}
+
+/// Get and set fields by their names, implemented by `#[derive(PackedStruct)]`.
+///
+/// Unknown fields and values that don't fit the field return `InvalidValue`.
+pub trait PackedDynamic: PackedStruct + PackedStructLayout {
+    /// Get the value of a field, like `"mode"`, `"values[2]"` or `"flags.ready"`.
+    fn get_field(&self, name: &str) -> PackingResult<Value>;
+
+    /// Set the value of a field, like `"mode"`, `"values[2]"` or `"flags.ready"`.
+    fn set_field(&mut self, name: &str, value: Value) -> PackingResult<()>;
+
+    /// All fields as a nested value.
+    fn to_value(&self) -> PackingResult<Value> {
+        let fields = Self::FIELDS.iter()
+            .map(|f| Ok((Cow::Borrowed(f.name), self.get_field(f.name)?)))
+            .collect::<PackingResult<_>>()?;
+        Ok(Value::Nested(fields))
+    }
+
+    /// Set the fields of a nested value, or unpack the bytes of a bytes value.
+    fn set_value(&mut self, value: Value) -> PackingResult<()> {
+        match value {
+            Value::Nested(fields) => {
+                for (name, value) in fields {
+                    self.set_field(&name, value)?;
+                }
+                Ok(())
+            },
+            Value::Bytes(bytes) => {
+                *self = unpack_bytes(&bytes)?;
+                Ok(())
+            },
+            _ => Err(PackingError::InvalidValue)
+        }
+    }
+}
+
+/// Splits the first field name off a dotted path.
+#[doc(hidden)]
+pub fn split_path(path: &str) -> (&str, Option<&str>) {
+    match path.find('.') {
+        Some(i) => (&path[..i], Some(&path[i + 1..])),
+        None => (path, None)
+    }
+}
+
+fn unpack_bytes<T: PackedStruct>(bytes: &[u8]) -> PackingResult<T> {
+    if T::ByteArray::len() != bytes.len() {
+        return Err(PackingError::BufferSizeMismatch { expected: T::ByteArray::len(), actual: bytes.len() });
+    }
+    let mut packed = T::ByteArray::new(0);
+    packed.as_mut_bytes_slice().copy_from_slice(bytes);
+    T::unpack(&packed)
+}
+
+/// Integers and booleans, converted from values with a range check.
+pub trait DynamicScalar: Sized {
+    fn to_value(&self) -> Value;
+    fn from_value(value: Value) -> PackingResult<Self>;
+}
+
+macro_rules! dynamic_scalar {
+    ($variant: ident, $($T: ty),*) => {
+        $(
+            impl DynamicScalar for $T {
+                fn to_value(&self) -> Value {
+                    Value::Integer(IntegerValue::$variant((*self).into()))
+                }
+
+                fn from_value(value: Value) -> PackingResult<Self> {
+                    let v = match value {
+                        Value::Integer(IntegerValue::Unsigned(v)) => <$T>::try_from(v).ok(),
+                        Value::Integer(IntegerValue::Signed(v)) => <$T>::try_from(v).ok(),
+                        Value::Bool(v) => Some(v.into()),
+                        _ => None
+                    };
+                    v.ok_or(PackingError::InvalidValue)
+                }
+            }
+        )*
+    };
+}
+
+dynamic_scalar!(Unsigned, u8, u16, u32, u64, u128);
+dynamic_scalar!(Signed, i8, i16, i32, i64, i128);
+
+impl DynamicScalar for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_value(value: Value) -> PackingResult<Self> {
+        match value {
+            Value::Bool(v) => Ok(v),
+            Value::Integer(IntegerValue::Unsigned(v)) if v <= 1 => Ok(v == 1),
+            Value::Integer(IntegerValue::Signed(v)) if v == 0 || v == 1 => Ok(v == 1),
+            _ => Err(PackingError::InvalidValue)
+        }
+    }
+}
+
+impl<T, B> DynamicScalar for Integer<T, B>
+    where T: DynamicScalar + Copy + PartialEq, B: NumberOfBits, Integer<T, B>: From<T> + Deref<Target = T>
+{
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+
+    fn from_value(value: Value) -> PackingResult<Self> {
+        let v = T::from_value(value)?;
+        let integer = Integer::from(v);
+        // the conversion masks the bits that don't fit
+        if *integer != v {
+            return Err(PackingError::InvalidValue);
+        }
+        Ok(integer)
+    }
+}
+
+/// Picks the conversion of a field's type. Used by the generated code, the
+/// most specific implementation is picked by auto-ref.
+#[doc(hidden)]
+pub struct FieldType<T>(PhantomData<T>);
+
+impl<T> FieldType<T> {
+    #[inline]
+    pub fn of(_field: &T) -> Self {
+        FieldType(PhantomData)
+    }
+}
+
+fn no_path(rest: Option<&str>) -> PackingResult<()> {
+    match rest {
+        Some(_) => Err(PackingError::InvalidValue),
+        None => Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub trait DynamicScalarField<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value>;
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()>;
+}
+
+impl<T: DynamicScalar> DynamicScalarField<T> for &&&&FieldType<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value> {
+        no_path(rest)?;
+        Ok(field.to_value())
+    }
+
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()> {
+        no_path(rest)?;
+        *field = T::from_value(value)?;
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub trait DynamicEnumField<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value>;
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()>;
+}
+
+impl<T> DynamicEnumField<T> for &&&FieldType<T>
+    where T: PrimitiveEnum + PrimitiveEnumDynamicStr, T::Primitive: DynamicScalar
+{
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value> {
+        no_path(rest)?;
+        Ok(Value::Enum(field.to_display_str()))
+    }
+
+    /// Accepts the variant's name or its primitive value.
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()> {
+        no_path(rest)?;
+        let v = match value {
+            Value::Enum(name) => T::from_str(&name),
+            value => T::from_primitive(T::Primitive::from_value(value)?)
+        };
+        *field = v.ok_or(PackingError::InvalidValue)?;
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub trait DynamicNestedField<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value>;
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()>;
+}
+
+impl<T: PackedDynamic> DynamicNestedField<T> for &&FieldType<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value> {
+        match rest {
+            Some(path) => field.get_field(path),
+            None => field.to_value()
+        }
+    }
+
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()> {
+        match rest {
+            Some(path) => field.set_field(path, value),
+            None => field.set_value(value)
+        }
+    }
+}
+
+#[doc(hidden)]
+pub trait DynamicBytesField<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value>;
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()>;
+}
+
+impl<T: PackedStruct> DynamicBytesField<T> for &FieldType<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value> {
+        no_path(rest)?;
+        Ok(Value::Bytes(field.pack()?.as_bytes_slice().to_vec()))
+    }
+
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()> {
+        no_path(rest)?;
+        match value {
+            Value::Bytes(bytes) => {
+                *field = unpack_bytes(&bytes)?;
+                Ok(())
+            },
+            _ => Err(PackingError::InvalidValue)
+        }
+    }
+}
+
+#[doc(hidden)]
+pub trait DynamicUnsupportedField<T> {
+    fn get_dynamic(&self, field: &T, rest: Option<&str>) -> PackingResult<Value>;
+    fn set_dynamic(&self, field: &mut T, rest: Option<&str>, value: Value) -> PackingResult<()>;
+}
+
+impl<T> DynamicUnsupportedField<T> for FieldType<T> {
+    fn get_dynamic(&self, _field: &T, _rest: Option<&str>) -> PackingResult<Value> {
+        Err(PackingError::NotImplemented)
+    }
+
+    fn set_dynamic(&self, _field: &mut T, _rest: Option<&str>, _value: Value) -> PackingResult<()> {
+        Err(PackingError::NotImplemented)
+    }
+}
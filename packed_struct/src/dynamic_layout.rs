@@ -0,0 +1,381 @@
+//! Layouts defined at runtime, for messages that are only known from
+//! configuration files. Packs and unpacks ordered `Value::Nested` maps with
+//! the same bit semantics as `#[derive(PackedStruct)]`.
+//!
+//! Bit ranges are inclusive and use the layout's bit numbering, like the
+//! `bits` attribute of the derive. Integers wider than a byte are MSB unless
+//! stated otherwise. Fields unpack as:
+//!
+//!  * enum variant names, if the field has a variant table and the value is in it,
+//!  * booleans, for unsigned single-bit fields,
+//!  * bytes, for fields wider than 128 bits,
+//!  * integers otherwise.
+//!
+//! A layout is validated once, when it's built or deserialized, so packing
+//! and unpacking only check the values. The JSON schema is the one written by
+//! `StructLayout::write_json`, so the layout of a derived structure can be
+//! loaded as is: fields are placed by their `declared_bits`, and the keys that
+//! a runtime layout doesn't use, like `type` or `nested`, are ignored. Nested
+//! structures are packed as their raw bits.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::dynamic::Value;
+//! use packed_struct::dynamic_layout::{DynamicField, DynamicLayoutBuilder};
+//! use packed_struct::layout::BitNumbering;
+//! use packed_struct::visit::IntegerEndianness;
+//!
+//! # fn main() -> Result<(), PackingError> {
+//! let layout = DynamicLayoutBuilder::new("Status", 3, BitNumbering::Msb0)
+//!     .with_field(DynamicField::new("busy", 0, 0))
+//!     .with_field(DynamicField::new("mode", 4, 7).with_variant("Idle", 0).with_variant("Run", 1))
+//!     .with_field(DynamicField::new("offset", 8, 23).with_endianness(IntegerEndianness::Lsb).with_signed(true))
+//!     .build()?;
+//!
+//! let values = layout.unpack(&[0x81, 0xFE, 0xFF])?;
+//! assert_eq!(Some(&Value::Bool(true)), values.field("busy"));
+//! assert_eq!(Some(&Value::from("Run")), values.field("mode"));
+//! assert_eq!(Some(&Value::from(-2i16)), values.field("offset"));
+//! assert_eq!(vec![0x81, 0xFE, 0xFF], layout.pack(&values)?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::internal_prelude::v1::*;
+use crate::dynamic::Value;
+use crate::layout::BitNumbering;
+use crate::packing::{PackingError, PackingResult};
+use crate::visit::{IntegerEndianness, IntegerValue};
+
+/// A named value of an enum field. The value is an `i64`, TOML doesn't support wider integers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct DynamicVariant {
+    pub name: String,
+    pub value: i64,
+}
+
+/// A field of a runtime-defined layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct DynamicField {
+    pub name: String,
+    /// The inclusive bit range, in the layout's bit numbering.
+    pub declared_bits: (usize, usize),
+    /// Byte order of integers wider than a byte, MSB if not set. Places the
+    /// declared bits of sawtooth fields, see `BitNumbering::Sawtooth`.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub endianness: Option<IntegerEndianness>,
+    /// Two's complement integer.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub signed: bool,
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub variants: Vec<DynamicVariant>,
+}
+
+impl DynamicField {
+    /// An unsigned field over the inclusive bit range, in any order.
+    pub fn new(name: &str, first_bit: usize, last_bit: usize) -> Self {
+        DynamicField {
+            name: name.into(),
+            declared_bits: (first_bit, last_bit),
+            endianness: None,
+            signed: false,
+            variants: vec![]
+        }
+    }
+
+    pub fn with_endianness(mut self, endianness: IntegerEndianness) -> Self {
+        self.endianness = Some(endianness);
+        self
+    }
+
+    pub fn with_signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// Add a variant to the field's enum table.
+    pub fn with_variant(mut self, name: &str, value: i64) -> Self {
+        self.variants.push(DynamicVariant { name: name.into(), value });
+        self
+    }
+}
+
+/// The definition of a runtime-defined layout, before it's validated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct DynamicLayoutBuilder {
+    pub name: String,
+    pub size_bytes: usize,
+    pub bit_numbering: BitNumbering,
+    pub fields: Vec<DynamicField>,
+}
+
+impl DynamicLayoutBuilder {
+    pub fn new(name: &str, size_bytes: usize, bit_numbering: BitNumbering) -> Self {
+        DynamicLayoutBuilder {
+            name: name.into(),
+            size_bytes,
+            bit_numbering,
+            fields: vec![]
+        }
+    }
+
+    pub fn with_field(mut self, field: DynamicField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Checks that the fields fit into the structure without overlapping, and
+    /// that their names are unique. Returns `BitsError` or `InvalidValue`.
+    pub fn build(self) -> PackingResult<DynamicLayout> {
+        let mut used = vec![false; self.size_bytes * 8];
+        let mut field_bits = Vec::with_capacity(self.fields.len());
+        for (i, field) in self.fields.iter().enumerate() {
+            if self.fields[..i].iter().any(|f| f.name == field.name) {
+                return Err(PackingError::InvalidValue);
+            }
+            let bits = self.field_bits(field)?;
+            for &bit in &bits {
+                if used[bit] {
+                    return Err(PackingError::BitsError);
+                }
+                used[bit] = true;
+            }
+            field_bits.push(bits);
+        }
+
+        Ok(DynamicLayout { layout: self, field_bits })
+    }
+
+    /// The field's bits in MSB0 numbering, most significant first.
+    fn field_bits(&self, field: &DynamicField) -> PackingResult<Vec<usize>> {
+        let (first, last) = (min(field.declared_bits.0, field.declared_bits.1), max(field.declared_bits.0, field.declared_bits.1));
+        let num_bits = self.size_bytes * 8;
+        if last >= num_bits {
+            return Err(PackingError::BitsError);
+        }
+
+        let msb0 = |n: usize| (n / 8) * 8 + 7 - (n % 8);
+        Ok(match self.bit_numbering {
+            BitNumbering::Msb0 => (first..=last).collect(),
+            BitNumbering::Lsb0 => ((num_bits - 1 - last)..=(num_bits - 1 - first)).collect(),
+            BitNumbering::Sawtooth if field.endianness == Some(IntegerEndianness::Lsb) => {
+                (first..=last).rev().map(msb0).collect()
+            },
+            BitNumbering::Sawtooth => {
+                let (a, b) = (msb0(first), msb0(last));
+                (min(a, b)..=max(a, b)).collect()
+            }
+        })
+    }
+}
+
+/// A validated runtime-defined layout. Serializes as its `DynamicLayoutBuilder`,
+/// and is validated when it's deserialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize), serde(try_from = "DynamicLayoutBuilder", into = "DynamicLayoutBuilder"))]
+pub struct DynamicLayout {
+    layout: DynamicLayoutBuilder,
+    /// The bits of every field, as returned by `DynamicLayoutBuilder::field_bits`.
+    field_bits: Vec<Vec<usize>>,
+}
+
+impl TryFrom<DynamicLayoutBuilder> for DynamicLayout {
+    type Error = PackingError;
+
+    fn try_from(layout: DynamicLayoutBuilder) -> PackingResult<Self> {
+        layout.build()
+    }
+}
+
+impl From<DynamicLayout> for DynamicLayoutBuilder {
+    fn from(layout: DynamicLayout) -> Self {
+        layout.layout
+    }
+}
+
+impl DynamicLayout {
+    /// Load a layout from JSON and validate it.
+    #[cfg(feature = "json")]
+    pub fn from_json_str(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
+    /// Load a layout from TOML and validate it.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.layout.name
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.layout.size_bytes
+    }
+
+    pub fn bit_numbering(&self) -> BitNumbering {
+        self.layout.bit_numbering
+    }
+
+    pub fn fields(&self) -> &[DynamicField] {
+        &self.layout.fields
+    }
+
+    /// Find a field by its name.
+    pub fn field(&self, name: &str) -> Option<&DynamicField> {
+        self.fields().iter().find(|f| f.name == name)
+    }
+
+    /// Sawtooth bit positions already order the bytes of little-endian fields.
+    fn swaps_bytes(&self, field: &DynamicField) -> bool {
+        field.endianness == Some(IntegerEndianness::Lsb) && self.layout.bit_numbering != BitNumbering::Sawtooth
+    }
+
+    /// Unpack the bytes into a `Value::Nested` with every field, in their declared order.
+    pub fn unpack(&self, bytes: &[u8]) -> PackingResult<Value> {
+        if bytes.len() != self.size_bytes() {
+            return Err(PackingError::BufferSizeMismatch { expected: self.size_bytes(), actual: bytes.len() });
+        }
+
+        let mut values = Vec::with_capacity(self.fields().len());
+        for (field, bits) in self.fields().iter().zip(&self.field_bits) {
+            let value = if bits.len() > 128 {
+                let mut raw = vec![0; bits.len().div_ceil(8)];
+                let offset = raw.len() * 8 - bits.len();
+                for (i, &bit) in bits.iter().enumerate() {
+                    set_bit(&mut raw, offset + i, get_bit(bytes, bit));
+                }
+                Value::Bytes(raw)
+            } else {
+                let mut raw = 0;
+                for &bit in bits {
+                    raw = (raw << 1) | get_bit(bytes, bit) as u128;
+                }
+                if self.swaps_bytes(field) {
+                    raw = swap_bytes(raw, bits.len());
+                }
+                field_value(field, raw, bits.len())
+            };
+            values.push((Cow::Owned(field.name.clone()), value));
+        }
+
+        Ok(Value::Nested(values))
+    }
+
+    /// Pack a `Value::Nested` into bytes. Fields missing from the values are
+    /// packed as zeroes, unknown fields and values that don't fit return `InvalidValue`.
+    pub fn pack(&self, values: &Value) -> PackingResult<Vec<u8>> {
+        let values = match *values {
+            Value::Nested(ref values) => values,
+            _ => return Err(PackingError::InvalidValue)
+        };
+
+        let mut bytes = vec![0; self.size_bytes()];
+        for (name, value) in values {
+            let index = self.fields().iter().position(|f| f.name == *name).ok_or(PackingError::InvalidValue)?;
+            let (field, bits) = (&self.fields()[index], &self.field_bits[index]);
+
+            if let Value::Bytes(ref raw) = *value {
+                if raw.len() != bits.len().div_ceil(8) {
+                    return Err(PackingError::BufferSizeMismatch { expected: bits.len().div_ceil(8), actual: raw.len() });
+                }
+                let offset = raw.len() * 8 - bits.len();
+                if (0..offset).any(|i| get_bit(raw, i)) {
+                    return Err(PackingError::InvalidValue);
+                }
+                for (i, &bit) in bits.iter().enumerate() {
+                    set_bit(&mut bytes, bit, get_bit(raw, offset + i));
+                }
+                continue;
+            }
+
+            if bits.len() > 128 {
+                return Err(PackingError::InvalidValue);
+            }
+
+            let mut raw = field_raw(field, value, bits.len())?;
+            if self.swaps_bytes(field) {
+                raw = swap_bytes(raw, bits.len());
+            }
+            for (i, &bit) in bits.iter().rev().enumerate() {
+                set_bit(&mut bytes, bit, (raw >> i) & 1 == 1);
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+fn field_value(field: &DynamicField, raw: u128, num_bits: usize) -> Value {
+    let integer = if field.signed && num_bits < 128 && (raw >> (num_bits - 1)) & 1 == 1 {
+        IntegerValue::Signed((raw | !mask(num_bits)) as i128)
+    } else if field.signed {
+        IntegerValue::Signed(raw as i128)
+    } else {
+        IntegerValue::Unsigned(raw)
+    };
+
+    if let Some(variant) = field.variants.iter().find(|v| v.value as i128 == integer.as_i128()) {
+        return Value::Enum(Cow::Owned(variant.name.clone()));
+    }
+
+    match integer {
+        IntegerValue::Unsigned(v) if num_bits == 1 && field.variants.is_empty() => Value::Bool(v == 1),
+        integer => Value::Integer(integer)
+    }
+}
+
+/// The raw bits of the value, with a range check.
+fn field_raw(field: &DynamicField, value: &Value, num_bits: usize) -> PackingResult<u128> {
+    let integer = match *value {
+        Value::Integer(integer) => integer,
+        Value::Bool(v) => IntegerValue::Unsigned(v as u128),
+        Value::Enum(ref name) => {
+            let variant = field.variants.iter().find(|v| v.name == *name).ok_or(PackingError::InvalidValue)?;
+            IntegerValue::Signed(variant.value.into())
+        },
+        _ => return Err(PackingError::InvalidValue)
+    };
+
+    let fits = match (integer, field.signed) {
+        (IntegerValue::Unsigned(v), false) => num_bits == 128 || v >> num_bits == 0,
+        (IntegerValue::Signed(v), false) => v >= 0 && (num_bits == 128 || v >> num_bits == 0),
+        (IntegerValue::Unsigned(v), true) => v >> (num_bits - 1) == 0,
+        (IntegerValue::Signed(v), true) => num_bits == 128 || (v >> (num_bits - 1) == 0 || v >> (num_bits - 1) == -1)
+    };
+    if !fits {
+        return Err(PackingError::InvalidValue);
+    }
+
+    Ok((integer.as_i128() as u128) & mask(num_bits))
+}
+
+/// Swaps the bytes of an integer that's stored in the given number of bits.
+fn swap_bytes(raw: u128, num_bits: usize) -> u128 {
+    if num_bits <= 8 {
+        return raw;
+    }
+    let num_bytes = num_bits.div_ceil(8);
+    (raw.swap_bytes() >> (128 - num_bytes * 8)) & mask(num_bits)
+}
+
+fn mask(num_bits: usize) -> u128 {
+    if num_bits >= 128 { u128::MAX } else { (1 << num_bits) - 1 }
+}
+
+fn get_bit(bytes: &[u8], index: usize) -> bool {
+    (bytes[index / 8] >> (7 - (index % 8))) & 1 == 1
+}
+
+fn set_bit(bytes: &mut [u8], index: usize, bit: bool) {
+    let mask = 1 << (7 - (index % 8));
+    if bit {
+        bytes[index / 8] |= mask;
+    } else {
+        bytes[index / 8] &= !mask;
+    }
+}
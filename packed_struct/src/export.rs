@@ -0,0 +1,100 @@
+//! Helpers shared by the generators of sources in other languages.
+
+use crate::internal_prelude::v1::*;
+use crate::layout::FieldInfo;
+
+/// Where a field's bits are in the packed bytes.
+pub(crate) struct FieldPlacement {
+    /// The first byte with some of the field's bits.
+    pub offset: usize,
+    /// The number of bytes from the first to the last byte with the field's bits.
+    pub bytes: usize,
+    /// Byte order and shift of fields that are a run of bits in their
+    /// bytes, read as an integer of at most 64 bits.
+    pub window: Option<(bool, usize)>,
+}
+
+impl FieldPlacement {
+    /// Place a field from its `(value bit, MSB0 position)` pairs, see `FieldInfo::bit_map`.
+    pub fn of<I: Iterator<Item = (usize, usize)>>(bits: impl Fn() -> I, width: usize) -> Self {
+        let first = bits().map(|(_, p)| p / 8).min().unwrap_or(0);
+        let last = bits().map(|(_, p)| p / 8).max().unwrap_or(0);
+        let bytes = last - first + 1;
+        let complete = bits().count() == width;
+
+        // bit k of the bytes read as a big-endian or little-endian integer
+        let big = |p: usize| (first + bytes) * 8 - 1 - p;
+        let little = |p: usize| (p / 8 - first) * 8 + 7 - p % 8;
+        let run = |k: &dyn Fn(usize) -> usize| {
+            let shift = bits().find(|&(v, _)| v == 0).map(|(_, p)| k(p))?;
+            match bits().all(|(v, p)| k(p) == shift + v) {
+                true => Some(shift),
+                false => None
+            }
+        };
+
+        let window = if !complete || bytes > 8 {
+            None
+        } else if let Some(shift) = run(&big) {
+            Some((false, shift))
+        } else {
+            run(&little).map(|shift| (true, shift))
+        };
+
+        FieldPlacement { offset: first, bytes, window }
+    }
+
+    pub fn little_endian(&self) -> bool {
+        matches!(self.window, Some((true, _)))
+    }
+}
+
+/// An identifier in snake case, upper or lower. Other characters than ASCII
+/// letters and digits separate words, leading and trailing ones are dropped.
+#[derive(Copy, Clone)]
+pub(crate) struct SnakeCase<'a>(pub &'a str, pub bool);
+
+impl<'a> Display for SnakeCase<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut separate = false;
+        let mut written = false;
+        let mut previous_lower = false;
+        for c in self.0.chars() {
+            if !c.is_ascii_alphanumeric() {
+                separate = written;
+                previous_lower = false;
+                continue;
+            }
+            if (separate || (c.is_ascii_uppercase() && previous_lower)) && written {
+                f.write_char('_')?;
+            }
+            f.write_char(if self.1 { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() })?;
+            separate = false;
+            written = true;
+            previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+        Ok(())
+    }
+}
+
+/// Fields of signed integers, or of primitive enums with negative values.
+pub(crate) fn is_signed(field: &FieldInfo) -> bool {
+    match field.variants.is_empty() {
+        true => field.type_name
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|t| matches!(t, "i8" | "i16" | "i32" | "i64" | "i128" | "isize")),
+        false => field.variants.iter().any(|v| v.value < 0)
+    }
+}
+
+/// Splits an array element's name like `data[1]`.
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub(crate) fn array_name(name: &str) -> (&str, Option<usize>) {
+    match (name.find('['), name.strip_suffix(']')) {
+        (Some(open), Some(rest)) => match rest[open + 1..].parse() {
+            Ok(index) => (&name[..open], Some(index)),
+            Err(_) => (name, None)
+        },
+        _ => (name, None)
+    }
+}
@@ -0,0 +1,225 @@
+//! Register devices on top of the `embedded-hal` I2C and SPI traits. Enabled
+//! with the `embedded_hal` feature.
+//!
+//! Both devices implement [`RegisterBus`], so they can be used with the
+//! functions generated by `#[derive(RegisterMap)]` as well. Register addresses
+//! are sent in big endian, using 1 byte by default. Accessing a register whose
+//! address doesn't fit in those bytes fails with `AddressTooWide`.
+
+use crate::internal_prelude::v1::*;
+use crate::access::PackedStructAccess;
+use crate::packing::PackedStruct;
+use crate::register_map::{RegisterBus, RegisterError};
+
+use embedded_hal::i2c::{self, I2c, SevenBitAddress};
+use embedded_hal::spi::{self, SpiDevice};
+
+/// The number of bytes that register addresses are sent with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RegisterAddressBytes {
+    One = 1,
+    Two = 2,
+    Three = 3,
+    Four = 4,
+}
+
+/// Errors of the register devices.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RegisterDeviceError<E> {
+    /// The I2C or SPI bus reported an error.
+    Bus(E),
+    /// The register's address doesn't fit in the device's address bytes.
+    AddressTooWide { address: u32, address_bytes: RegisterAddressBytes },
+}
+
+impl<E: Debug> Display for RegisterDeviceError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Debug> ::std::error::Error for RegisterDeviceError<E> {}
+
+/// The address's big-endian bytes, and the range of them that is sent.
+type AddressHeader = ([u8; 4], Range<usize>);
+
+/// The address header of a register access.
+fn register_address<E>(address: u32, address_bytes: RegisterAddressBytes) -> Result<AddressHeader, RegisterDeviceError<E>> {
+    let len = address_bytes as usize;
+    if len < 4 && address >> (8 * len) != 0 {
+        return Err(RegisterDeviceError::AddressTooWide { address, address_bytes });
+    }
+    Ok((address.to_be_bytes(), (4 - len)..4))
+}
+
+/// A device whose registers are accessed over I2C. The register address is
+/// written first, followed by the register's bytes. Reads use a repeated start.
+#[derive(Debug)]
+pub struct I2cRegisterDevice<I2C> {
+    i2c: I2C,
+    address: SevenBitAddress,
+    address_bytes: RegisterAddressBytes,
+}
+
+impl<I2C: I2c> I2cRegisterDevice<I2C> {
+    /// A device at the 7-bit I2C address.
+    pub fn new(i2c: I2C, address: SevenBitAddress) -> Self {
+        I2cRegisterDevice {
+            i2c,
+            address,
+            address_bytes: RegisterAddressBytes::One,
+        }
+    }
+
+    /// The number of bytes used for register addresses.
+    pub fn with_register_address_bytes(mut self, address_bytes: RegisterAddressBytes) -> Self {
+        self.address_bytes = address_bytes;
+        self
+    }
+
+    /// The device's I2C address.
+    pub fn address(&self) -> SevenBitAddress {
+        self.address
+    }
+
+    /// Release the I2C bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Read and unpack the register.
+    pub fn read<T: PackedStruct>(&mut self, address: u32) -> Result<T, RegisterError<RegisterDeviceError<I2C::Error>>> {
+        self.read_register(address)
+    }
+
+    /// Pack and write the register.
+    pub fn write<T: PackedStruct>(&mut self, address: u32, value: &T) -> Result<(), RegisterError<RegisterDeviceError<I2C::Error>>> {
+        self.write_register(address, value)
+    }
+
+    /// Read the register, modify it and write it back. Returns the written value.
+    pub fn modify<T, F>(&mut self, address: u32, f: F) -> Result<T, RegisterError<RegisterDeviceError<I2C::Error>>>
+        where T: PackedStruct, F: FnOnce(&mut T)
+    {
+        self.modify_register(address, f)
+    }
+
+    /// Read the register, modify it and write it back while respecting the access
+    /// semantics of its fields.
+    pub fn update<T, F>(&mut self, address: u32, f: F) -> Result<T, RegisterError<RegisterDeviceError<I2C::Error>>>
+        where T: PackedStructAccess, F: FnOnce(&mut T)
+    {
+        self.update_register(address, f)
+    }
+}
+
+impl<I2C: I2c> RegisterBus for I2cRegisterDevice<I2C> {
+    type Error = RegisterDeviceError<I2C::Error>;
+
+    fn read_bytes(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let (header, range) = register_address(address, self.address_bytes)?;
+        self.i2c.write_read(self.address, &header[range], buffer)
+            .map_err(RegisterDeviceError::Bus)
+    }
+
+    fn write_bytes(&mut self, address: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let (header, range) = register_address(address, self.address_bytes)?;
+        self.i2c.transaction(self.address, &mut [
+            i2c::Operation::Write(&header[range]),
+            i2c::Operation::Write(bytes)
+        ]).map_err(RegisterDeviceError::Bus)
+    }
+}
+
+/// A device whose registers are accessed over SPI. Each access is a single
+/// transaction that starts with the register address. The first address byte is
+/// combined with the read or write flag, by default `0x80` for reads and `0x00`
+/// for writes.
+#[derive(Debug)]
+pub struct SpiRegisterDevice<SPI> {
+    spi: SPI,
+    address_bytes: RegisterAddressBytes,
+    read_flag: u8,
+    write_flag: u8,
+}
+
+impl<SPI: SpiDevice> SpiRegisterDevice<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        SpiRegisterDevice {
+            spi,
+            address_bytes: RegisterAddressBytes::One,
+            read_flag: 0x80,
+            write_flag: 0x00,
+        }
+    }
+
+    /// The number of bytes used for register addresses.
+    pub fn with_register_address_bytes(mut self, address_bytes: RegisterAddressBytes) -> Self {
+        self.address_bytes = address_bytes;
+        self
+    }
+
+    /// The bits that are set in the first address byte of reads and writes.
+    pub fn with_flags(mut self, read_flag: u8, write_flag: u8) -> Self {
+        self.read_flag = read_flag;
+        self.write_flag = write_flag;
+        self
+    }
+
+    /// Release the SPI device.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+
+    /// Read and unpack the register.
+    pub fn read<T: PackedStruct>(&mut self, address: u32) -> Result<T, RegisterError<RegisterDeviceError<SPI::Error>>> {
+        self.read_register(address)
+    }
+
+    /// Pack and write the register.
+    pub fn write<T: PackedStruct>(&mut self, address: u32, value: &T) -> Result<(), RegisterError<RegisterDeviceError<SPI::Error>>> {
+        self.write_register(address, value)
+    }
+
+    /// Read the register, modify it and write it back. Returns the written value.
+    pub fn modify<T, F>(&mut self, address: u32, f: F) -> Result<T, RegisterError<RegisterDeviceError<SPI::Error>>>
+        where T: PackedStruct, F: FnOnce(&mut T)
+    {
+        self.modify_register(address, f)
+    }
+
+    /// Read the register, modify it and write it back while respecting the access
+    /// semantics of its fields.
+    pub fn update<T, F>(&mut self, address: u32, f: F) -> Result<T, RegisterError<RegisterDeviceError<SPI::Error>>>
+        where T: PackedStructAccess, F: FnOnce(&mut T)
+    {
+        self.update_register(address, f)
+    }
+
+    fn header(&self, address: u32, flag: u8) -> Result<AddressHeader, RegisterDeviceError<SPI::Error>> {
+        let (mut header, range) = register_address(address, self.address_bytes)?;
+        header[range.start] |= flag;
+        Ok((header, range))
+    }
+}
+
+impl<SPI: SpiDevice> RegisterBus for SpiRegisterDevice<SPI> {
+    type Error = RegisterDeviceError<SPI::Error>;
+
+    fn read_bytes(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let (header, range) = self.header(address, self.read_flag)?;
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&header[range]),
+            spi::Operation::Read(buffer)
+        ]).map_err(RegisterDeviceError::Bus)
+    }
+
+    fn write_bytes(&mut self, address: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let (header, range) = self.header(address, self.write_flag)?;
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&header[range]),
+            spi::Operation::Write(bytes)
+        ]).map_err(RegisterDeviceError::Bus)
+    }
+}
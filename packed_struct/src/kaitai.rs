@@ -0,0 +1,341 @@
+//! Kaitai Struct `.ksy` descriptions of packed structures, for the parsers
+//! Kaitai generates in many languages and for its Web IDE.
+//!
+//! Kaitai reads the attributes of a type one after another, so the fields are
+//! sorted by their position and the unused bits between them are read as
+//! `gap_<bit>` attributes. Fields become bit-sized integers like `b3` and
+//! `b12be`, or `u2le` and `s4be` when they are whole aligned bytes. Booleans are
+//! `b1`, primitive enums refer to an enum of the type, reserved fields check
+//! their value with `valid`, nested structures are types of their own and
+//! arrays are repeated attributes.
+//!
+//! Types with little-endian fields that aren't whole aligned bytes, like the
+//! Intel signals of sawtooth structures, are read with `bit-endian: le`. Fields
+//! that Kaitai can't read in one go, like signed bit-sized integers or
+//! big-endian fields in such types, are read in parts and put together by a
+//! value instance with the field's name.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::layout::PackedStructLayout;
+//!
+//! #[derive(PackedStruct)]
+//! #[packed_struct(bit_numbering="msb0", endian="msb")]
+//! pub struct Ping {
+//!     #[packed_field(bits="0")]
+//!     reply: bool,
+//!     #[packed_field(bits="4:15")]
+//!     sequence: Integer<u16, packed_bits::Bits12>
+//! }
+//!
+//! let ksy = Ping::ksy();
+//! assert!(ksy.contains("seq:\n  - id: reply\n    type: b1\n  - id: gap_1\n    type: b3\n  - id: sequence\n    type: b12be\n"));
+//! ```
+
+use crate::internal_prelude::v1::*;
+use crate::export::{array_name, is_signed, FieldPlacement, SnakeCase};
+use crate::layout::{FieldInfo, StructLayout};
+
+impl StructLayout {
+    /// Write a Kaitai Struct description of the structure, see the
+    /// [module documentation](crate::kaitai).
+    pub fn write_ksy<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        writeln!(w, "# The layout of the {} packed structure. Generated, don't edit.", self.name)?;
+        writeln!(w, "meta:")?;
+        writeln!(w, "  id: {}", SnakeCase(self.name, false))?;
+        write_type(w, self, 0)?;
+
+        let mut nested = Vec::new();
+        collect_nested(self, &mut nested);
+        if !nested.is_empty() {
+            writeln!(w, "types:")?;
+            for layout in &nested {
+                writeln!(w, "  {}:", SnakeCase(layout.name, false))?;
+                writeln!(w, "    meta:")?;
+                write_type(w, layout, 2)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The layouts of the nested structures, each one once.
+fn collect_nested(layout: &StructLayout, found: &mut Vec<StructLayout>) {
+    for field in layout.fields {
+        if let Some(nested) = layout.nested(field.name) {
+            if !found.iter().any(|l| l.name == nested.name) {
+                found.push(nested);
+                collect_nested(&nested, found);
+            }
+        }
+    }
+}
+
+/// A run of a field's bits that Kaitai reads as one integer.
+struct Chunk<'a> {
+    /// The position of the first bit in the order of reading.
+    start: usize,
+    len: usize,
+    field: &'a FieldInfo,
+    /// The value bit of the chunk's least significant bit.
+    value_shift: usize,
+    /// The chunk is the whole field.
+    whole: bool,
+    /// The byte order of fields that are whole aligned bytes, little-endian or not.
+    aligned_bytes: Option<bool>,
+}
+
+/// An attribute of the sequence.
+struct Attribute {
+    id: String,
+    /// The `type` or the `size` and its value.
+    kind: (&'static str, String),
+    enum_id: Option<String>,
+    valid: Option<u128>,
+    doc: &'static str,
+    /// The array name and index of the element.
+    element: Option<(String, usize)>,
+}
+
+fn write_type<W: fmt::Write + ?Sized>(w: &mut W, layout: &StructLayout, level: usize) -> fmt::Result {
+    let indent = "  ".repeat(level);
+    let placements: Vec<_> = layout.fields.iter()
+        .map(|f| FieldPlacement::of(|| f.bit_map(layout.bit_numbering), f.width))
+        .collect();
+
+    // read the bits from the least significant one of each byte up
+    let little_endian = layout.fields.iter().zip(&placements).any(|(f, p)| {
+        p.little_endian() && p.bytes > 1 && !(p.window == Some((true, 0)) && f.width == p.bytes * 8)
+    });
+    let stream = |p: usize| if little_endian { (p / 8) * 8 + 7 - p % 8 } else { p };
+    writeln!(w, "{}  bit-endian: {}", indent, if little_endian { "le" } else { "be" })?;
+
+    let mut chunks = Vec::new();
+    for (field, placement) in layout.fields.iter().zip(&placements) {
+        let whole_bytes = field.width == placement.bytes * 8 && field.width <= 64;
+        if is_aligned_nested(layout, field, placement) || (whole_bytes && placement.window.map(|(_, shift)| shift) == Some(0)) {
+            let aligned_bytes = placement.window.map(|(le, _)| le);
+            chunks.push(Chunk { start: placement.offset * 8, len: field.width, field, value_shift: 0, whole: true, aligned_bytes });
+            continue;
+        }
+
+        let mut bits: Vec<_> = field.bit_map(layout.bit_numbering).map(|(v, p)| (v, stream(p))).collect();
+        bits.sort_by_key(|&(_, s)| s);
+        let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+        for (v, s) in bits {
+            match runs.last_mut() {
+                Some((start, len, low)) if *start + *len == s && *len < 64 && little_endian && *low + *len == v => *len += 1,
+                Some((start, len, low)) if *start + *len == s && *len < 64 && !little_endian && *low == v + 1 => {
+                    *len += 1;
+                    *low = v;
+                },
+                _ => runs.push((s, 1, v))
+            }
+        }
+        let whole = runs.len() == 1 && runs[0].1 == field.width;
+        for (start, len, value_shift) in runs {
+            chunks.push(Chunk { start, len, field, value_shift, whole, aligned_bytes: None });
+        }
+    }
+    chunks.sort_by_key(|c| c.start);
+
+    let mut attributes = Vec::new();
+    let mut instances = Vec::new();
+    let mut position = 0;
+    for chunk in &chunks {
+        write_gap(&mut attributes, position, chunk.start);
+        position = chunk.start + chunk.len;
+
+        let field = chunk.field;
+        let name = format!("{}", SnakeCase(field.name, false));
+        let signed = is_signed(field);
+        let enum_id = match field.variants.is_empty() {
+            true => None,
+            false => Some(format!("{}", SnakeCase(array_name(field.name).0, false)))
+        };
+        let bit_type = |len: usize| {
+            let single_byte = chunk.start / 8 == (chunk.start + len - 1) / 8;
+            match (single_byte, chunk.aligned_bytes.unwrap_or(little_endian)) {
+                (true, _) => format!("b{}", len),
+                (false, true) => format!("b{}le", len),
+                (false, false) => format!("b{}be", len)
+            }
+        };
+
+        if !chunk.whole {
+            let part = format!("{}_part_{}", name, chunk.value_shift);
+            attributes.push(Attribute { id: part, kind: ("type", bit_type(chunk.len)), enum_id: None, valid: None, doc: "", element: None });
+            if chunk.value_shift == 0 {
+                instances.push((name, field, enum_id, signed, true));
+            }
+            continue;
+        }
+
+        let kind = if let Some(nested) = layout.nested(field.name) {
+            ("type", format!("{}", SnakeCase(nested.name, false)))
+        } else if field.width > 64 {
+            ("size", format!("{}", field.width / 8))
+        } else if field.type_name == "bool" {
+            ("type", "b1".into())
+        } else if let (Some(le), 8 | 16 | 32 | 64) = (chunk.aligned_bytes, field.width) {
+            let endian = match (field.width, le) {
+                (8, _) => "",
+                (_, true) => "le",
+                (_, false) => "be"
+            };
+            ("type", format!("{}{}{}", if signed { "s" } else { "u" }, field.width / 8, endian))
+        } else if signed {
+            let raw = format!("{}_raw", name);
+            attributes.push(Attribute { id: raw, kind: ("type", bit_type(chunk.len)), enum_id: None, valid: None, doc: "", element: None });
+            instances.push((name, field, enum_id, signed, false));
+            continue;
+        } else {
+            ("type", bit_type(field.width))
+        };
+
+        let valid = match field.reserved {
+            Some(true) => Some(u128::MAX >> (128 - field.width)),
+            Some(false) => Some(0),
+            None => None
+        };
+        let element = match array_name(field.name) {
+            (base, Some(index)) => Some((format!("{}", SnakeCase(base, false)), index)),
+            _ => None
+        };
+        attributes.push(Attribute { id: name, kind, enum_id, valid, doc: field.doc, element });
+    }
+    write_gap(&mut attributes, position, layout.packed_bytes * 8);
+
+    writeln!(w, "{}seq:", indent)?;
+    let mut i = 0;
+    while i < attributes.len() {
+        let attribute = &attributes[i];
+        let repeat = match attribute.element {
+            Some((ref base, 0)) => attributes[i..].iter().enumerate()
+                .take_while(|(n, a)| matches!(a.element, Some((ref b, index)) if b == base && index == *n) && a.kind == attribute.kind)
+                .count(),
+            _ => 1
+        };
+
+        match attribute.element {
+            Some((ref base, _)) if repeat > 1 => writeln!(w, "{}  - id: {}", indent, base)?,
+            _ => writeln!(w, "{}  - id: {}", indent, attribute.id)?
+        }
+        writeln!(w, "{}    {}: {}", indent, attribute.kind.0, attribute.kind.1)?;
+        if let Some(ref enum_id) = attribute.enum_id {
+            writeln!(w, "{}    enum: {}", indent, enum_id)?;
+        }
+        if let Some(valid) = attribute.valid {
+            writeln!(w, "{}    valid: {}", indent, valid)?;
+        }
+        if repeat > 1 {
+            writeln!(w, "{}    repeat: expr", indent)?;
+            writeln!(w, "{}    repeat-expr: {}", indent, repeat)?;
+        }
+        if !attribute.doc.is_empty() {
+            writeln!(w, "{}    doc: {}", indent, YamlStr(attribute.doc))?;
+        }
+        i += repeat;
+    }
+
+    if !instances.is_empty() {
+        writeln!(w, "{}instances:", indent)?;
+        for (name, field, enum_id, signed, from_parts) in instances {
+            let raw = match from_parts {
+                false => format!("{}_raw", name),
+                true => {
+                    let mut parts: Vec<_> = chunks.iter().filter(|c| core::ptr::eq(c.field, field)).map(|c| c.value_shift).collect();
+                    parts.sort_unstable_by(|a, b| b.cmp(a));
+                    let terms: Vec<_> = parts.iter().map(|&shift| match shift {
+                        0 => format!("{}_part_0", name),
+                        _ => format!("({}_part_{} << {})", name, shift, shift)
+                    }).collect();
+                    terms.join(" | ")
+                }
+            };
+            let sign = 1u128 << (field.width - 1);
+            let value = match (signed, from_parts) {
+                (true, true) => format!("(({}) ^ {}) - {}", raw, sign, sign),
+                (true, false) => format!("({} ^ {}) - {}", raw, sign, sign),
+                (false, _) => raw
+            };
+
+            writeln!(w, "{}  {}:", indent, name)?;
+            writeln!(w, "{}    value: '{}'", indent, value)?;
+            if let Some(enum_id) = enum_id {
+                writeln!(w, "{}    enum: {}", indent, enum_id)?;
+            }
+            if !field.doc.is_empty() {
+                writeln!(w, "{}    doc: {}", indent, YamlStr(field.doc))?;
+            }
+        }
+    }
+
+    let mut enums: Vec<(String, &FieldInfo)> = Vec::new();
+    for field in layout.fields.iter().filter(|f| !f.variants.is_empty()) {
+        let enum_id = format!("{}", SnakeCase(array_name(field.name).0, false));
+        if !enums.iter().any(|(id, _)| *id == enum_id) {
+            enums.push((enum_id, field));
+        }
+    }
+    if !enums.is_empty() {
+        writeln!(w, "{}enums:", indent)?;
+        for (enum_id, field) in enums {
+            writeln!(w, "{}  {}:", indent, enum_id)?;
+            for variant in field.variants {
+                writeln!(w, "{}    {}: {}", indent, variant.value, SnakeCase(variant.name, false))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the unused bits between two positions.
+fn write_gap(attributes: &mut Vec<Attribute>, from: usize, to: usize) {
+    let mut gap = |start: usize, kind: (&'static str, String)| {
+        attributes.push(Attribute { id: format!("gap_{}", start), kind, enum_id: None, valid: None, doc: "", element: None });
+    };
+
+    let mut position = from;
+    if position < to && !position.is_multiple_of(8) {
+        let len = min(8 - position % 8, to - position);
+        gap(position, ("type", format!("b{}", len)));
+        position += len;
+    }
+    if to - position >= 8 {
+        let bytes = (to - position) / 8;
+        gap(position, ("size", format!("{}", bytes)));
+        position += bytes * 8;
+    }
+    if position < to {
+        gap(position, ("type", format!("b{}", to - position)));
+    }
+}
+
+/// Nested structures read as their own type: their bits are whole bytes, in order.
+fn is_aligned_nested(layout: &StructLayout, field: &FieldInfo, placement: &FieldPlacement) -> bool {
+    match layout.nested(field.name) {
+        Some(nested) => placement.window == Some((false, 0)) && field.width == nested.packed_bytes * 8,
+        None => false
+    }
+}
+
+/// A double-quoted YAML string.
+struct YamlStr<'a>(&'a str);
+
+impl<'a> Display for YamlStr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\t' => f.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\x{:02x}", c as u32)?,
+                c => f.write_char(c)?
+            }
+        }
+        f.write_char('"')
+    }
+}
@@ -0,0 +1,400 @@
+//! Static layout metadata of packed structures, for tools that need the
+//! packing table as data. Available in `no_std`, as is the JSON description
+//! of the layout, for tools written in other languages.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::layout::{BitNumbering, PackedStructLayout};
+//!
+//! #[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+//! pub enum Mode {
+//!     Idle = 0,
+//!     Active = 2
+//! }
+//!
+//! #[derive(PackedStruct)]
+//! #[packed_struct(bit_numbering="lsb0", size_bytes="1")]
+//! pub struct Control {
+//!     /// Selected input channel
+//!     #[packed_field(bits="7:4")]
+//!     channel: Integer<u8, packed_bits::Bits4>,
+//!     #[packed_field(bits="1:0", ty="enum")]
+//!     mode: Mode
+//! }
+//!
+//! let mode = Control::field("mode").unwrap();
+//! assert_eq!(6..7, mode.bits);
+//! assert_eq!(0..1, mode.declared_bits);
+//! assert_eq!(BitNumbering::Lsb0, Control::BIT_NUMBERING);
+//! assert_eq!("Active", mode.variants[1].name);
+//! assert_eq!("Selected input channel", Control::FIELDS[0].doc);
+//! ```
+
+use crate::visit::IntegerEndianness;
+use crate::types::{BitOne, BitZero, ReservedBits};
+use crate::FieldAccess;
+use crate::export::is_signed;
+use crate::internal_prelude::v1::*;
+
+/// https://en.wikipedia.org/wiki/Bit_numbering
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize), serde(rename_all = "lowercase"))]
+pub enum BitNumbering {
+    Msb0,
+    Lsb0,
+    /// Bit `n` is bit `n % 8` of byte `n / 8`, counted from the least
+    /// significant bit, as in CAN DBC files. Big-endian fields are declared
+    /// from their most significant bit, little-endian fields from their
+    /// least significant bit.
+    Sawtooth,
+}
+
+/// A variant of a primitive enum.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EnumVariantInfo {
+    pub name: &'static str,
+    pub value: i128,
+}
+
+/// A field of a packed structure. Array elements are separate fields, named like `data[1]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// MSB0 inclusive bit range.
+    pub bits: Range<usize>,
+    /// The inclusive bit range in the structure's declared numbering, lowest bit first.
+    pub declared_bits: Range<usize>,
+    pub width: usize,
+    /// Byte order of integers wider than a byte, and of little-endian fields
+    /// of sawtooth-numbered structures.
+    pub endianness: Option<IntegerEndianness>,
+    /// The field's type, as written in the structure.
+    pub type_name: &'static str,
+    /// Variants of primitive enum fields, empty for other fields.
+    pub variants: &'static [EnumVariantInfo],
+    /// The bit reserved fields are always packed with, `None` for other fields.
+    pub reserved: Option<bool>,
+    pub access: FieldAccess,
+    /// The field's doc comment, lines separated with `\n`.
+    pub doc: &'static str,
+}
+
+impl FieldInfo {
+    /// The field's bits as `(value bit, position)` pairs, where the value bit
+    /// counts from the least significant bit of the field's value and the
+    /// position is the MSB0 bit in the packed bytes. Resolves the byte order
+    /// of little-endian fields and the actual bits of little-endian sawtooth
+    /// fields, whose `bits` only span them.
+    ///
+    /// A little-endian field whose width isn't a multiple of 8 bits is packed
+    /// as the trailing bits of its little-endian bytes, so the leading bits of
+    /// the lowest byte are cut off. For a 20 bit field, value bits 4..7 aren't
+    /// stored and the mapping lists the value bits 20..23 in their place, which
+    /// are packed from the bytes' zero padding. The mapping always matches how
+    /// the structure is packed and unpacked.
+    pub fn bit_map(&self, bit_numbering: BitNumbering) -> impl Iterator<Item = (usize, usize)> {
+        let (start, width, low) = (self.bits.start, self.width, self.declared_bits.start);
+        let sawtooth_lsb = bit_numbering == BitNumbering::Sawtooth && self.bits.end - start + 1 > width;
+        let lsb = self.endianness == Some(IntegerEndianness::Lsb);
+        let num_bytes = width.div_ceil(8);
+
+        (0..width).map(move |i| {
+            if sawtooth_lsb {
+                let n = low + i;
+                (i, (n / 8) * 8 + 7 - n % 8)
+            } else if lsb {
+                let q = i + num_bytes * 8 - width;
+                ((q / 8) * 8 + 7 - q % 8, start + i)
+            } else {
+                (width - 1 - i, start + i)
+            }
+        })
+    }
+}
+
+/// The layout of a packed structure, implemented by `#[derive(PackedStruct)]`.
+pub trait PackedStructLayout {
+    /// The name of the structure.
+    const NAME: &'static str;
+    const FIELDS: &'static [FieldInfo];
+    const BIT_NUMBERING: BitNumbering;
+    const PACKED_BITS: usize;
+    const PACKED_BYTES: usize;
+
+    /// The layout of a field's nested packed structure.
+    fn nested_layout(field: &str) -> Option<StructLayout>;
+
+    /// Find a field by its name.
+    fn field(name: &str) -> Option<&'static FieldInfo> {
+        Self::FIELDS.iter().find(|f| f.name == name)
+    }
+
+    /// Write the layout as JSON, see `StructLayout::write_json`.
+    fn write_layout_json<W: fmt::Write + ?Sized>(w: &mut W) -> fmt::Result where Self: Sized {
+        StructLayout::of::<Self>().write_json(w)
+    }
+
+    /// The layout as JSON, see `StructLayout::write_json`.
+    #[cfg(any(feature="alloc", feature="std"))]
+    fn layout_json() -> String where Self: Sized {
+        let mut s = String::new();
+        // writing into a string doesn't fail
+        let _ = Self::write_layout_json(&mut s);
+        s
+    }
+
+    /// Write a C header for the structure, see the `c_header` module.
+    fn write_c_header<W: fmt::Write + ?Sized>(w: &mut W) -> fmt::Result where Self: Sized {
+        StructLayout::of::<Self>().write_c_header(w)
+    }
+
+    /// A C header for the structure, see the `c_header` module.
+    #[cfg(any(feature="alloc", feature="std"))]
+    fn c_header() -> String where Self: Sized {
+        let mut s = String::new();
+        // writing into a string doesn't fail
+        let _ = Self::write_c_header(&mut s);
+        s
+    }
+
+    /// A Kaitai Struct description of the structure, see the `kaitai` module.
+    #[cfg(any(feature="alloc", feature="std"))]
+    fn ksy() -> String where Self: Sized {
+        let mut s = String::new();
+        // writing into a string doesn't fail
+        let _ = StructLayout::of::<Self>().write_ksy(&mut s);
+        s
+    }
+
+    /// A Python module with a dataclass for the structure, see the `python` module.
+    #[cfg(any(feature="alloc", feature="std"))]
+    fn python() -> String where Self: Sized {
+        crate::python::PythonModule::new(StructLayout::of::<Self>()).to_python()
+    }
+}
+
+/// The layout of a packed structure as a value, including the layouts of its
+/// nested structures.
+#[derive(Copy, Clone)]
+pub struct StructLayout {
+    pub name: &'static str,
+    pub fields: &'static [FieldInfo],
+    pub bit_numbering: BitNumbering,
+    pub packed_bits: usize,
+    pub packed_bytes: usize,
+    nested: fn(&str) -> Option<StructLayout>,
+}
+
+impl StructLayout {
+    pub fn of<T: PackedStructLayout>() -> Self {
+        StructLayout {
+            name: T::NAME,
+            fields: T::FIELDS,
+            bit_numbering: T::BIT_NUMBERING,
+            packed_bits: T::PACKED_BITS,
+            packed_bytes: T::PACKED_BYTES,
+            nested: T::nested_layout
+        }
+    }
+
+    /// The layout of a field's nested packed structure.
+    pub fn nested(&self, field: &str) -> Option<StructLayout> {
+        (self.nested)(field)
+    }
+
+    /// Write the layout as indented JSON. Bit ranges are inclusive `[start, end]`
+    /// pairs, `bits` in MSB0 numbering and `declared_bits` in the structure's
+    /// declared numbering. Nested structures are written inline. The layout can
+    /// be loaded as a `DynamicLayout`, when the `json` feature is enabled.
+    ///
+    /// ```rust
+    /// use packed_struct::prelude::*;
+    /// use packed_struct::layout::PackedStructLayout;
+    ///
+    /// #[derive(PackedStruct)]
+    /// #[packed_struct(bit_numbering="msb0")]
+    /// pub struct Status {
+    ///     #[packed_field(bits="0")]
+    ///     busy: bool
+    /// }
+    ///
+    /// let mut json = String::new();
+    /// Status::write_layout_json(&mut json).unwrap();
+    /// assert!(json.starts_with("{\n  \"name\": \"Status\",\n  \"size_bytes\": 1,"));
+    /// ```
+    pub fn write_json<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        self.write_json_indented(w, 0)
+    }
+
+    fn write_json_indented<W: fmt::Write + ?Sized>(&self, w: &mut W, level: usize) -> fmt::Result {
+        let bit_numbering = match self.bit_numbering {
+            BitNumbering::Msb0 => "msb0",
+            BitNumbering::Lsb0 => "lsb0",
+            BitNumbering::Sawtooth => "sawtooth",
+        };
+
+        w.write_char('{')?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"name\": {},", JsonStr(self.name))?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"size_bytes\": {},", self.packed_bytes)?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"size_bits\": {},", self.packed_bits)?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"bit_numbering\": \"{}\",", bit_numbering)?;
+        json_indent(w, level + 1)?;
+        w.write_str("\"fields\": [")?;
+
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                w.write_char(',')?;
+            }
+            json_indent(w, level + 2)?;
+            self.write_field_json(w, field, level + 2)?;
+        }
+
+        if !self.fields.is_empty() {
+            json_indent(w, level + 1)?;
+        }
+        w.write_char(']')?;
+        json_indent(w, level)?;
+        w.write_char('}')
+    }
+
+    fn write_field_json<W: fmt::Write + ?Sized>(&self, w: &mut W, field: &FieldInfo, level: usize) -> fmt::Result {
+        let endianness = match field.endianness {
+            Some(IntegerEndianness::Msb) => "\"msb\"",
+            Some(IntegerEndianness::Lsb) => "\"lsb\"",
+            None => "null"
+        };
+
+        w.write_char('{')?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"name\": {},", JsonStr(field.name))?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"bits\": [{}, {}],", field.bits.start, field.bits.end)?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"declared_bits\": [{}, {}],", field.declared_bits.start, field.declared_bits.end)?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"width\": {},", field.width)?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"endianness\": {},", endianness)?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"signed\": {},", is_signed(field))?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"type\": {},", JsonStr(field.type_name))?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"access\": \"{}\",", field.access.as_str())?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"reserved\": {},", field.reserved.is_some())?;
+        json_indent(w, level + 1)?;
+        write!(w, "\"doc\": {},", JsonStr(field.doc))?;
+        json_indent(w, level + 1)?;
+        w.write_str("\"variants\": [")?;
+        for (i, variant) in field.variants.iter().enumerate() {
+            if i > 0 {
+                w.write_str(", ")?;
+            }
+            write!(w, "{{\"name\": {}, \"value\": {}}}", JsonStr(variant.name), variant.value)?;
+        }
+        w.write_str("],")?;
+        json_indent(w, level + 1)?;
+        w.write_str("\"nested\": ")?;
+        match self.nested(field.name) {
+            Some(nested) => nested.write_json_indented(w, level + 1)?,
+            None => w.write_str("null")?
+        }
+        json_indent(w, level)?;
+        w.write_char('}')
+    }
+}
+
+impl Debug for StructLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StructLayout")
+            .field("name", &self.name)
+            .field("fields", &self.fields)
+            .field("bit_numbering", &self.bit_numbering)
+            .field("packed_bits", &self.packed_bits)
+            .field("packed_bytes", &self.packed_bytes)
+            .finish()
+    }
+}
+
+fn json_indent<W: fmt::Write + ?Sized>(w: &mut W, level: usize) -> fmt::Result {
+    w.write_char('\n')?;
+    for _ in 0..level {
+        w.write_str("  ")?;
+    }
+    Ok(())
+}
+
+/// A quoted and escaped JSON string.
+struct JsonStr<'a>(&'a str);
+
+impl<'a> Display for JsonStr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Finds the fill bit of reserved fields from their type. Used by the
+/// generated code, the inherent constant shadows the trait's default.
+#[doc(hidden)]
+pub struct ReservedFill<T>(pub PhantomData<T>);
+
+impl<B> ReservedFill<ReservedBits<BitZero, B>> {
+    pub const FILL: Option<bool> = Some(false);
+}
+
+impl<B> ReservedFill<ReservedBits<BitOne, B>> {
+    pub const FILL: Option<bool> = Some(true);
+}
+
+#[doc(hidden)]
+pub trait NotReserved {
+    const FILL: Option<bool> = None;
+}
+
+impl<T> NotReserved for ReservedFill<T> {}
+
+/// Finds the layout of nested structures. Used by the generated code, the
+/// most specific implementation is picked by auto-ref.
+#[doc(hidden)]
+pub struct LayoutOf<T>(pub PhantomData<T>);
+
+#[doc(hidden)]
+pub trait NestedLayout {
+    fn nested_layout(&self) -> Option<StructLayout>;
+}
+
+impl<T: PackedStructLayout> NestedLayout for LayoutOf<T> {
+    #[inline]
+    fn nested_layout(&self) -> Option<StructLayout> {
+        Some(StructLayout::of::<T>())
+    }
+}
+
+#[doc(hidden)]
+pub trait NoNestedLayout {
+    fn nested_layout(&self) -> Option<StructLayout>;
+}
+
+impl<T> NoNestedLayout for &LayoutOf<T> {
+    #[inline]
+    fn nested_layout(&self) -> Option<StructLayout> {
+        None
+    }
+}
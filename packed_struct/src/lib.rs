@@ -16,17 +16,39 @@
 //!  * Plain Rust structures, decorated with attributes
 //!  * MSB or LSB integers of user-defined bit widths
 //!  * Primitive enum code generation helper
-//!  * MSB0 or LSB0 bit positioning
+//!  * MSB0, LSB0 or sawtooth bit positioning, the last one for CAN signals
 //!  * Documents the field's packing table
 //!  * Runtime packing visualization
+//!  * ANSI-colored bit maps of the fields, for terminal debugging
+//!  * Allocation-free field formatting for `no_std` targets
+//!  * Field-level diffs between two values or raw buffers
+//!  * Field visitors for custom output formats, with bit ranges, raw bits and integer values
+//!  * Static layout metadata of the fields, for tools, also in `no_std`
+//!  * JSON layout descriptions, for firmware and test tools in other languages
+//!  * C headers with field masks, shifts and accessors, for firmware still written in C
+//!  * Wireshark Lua dissectors, to decode captures with the same layouts
+//!  * Kaitai Struct `.ksy` descriptions, for parsers in other languages and the Kaitai Web IDE
+//!  * Python modules with dataclasses and test vectors, for test tools written in Python
+//!  * Hex and bit string parsing and formatting, for datasheet examples
+//!  * CSV export and import of many records, one column per field
+//!  * Dynamic field access by name, for scripting consoles and test benches
+//!  * Runtime-defined layouts, for messages only known from configuration files
 //!  * Nested packed types
 //!  * Arrays of packed structures as fields
 //!  * Reserved fields, their bits are always 0 or 1
+//!  * Byte-at-a-time decoding for interrupt-driven input
+//!  * Typed register maps over a byte-addressed bus, with an in-memory bus simulator
+//!  * Volatile access to memory-mapped registers
+//!  * Register structures generated from CMSIS-SVD files by the `packed_struct_build` crate
+//!  * CAN message structures generated from DBC files by the `packed_struct_build` crate
+//!  * Lock-free atomic storage of small structures, for flags shared with interrupt handlers
 //!
 //! # Crate-level feature flags
 //!  * `std`: use the Rust standard library. Default.
 //!  * `alloc`: use the `alloc` crate for `no_std` + `alloc` scenarios. Requires nightly Rust.
 //!  * `use_serde`: add serialization support to the built-in helper types.
+//!  * `embedded_hal`: I2C and SPI register devices on top of `embedded-hal` 1.0.
+//!  * `json`, `toml`: load runtime-defined layouts from JSON or TOML files.
 //!  * `byte_types_64`, `byte_types_256`: enlarge the size of the generated array, byte and bit width types.
 //!
 //! # Sample usage
@@ -118,8 +140,10 @@
 //! Attribute | Values | Comment
 //! :--|:--|:--
 //! ```size_bytes``` | ```1``` ... n | Size of the packed byte stream
-//! ```bit_numbering``` | ```msb0``` or ```lsb0``` | Bit numbering for bit positioning of fields. Required if the bits attribute field is used.
+//! ```bit_numbering``` | ```msb0```, ```lsb0``` or ```sawtooth``` | Bit numbering for bit positioning of fields. Required if the bits attribute field is used. ```lsb0``` and ```sawtooth``` also require ```size_bytes```.
 //! ```endian``` | ```msb``` or ```lsb``` | Default integer endianness
+//! ```display``` | ```table```, ```compact``` or ```none``` | The generated ```Display``` implementation. ```none``` leaves it to the user. Default: ```table```
+//! ```atomic_fields``` | A trait name | Generates the trait with ```set_<field>``` and ```update_<field>``` methods for ```AtomicPacked```, which only exchange the field's bits. Fields starting with an underscore are left out. Structures of up to 8 bytes.
 //!
 //! ## Per-field attributes
 //!
@@ -133,6 +157,7 @@
 //! ```element_size_bytes``` | ```1```, ... | Same as above, multiplied by 8.
 //! ```ty``` | ```enum``` | Packing helper for primitive enums.
 //! ```endian``` | ```msb``` or ```lsb``` | Integer endianness. Applies to u16/i16 and larger types.
+//! ```access``` | ```rw```, ```ro```, ```wo```, ```w1c``` or ```rc``` | Access semantics of the field within a hardware register. Used by the documentation, the runtime formatter and ```PackedStructAccess::pack_write```.
 //!
 //! ## Bit and byte positioning
 //!
@@ -194,6 +219,37 @@
 //! }
 //! ```
 //!
+//! ## CAN signals
+//!
+//! With ```sawtooth``` bit numbering, fields are positioned like the signals of
+//! CAN DBC files. Bit n is bit n % 8 of byte n / 8, counted from the least
+//! significant bit. Little-endian fields start at their least significant bit,
+//! like Intel signals, and big-endian fields at their most significant bit, like
+//! Motorola signals, which continue from bit 7 of the next byte.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//!
+//! #[derive(PackedStruct)]
+//! #[packed_struct(bit_numbering="sawtooth", size_bytes="4")]
+//! pub struct WheelSpeeds {
+//!     #[packed_field(bits="4..=15", endian="lsb")]
+//!     front: Integer<u16, packed_bits::Bits12>,
+//!     #[packed_field(bits="19..=24", endian="msb")]
+//!     rear: Integer<u16, packed_bits::Bits12>
+//! }
+//!
+//! fn main() -> Result<(), PackingError> {
+//!     let speeds = WheelSpeeds {
+//!         front: 0xABC.into(),
+//!         rear: 0x123.into()
+//!     };
+//!
+//!     assert_eq!([0xC0, 0xAB, 0x01, 0x23], speeds.pack()?);
+//!     Ok(())
+//! }
+//! ```
+//!
 //! ## Nested packed types
 //!
 //! ```rust
@@ -317,6 +373,55 @@
 //!
 //! # fn main() {}
 //! ```
+//!
+//! # Register maps
+//!
+//! Registers of a device can be collected into a register map. The derive generates typed
+//! `read_<register>`, `write_<register>` and `modify_<register>` functions that go through
+//! any implementation of the `RegisterBus` trait. `MemoryBus` simulates a device in tests.
+//!
+//! Read-only registers get no write functions and write-only registers no read functions.
+//! Registers with the same or overlapping addresses are rejected at compile time.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::register_map::MemoryBus;
+//!
+//! #[derive(PackedStruct, Debug, PartialEq)]
+//! #[packed_struct(bit_numbering="msb0")]
+//! pub struct Status {
+//!     #[packed_field(bits="0")]
+//!     ready: bool,
+//!     #[packed_field(bits="4:7")]
+//!     errors: Integer<u8, packed_bits::Bits4>
+//! }
+//!
+//! #[derive(PackedStruct, Debug, PartialEq)]
+//! #[packed_struct(endian="lsb")]
+//! pub struct Threshold {
+//!     value: u16
+//! }
+//!
+//! #[derive(RegisterMap)]
+//! pub struct Device {
+//!     #[register(address=0x10, access="ro")]
+//!     status: Status,
+//!     #[register(address=0x12, access="rw")]
+//!     threshold: Threshold
+//! }
+//!
+//! fn main() -> Result<(), PackingError> {
+//!     let mut bus = MemoryBus::new(0x20);
+//!     bus.memory_mut()[0x10] = 0x83;
+//!
+//!     let status = Device::read_status(&mut bus).unwrap();
+//!     assert_eq!(Status { ready: true, errors: 3.into() }, status);
+//!
+//!     Device::modify_threshold(&mut bus, |t| t.value = 0x1234).unwrap();
+//!     assert_eq!(&[0x34, 0x12], &bus.memory()[0x12..0x14]);
+//!     Ok(())
+//! }
+//! ```
 //! [crates-badge]: https://img.shields.io/crates/v/packed_struct.svg
 //! [crates-url]: https://crates.io/crates/packed_struct
 
@@ -334,6 +439,10 @@ extern crate serde;
 #[cfg(feature = "use_serde")]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "toml")]
+extern crate toml;
 
 mod internal_prelude;
 
@@ -344,9 +453,55 @@ mod primitive_enum;
 
 pub use primitive_enum::*;
 
-#[cfg(any(feature = "alloc", feature = "std"))]
+mod accumulator;
+
+pub use accumulator::*;
+
+mod access;
+
+pub use access::*;
+
+mod text;
+
+pub use text::*;
+
+pub mod register_map;
+
+pub mod mmio;
+
+pub mod atomic;
+
+#[cfg(feature = "embedded_hal")]
+pub mod hal;
+
 pub mod debug_fmt;
 
+pub mod visit;
+
+pub mod layout;
+
+mod export;
+
+pub mod c_header;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod wireshark;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod kaitai;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod python;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod csv;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod dynamic;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod dynamic_layout;
+
 mod types_array;
 mod types_basic;
 mod types_bits;
@@ -382,6 +537,7 @@ pub use self::packing::*;
 pub mod derive {
     pub use packed_struct_codegen::PackedStruct;
     pub use packed_struct_codegen::PrimitiveEnum;
+    pub use packed_struct_codegen::RegisterMap;
     pub use packed_struct_codegen::{
         PrimitiveEnum_i128, PrimitiveEnum_i16, PrimitiveEnum_i32, PrimitiveEnum_i64,
         PrimitiveEnum_i8,
@@ -397,7 +553,7 @@ pub mod prelude {
 
     pub use super::derive::*;
 
-    pub use crate::{PackedStruct, PackedStructSlice, PackingError};
+    pub use crate::{PackedStruct, PackedStructSlice, PackedStructText, PackingError};
 
     pub use crate::PrimitiveEnum;
     #[cfg(any(feature = "alloc", feature = "std"))]
@@ -408,6 +564,8 @@ pub mod prelude {
 
     pub use crate::EnumCatchAll;
 
+    pub use crate::register_map::RegisterBus;
+
     pub use crate::types::bits as packed_bits;
     pub use crate::types::*;
 }
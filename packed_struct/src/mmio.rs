@@ -0,0 +1,237 @@
+//! Volatile access to memory-mapped registers.
+
+use crate::internal_prelude::v1::*;
+use crate::access::PackedStructAccess;
+use crate::packing::{PackedStruct, PackingError, PackingResult};
+use crate::types_bits::ByteArray;
+
+/// Width of a single bus access.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessWidth {
+    Bits8,
+    Bits16,
+    Bits32,
+}
+
+impl AccessWidth {
+    /// Number of bytes transferred by a single access.
+    pub fn bytes(&self) -> usize {
+        match *self {
+            AccessWidth::Bits8 => 1,
+            AccessWidth::Bits16 => 2,
+            AccessWidth::Bits32 => 4,
+        }
+    }
+}
+
+/// How the bytes of a single bus access map onto the structure's byte array.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WordOrder {
+    /// The byte array mirrors the memory, its first byte is at the lowest address.
+    Memory,
+    /// The value of every accessed word is stored MSB first, the way registers
+    /// are drawn in data sheets. Independent of the CPU's endianness.
+    Msb,
+    /// The value of every accessed word is stored LSB first.
+    Lsb,
+}
+
+/// A packed structure that lives at a fixed memory address, for instance a
+/// peripheral register. All accesses are volatile.
+///
+/// ```rust
+/// use packed_struct::prelude::*;
+/// use packed_struct::mmio::{AccessWidth, Mmio, WordOrder};
+///
+/// #[derive(PackedStruct, Debug, PartialEq)]
+/// #[packed_struct(bit_numbering="msb0")]
+/// pub struct Control {
+///     #[packed_field(bits="0")]
+///     enabled: bool,
+///     #[packed_field(bits="24:31")]
+///     prescaler: u8
+/// }
+///
+/// fn main() -> Result<(), PackingError> {
+///     // a 32-bit register, simulated on the heap
+///     let mut memory = vec![0x8000_0010_u32];
+///     let reg = unsafe { Mmio::<Control>::from_ptr(memory.as_mut_ptr() as *mut u8) }
+///         .with_access_width(AccessWidth::Bits32)?
+///         .with_word_order(WordOrder::Msb);
+///
+///     assert_eq!(Control { enabled: true, prescaler: 0x10 }, reg.read()?);
+///     reg.modify(|c| c.prescaler = 0x20)?;
+///     assert_eq!(0x8000_0020, memory[0]);
+///     Ok(())
+/// }
+/// ```
+pub struct Mmio<T: PackedStruct> {
+    ptr: *mut u8,
+    width: AccessWidth,
+    order: WordOrder,
+    packed: PhantomData<T>,
+}
+
+impl<T: PackedStruct> Mmio<T> {
+    /// Access the structure at the pointer, byte by byte.
+    ///
+    /// # Safety
+    ///
+    /// The pointer has to be valid for volatile reads and writes of the structure's
+    /// byte array for as long as this wrapper is used.
+    pub unsafe fn from_ptr(ptr: *mut u8) -> Self {
+        Mmio {
+            ptr,
+            width: AccessWidth::Bits8,
+            order: WordOrder::Memory,
+            packed: PhantomData,
+        }
+    }
+
+    /// Access the structure at a raw address, byte by byte.
+    ///
+    /// # Safety
+    ///
+    /// Same as `from_ptr`.
+    pub unsafe fn from_address(address: usize) -> Self {
+        Self::from_ptr(address as *mut u8)
+    }
+
+    /// Use bus accesses of the given width. The address has to be aligned to the width,
+    /// `InvalidValue` otherwise, and the size of the structure has to be a multiple of it.
+    pub fn with_access_width(mut self, width: AccessWidth) -> PackingResult<Self> {
+        if (self.ptr as usize) & (width.bytes() - 1) != 0 {
+            return Err(PackingError::InvalidValue);
+        }
+
+        let size = <T::ByteArray as ByteArray>::len();
+        if size % width.bytes() != 0 {
+            return Err(PackingError::BufferModMismatch {
+                actual_size: size,
+                modulo_required: width.bytes(),
+            });
+        }
+
+        self.width = width;
+        Ok(self)
+    }
+
+    /// Map the bytes of every access onto the structure's byte array in this order.
+    pub fn with_word_order(mut self, order: WordOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    pub fn access_width(&self) -> AccessWidth {
+        self.width
+    }
+
+    pub fn word_order(&self) -> WordOrder {
+        self.order
+    }
+
+    /// Read the raw bytes of the structure.
+    pub fn read_bytes(&self) -> T::ByteArray {
+        let mut bytes = <T::ByteArray as ByteArray>::new(0);
+        let width = self.width.bytes();
+        for (i, word) in bytes.as_mut_bytes_slice().chunks_mut(width).enumerate() {
+            // Safety: the constructor's contract and the checks in `with_access_width`.
+            unsafe {
+                let ptr = self.ptr.add(i * width);
+                match self.width {
+                    AccessWidth::Bits8 => word[0] = ptr.read_volatile(),
+                    AccessWidth::Bits16 => {
+                        let v = (ptr as *const u16).read_volatile();
+                        word.copy_from_slice(&match self.order {
+                            WordOrder::Memory => v.to_ne_bytes(),
+                            WordOrder::Msb => v.to_be_bytes(),
+                            WordOrder::Lsb => v.to_le_bytes(),
+                        });
+                    }
+                    AccessWidth::Bits32 => {
+                        let v = (ptr as *const u32).read_volatile();
+                        word.copy_from_slice(&match self.order {
+                            WordOrder::Memory => v.to_ne_bytes(),
+                            WordOrder::Msb => v.to_be_bytes(),
+                            WordOrder::Lsb => v.to_le_bytes(),
+                        });
+                    }
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Write the raw bytes of the structure.
+    pub fn write_bytes(&self, bytes: &T::ByteArray) {
+        let width = self.width.bytes();
+        for (i, word) in bytes.as_bytes_slice().chunks(width).enumerate() {
+            // Safety: the constructor's contract and the checks in `with_access_width`.
+            unsafe {
+                let ptr = self.ptr.add(i * width);
+                match self.width {
+                    AccessWidth::Bits8 => ptr.write_volatile(word[0]),
+                    AccessWidth::Bits16 => {
+                        let word = [word[0], word[1]];
+                        let v = match self.order {
+                            WordOrder::Memory => u16::from_ne_bytes(word),
+                            WordOrder::Msb => u16::from_be_bytes(word),
+                            WordOrder::Lsb => u16::from_le_bytes(word),
+                        };
+                        (ptr as *mut u16).write_volatile(v);
+                    }
+                    AccessWidth::Bits32 => {
+                        let word = [word[0], word[1], word[2], word[3]];
+                        let v = match self.order {
+                            WordOrder::Memory => u32::from_ne_bytes(word),
+                            WordOrder::Msb => u32::from_be_bytes(word),
+                            WordOrder::Lsb => u32::from_le_bytes(word),
+                        };
+                        (ptr as *mut u32).write_volatile(v);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read and unpack the structure.
+    pub fn read(&self) -> PackingResult<T> {
+        T::unpack(&self.read_bytes())
+    }
+
+    /// Pack and write the structure.
+    pub fn write(&self, value: &T) -> PackingResult<()> {
+        self.write_bytes(&value.pack()?);
+        Ok(())
+    }
+
+    /// Read the structure, modify it and write it back. Returns the written value.
+    pub fn modify<F: FnOnce(&mut T)>(&self, f: F) -> PackingResult<T> {
+        let mut value = self.read()?;
+        f(&mut value);
+        self.write(&value)?;
+        Ok(value)
+    }
+}
+
+impl<T: PackedStructAccess> Mmio<T> {
+    /// Read the structure, modify it and write it back while respecting the access
+    /// semantics of its fields. See `PackedStructAccess::pack_write`.
+    pub fn update<F: FnOnce(&mut T)>(&self, f: F) -> PackingResult<T> {
+        let current = self.read_bytes();
+        let mut value = T::unpack(&current)?;
+        f(&mut value);
+        self.write_bytes(&value.pack_write(&current)?);
+        Ok(value)
+    }
+}
+
+impl<T: PackedStruct> Debug for Mmio<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mmio({:p}, {:?}, {:?})", self.ptr, self.width, self.order)
+    }
+}
@@ -1,10 +1,14 @@
 use crate::internal_prelude::v1::*;
+use crate::layout::EnumVariantInfo;
 
 /// An enum type that can be packed or unpacked from a simple primitive integer.
 pub trait PrimitiveEnum where Self: Sized + Copy {
     /// The primitve type into which we serialize and deserialize ourselves.
     type Primitive: PartialEq + Sized + Copy + Debug;
 
+    /// Names and values of the variants, generated by the derive.
+    const VARIANTS: &'static [EnumVariantInfo] = &[];
+
     /// Convert from a primitive, might fail.
     fn from_primitive(val: Self::Primitive) -> Option<Self>;
     /// Convert to a primitive value.
@@ -65,6 +69,8 @@ impl<E> PrimitiveEnum for EnumCatchAll<E>
 {
     type Primitive = E::Primitive;
 
+    const VARIANTS: &'static [EnumVariantInfo] = E::VARIANTS;
+
     fn from_primitive(val: E::Primitive) -> Option<Self> {
         match E::from_primitive(val) {
             Some(p) => Some(EnumCatchAll::Enum(p)),
@@ -0,0 +1,570 @@
+//! Python modules for packed structures, for test tools written in Python.
+//!
+//! The module only needs the standard library. Every structure, nested ones
+//! included, becomes a dataclass with a `pack()` method and an `unpack()`
+//! class method that put the bits in the same places as the Rust code, with
+//! the same byte order and sign extension. Primitive enums become
+//! `enum.IntEnum`s, arrays are lists and reserved fields are left out, they
+//! are packed as their fixed bits and ignored when unpacking. As in Rust,
+//! packing keeps only the bits that fit in a field, and unpacking an unknown
+//! enum value raises a `ValueError`, unless the field is an `EnumCatchAll`.
+//!
+//! Test vectors, structures packed by the Rust code, are written into the
+//! module with the values that the Rust code unpacks them to. Its
+//! `self_test()` function, also run when the module is executed as a script,
+//! checks that they unpack and pack back the same way in Python.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::layout::StructLayout;
+//! use packed_struct::python::PythonModule;
+//!
+//! #[derive(PackedStruct)]
+//! #[packed_struct(bit_numbering="msb0", endian="msb")]
+//! pub struct Ping {
+//!     #[packed_field(bits="0")]
+//!     reply: bool,
+//!     #[packed_field(bits="4:15")]
+//!     sequence: Integer<u16, packed_bits::Bits12>
+//! }
+//!
+//! # fn main() -> Result<(), PackingError> {
+//! let ping = Ping { reply: true, sequence: 300.into() };
+//! let python = PythonModule::new(StructLayout::of::<Ping>())
+//!     .with_test_vector(&ping)?
+//!     .to_python();
+//! assert!(python.contains("            sequence=n & 0xFFF,\n"));
+//! assert!(python.contains("        bytes.fromhex(\"812c\"),\n        Ping(reply=True, sequence=300),\n"));
+//! # Ok(())
+//! # }
+//! ```
+
+use core::cmp::Ordering;
+
+use crate::internal_prelude::v1::*;
+use crate::export::{array_name, is_signed, SnakeCase};
+use crate::layout::{FieldInfo, StructLayout};
+use crate::packing::{PackingError, PackingResult};
+use crate::types_bits::ByteArray;
+use crate::visit::{IntegerValue, PackedStructVisit, VisitContext, VisitField, Visitor};
+
+/// Generates a Python module with a dataclass for a structure and its nested structures.
+pub struct PythonModule {
+    layout: StructLayout,
+    test_vectors: Vec<TestVector>
+}
+
+/// Packed bytes and the values of their fields, as unpacked by the Rust code.
+struct TestVector {
+    packed: Vec<u8>,
+    values: TestValues
+}
+
+impl PythonModule {
+    pub fn new(layout: StructLayout) -> Self {
+        PythonModule {
+            layout,
+            test_vectors: Vec::new()
+        }
+    }
+
+    /// Add a test vector, the structure packed by the Rust code. The expected
+    /// values are the fields of the structure unpacked from those bytes.
+    pub fn with_test_vector<T: PackedStructVisit>(mut self, value: &T) -> PackingResult<Self> {
+        let packed = value.pack()?;
+        let unpacked = T::unpack(&packed)?;
+        let packed = packed.as_bytes_slice();
+        if packed.len() != self.layout.packed_bytes {
+            return Err(PackingError::BufferSizeMismatch { expected: self.layout.packed_bytes, actual: packed.len() });
+        }
+
+        let mut values = TestValues(Vec::new());
+        unpacked.visit_fields(&VisitContext::new(packed), None, &mut values)?;
+        self.test_vectors.push(TestVector { packed: packed.into(), values });
+        Ok(self)
+    }
+
+    /// Write the Python module.
+    pub fn write_python<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        let mut layouts = Vec::new();
+        collect_nested(&self.layout, &mut layouts);
+        layouts.push(self.layout);
+        let fields = || layouts.iter().flat_map(|l| l.fields.iter().filter(|f| f.reserved.is_none()).map(move |f| (l, f)));
+
+        writeln!(w, "# Packing of the {} packed structure. Generated, don't edit.", self.layout.name)?;
+        writeln!(w)?;
+        if fields().any(|(_, f)| !f.variants.is_empty()) {
+            writeln!(w, "import enum")?;
+        }
+        if fields().any(|(l, f)| l.nested(f.name).is_some() || array_name(f.name).1.is_some()) {
+            writeln!(w, "from dataclasses import dataclass, field")?;
+        } else {
+            writeln!(w, "from dataclasses import dataclass")?;
+        }
+        let typing: Vec<_> = [
+            ("List", fields().any(|(_, f)| array_name(f.name).1.is_some())),
+            ("Union", fields().any(|(_, f)| is_catch_all(f)))
+        ].iter().filter(|(_, used)| *used).map(|(name, _)| *name).collect();
+        if !typing.is_empty() {
+            writeln!(w, "from typing import {}", typing.join(", "))?;
+        }
+
+        if fields().any(|(l, f)| is_signed(f) && l.nested(f.name).is_none()) {
+            writeln!(w)?;
+            writeln!(w)?;
+            writeln!(w, "def _signed(value, bits):")?;
+            writeln!(w, "    \"\"\"Sign-extends a value of the given width.\"\"\"")?;
+            writeln!(w, "    return value - (1 << bits) if value >> (bits - 1) else value")?;
+        }
+        if fields().any(|(_, f)| is_catch_all(f)) {
+            writeln!(w)?;
+            writeln!(w)?;
+            writeln!(w, "def _enum_or_int(enum_type, value):")?;
+            writeln!(w, "    \"\"\"The enum's variant with the value, or the value if there's none.\"\"\"")?;
+            writeln!(w, "    try:")?;
+            writeln!(w, "        return enum_type(value)")?;
+            writeln!(w, "    except ValueError:")?;
+            writeln!(w, "        return value")?;
+        }
+
+        let mut enums: Vec<&str> = Vec::new();
+        for (_, field) in fields().filter(|(_, f)| !f.variants.is_empty()) {
+            let class = enum_class(field.type_name);
+            if enums.contains(&class) {
+                continue;
+            }
+            enums.push(class);
+            writeln!(w)?;
+            writeln!(w)?;
+            writeln!(w, "class {}(enum.IntEnum):", class)?;
+            for variant in field.variants {
+                writeln!(w, "    {} = {}", SnakeCase(variant.name, true), variant.value)?;
+            }
+        }
+
+        for layout in &layouts {
+            writeln!(w)?;
+            writeln!(w)?;
+            write_class(w, layout)?;
+        }
+
+        if !self.test_vectors.is_empty() {
+            writeln!(w)?;
+            writeln!(w)?;
+            writeln!(w, "# Packed by the Rust code, with the values they unpack to.")?;
+            writeln!(w, "TEST_VECTORS = [")?;
+            for vector in &self.test_vectors {
+                writeln!(w, "    (")?;
+                write!(w, "        bytes.fromhex(\"")?;
+                for byte in &vector.packed {
+                    write!(w, "{:02x}", byte)?;
+                }
+                writeln!(w, "\"),")?;
+                write!(w, "        ")?;
+                write_value(w, &self.layout, &vector.values, "")?;
+                writeln!(w, ",")?;
+                writeln!(w, "    ),")?;
+            }
+            writeln!(w, "]")?;
+            writeln!(w)?;
+            writeln!(w)?;
+            writeln!(w, "def self_test():")?;
+            writeln!(w, "    \"\"\"Checks that the test vectors unpack to their values and pack back to their bytes.\"\"\"")?;
+            writeln!(w, "    for data, value in TEST_VECTORS:")?;
+            writeln!(w, "        assert {}.unpack(data) == value, \"unpacking {{}}\".format(data.hex())", self.layout.name)?;
+            writeln!(w, "        assert value.pack() == data, \"packing {{}}\".format(data.hex())")?;
+            writeln!(w)?;
+            writeln!(w)?;
+            writeln!(w, "if __name__ == \"__main__\":")?;
+            writeln!(w, "    self_test()")?;
+        }
+        Ok(())
+    }
+
+    /// The Python module, see `write_python`.
+    pub fn to_python(&self) -> String {
+        let mut s = String::new();
+        // writing into a string doesn't fail
+        let _ = self.write_python(&mut s);
+        s
+    }
+}
+
+/// The layouts of the nested structures, each one once and after the
+/// structures it contains.
+fn collect_nested(layout: &StructLayout, found: &mut Vec<StructLayout>) {
+    for field in layout.fields {
+        if let Some(nested) = layout.nested(field.name) {
+            if !found.iter().any(|l| l.name == nested.name) {
+                collect_nested(&nested, found);
+                found.push(nested);
+            }
+        }
+    }
+}
+
+/// A dataclass attribute, a field or the elements of an array.
+struct Attribute<'a> {
+    name: &'a str,
+    fields: Vec<&'a FieldInfo>,
+    array: bool,
+    value: Value
+}
+
+/// The Python values of the fields.
+enum Value {
+    Bool,
+    Int { signed: bool },
+    Enum { signed: bool, catch_all: bool },
+    Nested(StructLayout)
+}
+
+fn attributes(layout: &StructLayout) -> Vec<Attribute<'_>> {
+    let mut attributes: Vec<Attribute> = Vec::new();
+    for field in layout.fields.iter().filter(|f| f.reserved.is_none()) {
+        let (name, index) = array_name(field.name);
+        if let Some(attribute) = attributes.last_mut() {
+            if attribute.array && attribute.name == name && index == Some(attribute.fields.len()) {
+                attribute.fields.push(field);
+                continue;
+            }
+        }
+
+        let value = if let Some(nested) = layout.nested(field.name) {
+            Value::Nested(nested)
+        } else if !field.variants.is_empty() {
+            Value::Enum { signed: is_signed(field), catch_all: is_catch_all(field) }
+        } else if field.type_name == "bool" {
+            Value::Bool
+        } else {
+            Value::Int { signed: is_signed(field) }
+        };
+        attributes.push(Attribute { name, fields: vec![field], array: index == Some(0), value });
+    }
+    attributes
+}
+
+fn write_class<W: fmt::Write + ?Sized>(w: &mut W, layout: &StructLayout) -> fmt::Result {
+    let attributes = attributes(layout);
+    let bytes = match layout.packed_bytes {
+        1 => "byte",
+        _ => "bytes"
+    };
+
+    writeln!(w, "@dataclass")?;
+    writeln!(w, "class {}:", layout.name)?;
+    writeln!(w, "    \"\"\"The {} packed structure, {} {}.\"\"\"", layout.name, layout.packed_bytes, bytes)?;
+    writeln!(w)?;
+    writeln!(w, "    SIZE = {}", layout.packed_bytes)?;
+    if !attributes.is_empty() {
+        writeln!(w)?;
+    }
+    for attribute in &attributes {
+        let field = attribute.fields[0];
+        let class = match attribute.value {
+            Value::Bool => "bool",
+            Value::Int { .. } => "int",
+            Value::Enum { .. } => enum_class(field.type_name),
+            Value::Nested(ref nested) => nested.name
+        };
+        let default = match attribute.value {
+            Value::Bool => "False".into(),
+            Value::Int { .. } => "0".into(),
+            Value::Enum { .. } => {
+                let variant = field.variants.iter().find(|v| v.value == 0).unwrap_or(&field.variants[0]);
+                format!("{}.{}", class, SnakeCase(variant.name, true))
+            },
+            Value::Nested(_) => class.into()
+        };
+
+        for line in field.doc.lines() {
+            writeln!(w, "    # {}", line)?;
+        }
+        let annotation = match attribute.value {
+            Value::Enum { catch_all: true, .. } => format!("Union[{}, int]", class),
+            _ => class.into()
+        };
+        match (attribute.array, &attribute.value) {
+            (false, Value::Nested(_)) => writeln!(w, "    {}: {} = field(default_factory={})", PyName(attribute.name), annotation, default)?,
+            (false, _) => writeln!(w, "    {}: {} = {}", PyName(attribute.name), annotation, default)?,
+            (true, Value::Nested(_)) => writeln!(w, "    {}: List[{}] = field(default_factory=lambda: [{}() for _ in range({})])",
+                PyName(attribute.name), annotation, default, attribute.fields.len())?,
+            (true, _) => writeln!(w, "    {}: List[{}] = field(default_factory=lambda: [{}] * {})",
+                PyName(attribute.name), annotation, default, attribute.fields.len())?
+        }
+    }
+
+    writeln!(w)?;
+    writeln!(w, "    def pack(self) -> bytes:")?;
+    writeln!(w, "        n = 0")?;
+    for field in layout.fields {
+        let segments = segments(layout, field);
+        match field.reserved {
+            Some(true) => {
+                for segment in &segments {
+                    writeln!(w, "        n |= {}", HexMask(segment.len, segment.bit))?;
+                }
+                continue;
+            },
+            Some(false) => continue,
+            None => ()
+        }
+
+        let attribute = attributes.iter().find(|a| a.fields.iter().any(|f| core::ptr::eq(*f, field))).expect("the field's attribute");
+        let value = match attribute.fields.iter().position(|f| core::ptr::eq(*f, field)) {
+            Some(index) if attribute.array => format!("self.{}[{}]", PyName(attribute.name), index),
+            _ => format!("self.{}", PyName(attribute.name))
+        };
+        let value = match attribute.value {
+            Value::Int { .. } => value,
+            Value::Bool | Value::Enum { .. } => format!("int({})", value),
+            Value::Nested(_) => format!("int.from_bytes({}.pack(), \"big\")", value)
+        };
+
+        let value = match segments.len() {
+            1 => value,
+            _ => {
+                writeln!(w, "        value = {}", value)?;
+                "value".into()
+            }
+        };
+        for segment in &segments {
+            let mask = HexMask(segment.len, segment.value_bit);
+            match segment.bit.cmp(&segment.value_bit) {
+                Ordering::Equal => writeln!(w, "        n |= {} & {}", value, mask)?,
+                Ordering::Greater => writeln!(w, "        n |= ({} & {}) << {}", value, mask, segment.bit - segment.value_bit)?,
+                Ordering::Less => writeln!(w, "        n |= ({} & {}) >> {}", value, mask, segment.value_bit - segment.bit)?
+            }
+        }
+    }
+    writeln!(w, "        return n.to_bytes({}, \"big\")", layout.packed_bytes)?;
+
+    writeln!(w)?;
+    writeln!(w, "    @classmethod")?;
+    writeln!(w, "    def unpack(cls, data: bytes) -> \"{}\":", layout.name)?;
+    writeln!(w, "        if len(data) != {}:", layout.packed_bytes)?;
+    writeln!(w, "            raise ValueError(\"{} is {} {}, not {{}}\".format(len(data)))", layout.name, layout.packed_bytes, bytes)?;
+    writeln!(w, "        n = int.from_bytes(data, \"big\")")?;
+    writeln!(w, "        return cls(")?;
+    for attribute in &attributes {
+        if !attribute.array {
+            writeln!(w, "            {}={},", PyName(attribute.name), unpack_value(layout, attribute, attribute.fields[0]))?;
+            continue;
+        }
+        writeln!(w, "            {}=[", PyName(attribute.name))?;
+        for field in &attribute.fields {
+            writeln!(w, "                {},", unpack_value(layout, attribute, field))?;
+        }
+        writeln!(w, "            ],")?;
+    }
+    writeln!(w, "        )")
+}
+
+/// A run of a field's bits, `len` bits from `value_bit` of the field's value
+/// at `bit` of the structure read as a big-endian integer.
+struct Segment {
+    value_bit: usize,
+    bit: usize,
+    len: usize
+}
+
+fn segments(layout: &StructLayout, field: &FieldInfo) -> Vec<Segment> {
+    let last = layout.packed_bytes * 8 - 1;
+    let mut bits: Vec<_> = field.bit_map(layout.bit_numbering).map(|(v, p)| (v, last - p)).collect();
+    bits.sort_unstable();
+
+    let mut segments: Vec<Segment> = Vec::new();
+    for (value_bit, bit) in bits {
+        match segments.last_mut() {
+            Some(s) if s.value_bit + s.len == value_bit && s.bit + s.len == bit => s.len += 1,
+            _ => segments.push(Segment { value_bit, bit, len: 1 })
+        }
+    }
+    segments
+}
+
+/// The expression that unpacks a field from `data` and its integer `n`.
+fn unpack_value(layout: &StructLayout, attribute: &Attribute, field: &FieldInfo) -> String {
+    let segments = segments(layout, field);
+    let terms: Vec<String> = segments.iter().map(|s| {
+        let mask = HexMask(s.len, s.value_bit);
+        match s.bit.cmp(&s.value_bit) {
+            Ordering::Equal => format!("n & {}", mask),
+            Ordering::Greater => format!("n >> {} & {}", s.bit - s.value_bit, mask),
+            Ordering::Less => format!("n << {} & {}", s.value_bit - s.bit, mask)
+        }
+    }).collect();
+    let raw = match terms.len() {
+        0 => "0".into(),
+        1 => terms[0].clone(),
+        _ => terms.iter().map(|t| format!("({})", t)).collect::<Vec<_>>().join(" | ")
+    };
+    let signed = |raw: String, signed: bool| match signed {
+        true => format!("_signed({}, {})", raw, field.width),
+        false => raw
+    };
+
+    match attribute.value {
+        Value::Bool => format!("bool({})", raw),
+        Value::Int { signed: s } => signed(raw, s),
+        Value::Enum { signed: s, catch_all: false } => format!("{}({})", enum_class(field.type_name), signed(raw, s)),
+        Value::Enum { signed: s, catch_all: true } => format!("_enum_or_int({}, {})", enum_class(field.type_name), signed(raw, s)),
+        Value::Nested(ref nested) => match segments.as_slice() {
+            [s] if s.value_bit == 0 && s.len == nested.packed_bytes * 8 && s.bit % 8 == 0 => {
+                let end = layout.packed_bytes - s.bit / 8;
+                format!("{}.unpack(data[{}:{}])", nested.name, end - nested.packed_bytes, end)
+            },
+            _ => format!("{}.unpack(({}).to_bytes({}, \"big\"))", nested.name, raw, nested.packed_bytes)
+        }
+    }
+}
+
+/// The values of a structure's fields, by their dotted paths.
+struct TestValues(Vec<(String, TestValue)>);
+
+enum TestValue {
+    Integer(IntegerValue),
+    /// The big-endian raw bits of fields without an integer value.
+    Bits(Vec<u8>)
+}
+
+impl Visitor for TestValues {
+    type Error = PackingError;
+
+    fn visit_field(&mut self, field: &VisitField) -> Result<(), PackingError> {
+        if field.reserved {
+            return Ok(());
+        }
+
+        let mut path = String::new();
+        // writing into a string doesn't fail
+        let _ = field.write_path(&mut path);
+        let value = match field.integer {
+            Some(integer) => TestValue::Integer(integer),
+            None => {
+                let count = field.bit_positions().count();
+                let mut bytes = vec![0u8; count.div_ceil(8)];
+                for (i, p) in field.bit_positions().enumerate() {
+                    if field.packed[p / 8] & (0x80 >> (p % 8)) != 0 {
+                        let bit = count - 1 - i;
+                        let len = bytes.len();
+                        bytes[len - 1 - bit / 8] |= 1 << (bit % 8);
+                    }
+                }
+                TestValue::Bits(bytes)
+            }
+        };
+        self.0.push((path, value));
+        Ok(())
+    }
+}
+
+impl TestValues {
+    fn get(&self, path: &str) -> Option<&TestValue> {
+        self.0.iter().find(|(p, _)| p == path).map(|(_, v)| v)
+    }
+}
+
+/// Writes the value of a test vector, as the dataclass's constructor. The
+/// fields of nested structures have the `prefix` in their paths.
+fn write_value<W: fmt::Write + ?Sized>(w: &mut W, layout: &StructLayout, values: &TestValues, prefix: &str) -> fmt::Result {
+    write!(w, "{}(", layout.name)?;
+    for (i, attribute) in attributes(layout).iter().enumerate() {
+        if i > 0 {
+            write!(w, ", ")?;
+        }
+        write!(w, "{}=", PyName(attribute.name))?;
+        if attribute.array {
+            write!(w, "[")?;
+        }
+        for (j, field) in attribute.fields.iter().enumerate() {
+            if j > 0 {
+                write!(w, ", ")?;
+            }
+            write_field_value(w, attribute, field, values, &format!("{}{}", prefix, field.name))?;
+        }
+        if attribute.array {
+            write!(w, "]")?;
+        }
+    }
+    write!(w, ")")
+}
+
+fn write_field_value<W: fmt::Write + ?Sized>(w: &mut W, attribute: &Attribute, field: &FieldInfo, values: &TestValues, path: &str) -> fmt::Result {
+    if let Value::Nested(ref nested) = attribute.value {
+        return write_value(w, nested, values, &format!("{}.", path));
+    }
+
+    let integer = match values.get(path) {
+        Some(TestValue::Integer(integer)) => *integer,
+        Some(TestValue::Bits(bytes)) => {
+            write!(w, "0x")?;
+            for byte in bytes {
+                write!(w, "{:02X}", byte)?;
+            }
+            return Ok(());
+        },
+        // the structure's visitor doesn't match its layout
+        None => return Err(fmt::Error)
+    };
+
+    match attribute.value {
+        Value::Bool => write!(w, "{}", if integer.as_i128() != 0 { "True" } else { "False" }),
+        Value::Enum { .. } => match field.variants.iter().find(|v| v.value == integer.as_i128()) {
+            Some(variant) => write!(w, "{}.{}", enum_class(field.type_name), SnakeCase(variant.name, true)),
+            None => write!(w, "{}", integer)
+        },
+        _ => write!(w, "{}", integer)
+    }
+}
+
+fn is_catch_all(field: &FieldInfo) -> bool {
+    !field.variants.is_empty() && field.type_name.contains("EnumCatchAll")
+}
+
+/// The name of a primitive enum's class, the enum's name.
+fn enum_class(type_name: &str) -> &str {
+    type_name
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .rfind(|t| t.starts_with(|c: char| c.is_ascii_uppercase()) && *t != "EnumCatchAll")
+        .unwrap_or(type_name)
+}
+
+/// A mask of `len` bits from bit `shift`, in hex.
+struct HexMask(usize, usize);
+
+impl Display for HexMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (len, shift) = (self.0, self.1);
+        f.write_str("0x")?;
+        for nibble in (0..(len + shift).div_ceil(4)).rev() {
+            let digit = (0..4).filter(|&i| (shift..shift + len).contains(&(nibble * 4 + i))).fold(0, |d, i| d | 1 << i);
+            write!(f, "{:X}", digit)?;
+        }
+        Ok(())
+    }
+}
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else",
+    "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not",
+    "or", "pass", "raise", "return", "try", "while", "with", "yield"
+];
+
+/// The dataclass's methods and the names its body refers to, which an
+/// attribute with the same name would shadow.
+const DATACLASS_NAMES: &[&str] = &["pack", "unpack", "field", "bool", "int"];
+
+/// A field's attribute name in snake case, with a trailing `_` if it's a
+/// Python keyword or one of the `DATACLASS_NAMES`.
+struct PyName<'a>(&'a str);
+
+impl<'a> Display for PyName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = format!("{}", SnakeCase(self.0, false));
+        match PYTHON_KEYWORDS.contains(&name.as_str()) || DATACLASS_NAMES.contains(&name.as_str()) {
+            true => write!(f, "{}_", name),
+            false => f.write_str(&name)
+        }
+    }
+}
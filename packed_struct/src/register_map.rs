@@ -0,0 +1,191 @@
+//! Register maps of devices, accessed over a byte-addressed bus.
+
+use crate::internal_prelude::v1::*;
+use crate::access::{FieldAccess, PackedStructAccess};
+use crate::packing::{PackedStruct, PackingError};
+use crate::types_bits::ByteArray;
+
+/// Description of a single register within a register map.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RegisterInfo {
+    pub name: &'static str,
+    pub address: u32,
+    pub size_bytes: usize,
+    /// How the register can be accessed over the bus: read only, write only or read and write.
+    pub access: FieldAccess,
+}
+
+/// Number of bytes that a register occupies on the bus.
+pub const fn register_size_bytes<T: PackedStruct>() -> usize {
+    mem::size_of::<T::ByteArray>()
+}
+
+/// A device's set of registers, implemented by `#[derive(RegisterMap)]`.
+pub trait RegisterMap {
+    /// All the registers of this device, in declaration order.
+    const REGISTERS: &'static [RegisterInfo];
+}
+
+/// Errors that might occur while accessing a register.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RegisterError<E> {
+    /// The bus reported an error.
+    Bus(E),
+    /// The register's value couldn't be packed or unpacked.
+    Packing(PackingError),
+}
+
+impl<E> From<PackingError> for RegisterError<E> {
+    fn from(e: PackingError) -> Self {
+        RegisterError::Packing(e)
+    }
+}
+
+impl<E: Debug> Display for RegisterError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Debug> ::std::error::Error for RegisterError<E> {}
+
+/// A bus that can read and write bytes at a register address.
+pub trait RegisterBus {
+    type Error;
+
+    /// Fill the buffer with the bytes stored at the address.
+    fn read_bytes(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Self::Error>;
+    /// Store the bytes at the address.
+    fn write_bytes(&mut self, address: u32, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    /// Read and unpack a register. The transfer length is the size of the register's byte array.
+    fn read_register<T: PackedStruct>(&mut self, address: u32) -> Result<T, RegisterError<Self::Error>> {
+        let mut buffer = <T::ByteArray as ByteArray>::new(0);
+        self.read_bytes(address, buffer.as_mut_bytes_slice())
+            .map_err(RegisterError::Bus)?;
+        Ok(T::unpack(&buffer)?)
+    }
+
+    /// Pack and write a register.
+    fn write_register<T: PackedStruct>(&mut self, address: u32, value: &T) -> Result<(), RegisterError<Self::Error>> {
+        let packed = value.pack()?;
+        self.write_bytes(address, packed.as_bytes_slice())
+            .map_err(RegisterError::Bus)
+    }
+
+    /// Read a register, modify it and write it back. Returns the written value.
+    fn modify_register<T, F>(&mut self, address: u32, f: F) -> Result<T, RegisterError<Self::Error>>
+        where T: PackedStruct, F: FnOnce(&mut T)
+    {
+        let mut value = self.read_register(address)?;
+        f(&mut value);
+        self.write_register(address, &value)?;
+        Ok(value)
+    }
+
+    /// Read a register, modify it and write it back while respecting the access
+    /// semantics of its fields. Write-1-to-clear bits that were read as set aren't
+    /// cleared by the write, and read-only bits keep their contents. Returns the
+    /// modified value.
+    fn update_register<T, F>(&mut self, address: u32, f: F) -> Result<T, RegisterError<Self::Error>>
+        where T: PackedStructAccess, F: FnOnce(&mut T)
+    {
+        let mut current = <T::ByteArray as ByteArray>::new(0);
+        self.read_bytes(address, current.as_mut_bytes_slice())
+            .map_err(RegisterError::Bus)?;
+        let mut value = T::unpack(&current)?;
+        f(&mut value);
+        let packed = value.pack_write(&current)?;
+        self.write_bytes(address, packed.as_bytes_slice())
+            .map_err(RegisterError::Bus)?;
+        Ok(value)
+    }
+}
+
+impl<B: RegisterBus> RegisterBus for &mut B {
+    type Error = B::Error;
+
+    fn read_bytes(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        (**self).read_bytes(address, buffer)
+    }
+
+    fn write_bytes(&mut self, address: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        (**self).write_bytes(address, bytes)
+    }
+}
+
+/// A single access recorded by the `MemoryBus`.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum BusTransaction {
+    Read { address: u32, bytes: Vec<u8> },
+    Write { address: u32, bytes: Vec<u8> },
+}
+
+/// An in-memory bus simulator for testing drivers. Every byte address is backed by
+/// a byte of memory, and all the accesses are recorded.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryBus {
+    memory: Vec<u8>,
+    transactions: Vec<BusTransaction>,
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl MemoryBus {
+    /// A bus with `size` bytes of zeroed memory, starting at address 0.
+    pub fn new(size: usize) -> Self {
+        MemoryBus {
+            memory: vec![0; size],
+            transactions: vec![],
+        }
+    }
+
+    /// The simulated memory.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// The simulated memory, for presetting register values.
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// All the reads and writes since the bus was created or the log was cleared.
+    pub fn transactions(&self) -> &[BusTransaction] {
+        &self.transactions
+    }
+
+    pub fn clear_transactions(&mut self) {
+        self.transactions.clear();
+    }
+
+    fn range(&self, address: u32, len: usize) -> Result<Range<usize>, PackingError> {
+        let start = address as usize;
+        let end = start + len;
+        if end > self.memory.len() {
+            return Err(PackingError::SliceIndexingError { slice_len: self.memory.len() });
+        }
+        Ok(start..end)
+    }
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl RegisterBus for MemoryBus {
+    type Error = PackingError;
+
+    fn read_bytes(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), PackingError> {
+        let range = self.range(address, buffer.len())?;
+        buffer.copy_from_slice(&self.memory[range]);
+        self.transactions.push(BusTransaction::Read { address, bytes: buffer.to_vec() });
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, address: u32, bytes: &[u8]) -> Result<(), PackingError> {
+        let range = self.range(address, bytes.len())?;
+        self.memory[range].copy_from_slice(bytes);
+        self.transactions.push(BusTransaction::Write { address, bytes: bytes.to_vec() });
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! Hex and bit string representations of the packed bytes, for datasheet
+//! examples, tests and command line tools.
+
+use crate::internal_prelude::v1::*;
+use crate::packing::{PackedStruct, PackingError, PackingResult};
+use crate::types_bits::ByteArray;
+
+/// Text representations of a packed structure.
+///
+/// Parsing ignores whitespace, commas and underscores, and accepts `0x` and
+/// `0b` prefixes on every group of digits. The number of digits has to match
+/// the size of the structure exactly.
+///
+/// ```rust
+/// use packed_struct::prelude::*;
+///
+/// #[derive(PackedStruct, Debug, PartialEq)]
+/// #[packed_struct(endian="msb")]
+/// pub struct Sample {
+///     id: u8,
+///     value: u16
+/// }
+///
+/// # fn main() -> Result<(), PackingError> {
+/// let sample = Sample::from_hex_str("0x8B E7_21")?;
+/// assert_eq!(Sample { id: 0x8B, value: 0xE721 }, sample);
+/// assert_eq!("8B E7 21", sample.to_hex_string()?);
+///
+/// assert_eq!(sample, Sample::from_bit_str("0b1000_1011 0b1110_0111 0b0010_0001")?);
+/// assert_eq!("0b1000_1011 0b1110_0111 0b0010_0001", sample.to_bit_string()?);
+/// # Ok(())
+/// # }
+/// ```
+pub trait PackedStructText: PackedStruct {
+    /// Unpack from hex digits, like `"8B E7 21 FA"`. Returns `BufferSizeMismatch`
+    /// with the expected and parsed number of bytes if the lengths differ.
+    fn from_hex_str(s: &str) -> PackingResult<Self> {
+        let mut bytes = Self::ByteArray::new(0);
+        parse_hex_bytes(s, bytes.as_mut_bytes_slice())?;
+        Self::unpack(&bytes)
+    }
+
+    /// Unpack from binary digits, like `"0b1000_1011 0b1110_0111"`. Returns
+    /// `BufferSizeMismatch` with the expected and parsed number of bits if the
+    /// lengths differ.
+    fn from_bit_str(s: &str) -> PackingResult<Self> {
+        let mut bytes = Self::ByteArray::new(0);
+        parse_bit_bytes(s, bytes.as_mut_bytes_slice())?;
+        Self::unpack(&bytes)
+    }
+
+    /// Pack into uppercase hex bytes separated by spaces, like `"8B E7 21 FA"`.
+    #[cfg(any(feature="alloc", feature="std"))]
+    fn to_hex_string(&self) -> PackingResult<String> {
+        let mut s = String::new();
+        write_hex_bytes(&mut s, self.pack()?.as_bytes_slice()).map_err(|_| PackingError::InternalError)?;
+        Ok(s)
+    }
+
+    /// Pack into binary bytes separated by spaces, like `"0b1000_1011 0b1110_0111"`.
+    #[cfg(any(feature="alloc", feature="std"))]
+    fn to_bit_string(&self) -> PackingResult<String> {
+        let mut s = String::new();
+        write_bit_bytes(&mut s, self.pack()?.as_bytes_slice()).map_err(|_| PackingError::InternalError)?;
+        Ok(s)
+    }
+}
+
+impl<T: PackedStruct> PackedStructText for T { }
+
+/// Parse hex digits into the output buffer, which has to be filled exactly.
+pub fn parse_hex_bytes(s: &str, output: &mut [u8]) -> PackingResult<()> {
+    let digits = parse_digits(s, 4, output)?;
+    if digits != output.len() * 2 {
+        return Err(PackingError::BufferSizeMismatch { expected: output.len(), actual: digits.div_ceil(2) });
+    }
+    Ok(())
+}
+
+/// Parse binary digits into the output buffer, which has to be filled exactly.
+pub fn parse_bit_bytes(s: &str, output: &mut [u8]) -> PackingResult<()> {
+    let digits = parse_digits(s, 1, output)?;
+    if digits != output.len() * 8 {
+        return Err(PackingError::BufferSizeMismatch { expected: output.len() * 8, actual: digits });
+    }
+    Ok(())
+}
+
+/// Shifts the digits into the output, most significant first. Returns the
+/// number of digits, which might not fit into the output.
+fn parse_digits(s: &str, digit_bits: usize, output: &mut [u8]) -> PackingResult<usize> {
+    let (prefix, radix) = if digit_bits == 4 { ('x', 16) } else { ('b', 2) };
+    let mut digits = 0;
+
+    for group in s.split(|c: char| c.is_whitespace() || c == ',').filter(|g| !g.is_empty()) {
+        let group = match group.strip_prefix('0') {
+            Some(rest) if rest.starts_with(prefix) || rest.starts_with(prefix.to_ascii_uppercase()) => &rest[1..],
+            _ => group
+        };
+
+        let mut group_digits = 0;
+        for c in group.chars().filter(|c| *c != '_') {
+            let digit = c.to_digit(radix).ok_or(PackingError::InvalidValue)? as u8;
+            let bit = digits * digit_bits;
+            if bit < output.len() * 8 {
+                output[bit / 8] |= digit << (8 - digit_bits - (bit % 8));
+            }
+            digits += 1;
+            group_digits += 1;
+        }
+
+        // a lone nibble is ambiguous, it could be either half of a byte
+        if digit_bits == 4 && group_digits % 2 != 0 {
+            return Err(PackingError::InvalidValue);
+        }
+    }
+
+    Ok(digits)
+}
+
+/// Write uppercase hex bytes, separated by spaces.
+pub fn write_hex_bytes<W: fmt::Write + ?Sized>(w: &mut W, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            w.write_str(" ")?;
+        }
+        write!(w, "{:02X}", byte)?;
+    }
+    Ok(())
+}
+
+/// Write binary bytes, separated by spaces, with an underscore between the nibbles.
+pub fn write_bit_bytes<W: fmt::Write + ?Sized>(w: &mut W, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            w.write_str(" ")?;
+        }
+        write!(w, "0b{:04b}_{:04b}", byte >> 4, byte & 0x0F)?;
+    }
+    Ok(())
+}
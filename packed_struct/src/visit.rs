@@ -0,0 +1,390 @@
+//! Walking the fields of packed structures, for custom output formats.
+//!
+//! `#[derive(PackedStruct)]` implements `PackedStructVisit`, which calls a
+//! `Visitor` for every field with its name, absolute bit range, raw bits and
+//! value. Nested packed structures are visited recursively. Doesn't allocate.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::visit::{PackedStructVisit, VisitField, Visitor};
+//!
+//! #[derive(PackedStruct, Debug)]
+//! #[packed_struct(bit_numbering="msb0")]
+//! pub struct Status {
+//!     #[packed_field(bits="0")]
+//!     busy: bool,
+//!     #[packed_field(bits="4:7")]
+//!     pending: Integer<u8, packed_bits::Bits4>
+//! }
+//!
+//! struct Sum(i128);
+//!
+//! impl Visitor for Sum {
+//!     type Error = PackingError;
+//!
+//!     fn visit_field(&mut self, field: &VisitField) -> Result<(), PackingError> {
+//!         self.0 += field.integer.map(|i| i.as_i128()).unwrap_or(0);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> Result<(), PackingError> {
+//! let mut sum = Sum(0);
+//! Status { busy: true, pending: 5.into() }.visit(&mut sum)?;
+//! assert_eq!(6, sum.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::internal_prelude::v1::*;
+use crate::debug_fmt::DebugRawBits;
+use crate::debug_fmt::DisplayRadix;
+use crate::packing::{PackedStruct, PackingError};
+use crate::primitive_enum::PrimitiveEnum;
+use crate::types_bits::{ByteArray, NumberOfBits};
+use crate::types_num::Integer;
+use crate::types_reserved::ReservedBits;
+use crate::FieldAccess;
+
+/// Byte order of a multi-byte integer field.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize), serde(rename_all = "lowercase"))]
+pub enum IntegerEndianness {
+    Msb,
+    Lsb,
+}
+
+/// The integer value of a field.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IntegerValue {
+    Unsigned(u128),
+    Signed(i128),
+}
+
+impl IntegerValue {
+    /// The value as a signed integer. Unsigned values above `i128::MAX` wrap around.
+    pub fn as_i128(&self) -> i128 {
+        match *self {
+            IntegerValue::Unsigned(v) => v as i128,
+            IntegerValue::Signed(v) => v,
+        }
+    }
+}
+
+impl Display for IntegerValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntegerValue::Unsigned(v) => Display::fmt(&v, f),
+            IntegerValue::Signed(v) => Display::fmt(&v, f),
+        }
+    }
+}
+
+/// A type whose values are integers.
+pub trait AsIntegerValue {
+    fn as_integer_value(&self) -> IntegerValue;
+}
+
+macro_rules! as_integer_value {
+    ($variant: ident, $as: ty, $($T: ty),*) => {
+        $(
+            impl AsIntegerValue for $T {
+                #[inline]
+                fn as_integer_value(&self) -> IntegerValue {
+                    IntegerValue::$variant(*self as $as)
+                }
+            }
+        )*
+    };
+}
+
+as_integer_value!(Unsigned, u128, u8, u16, u32, u64, u128, bool);
+as_integer_value!(Signed, i128, i8, i16, i32, i64, i128);
+
+impl<T, B> AsIntegerValue for Integer<T, B>
+    where T: AsIntegerValue + Copy, B: NumberOfBits, Integer<T, B>: Deref<Target = T>
+{
+    #[inline]
+    fn as_integer_value(&self) -> IntegerValue {
+        (**self).as_integer_value()
+    }
+}
+
+/// The bits of a little-endian field of a sawtooth-numbered structure, which
+/// are a run of bits in the structure with its bytes in reverse order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LsbFrame {
+    /// MSB0 inclusive bit range in the byte-reversed structure, the field's
+    /// most significant bit first.
+    pub bits: Range<usize>,
+    /// The structure's bytes, absolute within the visited structure.
+    pub bytes: Range<usize>,
+}
+
+impl LsbFrame {
+    /// The MSB0 position of a bit of the byte-reversed structure.
+    pub fn position(&self, bit: usize) -> usize {
+        (self.bytes.end - 1 - bit / 8) * 8 + bit % 8
+    }
+}
+
+/// The MSB0 positions of a field's bits, most significant first. The bits are
+/// the range, unless the field has an `LsbFrame`.
+pub fn bit_positions<'a>(bits: &Range<usize>, lsb_frame: Option<&'a LsbFrame>) -> impl Iterator<Item = usize> + 'a {
+    let run = lsb_frame.map(|f| f.bits.clone()).unwrap_or_else(|| bits.clone());
+    (run.start..(run.end + 1)).map(move |i| lsb_frame.map(|f| f.position(i)).unwrap_or(i))
+}
+
+/// A single field, passed to the `Visitor`.
+pub struct VisitField<'a> {
+    pub name: &'static str,
+    /// MSB0 inclusive bit range, absolute within the visited structure. Covers
+    /// all of the field's bits, and other bits too for fields with an `LsbFrame`.
+    pub bits: Range<usize>,
+    /// The actual bits of little-endian fields of sawtooth-numbered structures.
+    pub lsb_frame: Option<LsbFrame>,
+    /// Byte order of multi-byte integers and of fields with an `LsbFrame`, `None` for other fields.
+    pub endianness: Option<IntegerEndianness>,
+    /// The packed bytes of the visited structure.
+    pub packed: &'a [u8],
+    pub value: &'a dyn Debug,
+    /// Integers, booleans and primitive enums.
+    pub integer: Option<IntegerValue>,
+    pub access: FieldAccess,
+    /// Reserved bits, which are always packed as zeroes or ones.
+    pub reserved: bool,
+    /// Zero for the fields of the visited structure, increased by one for every level of nesting.
+    pub depth: usize,
+    /// The field of the outer structure, for fields of nested structures.
+    pub parent: Option<&'a VisitField<'a>>,
+}
+
+impl<'a> VisitField<'a> {
+    /// The MSB0 positions of the field's bits, most significant first.
+    pub fn bit_positions(&self) -> impl Iterator<Item = usize> + '_ {
+        bit_positions(&self.bits, self.lsb_frame.as_ref())
+    }
+
+    /// The field's raw bits.
+    pub fn raw_bits(&self) -> DebugRawBits<'_> {
+        DebugRawBits {
+            bits: &self.bits,
+            lsb_frame: self.lsb_frame.as_ref(),
+            slice: self.packed,
+            radix: DisplayRadix::Binary
+        }
+    }
+
+    /// The raw bits as an unsigned integer, for fields up to 128 bits wide.
+    pub fn raw_value(&self) -> Option<u128> {
+        if self.bit_positions().count() > 128 {
+            return None;
+        }
+
+        Some(self.bit_positions().fold(0, |v, i| {
+            (v << 1) | (((self.packed[i / 8] >> (7 - (i % 8))) & 1) as u128)
+        }))
+    }
+
+    /// Write the names of the outer fields and this field, separated with dots.
+    pub fn write_path<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        if let Some(parent) = self.parent {
+            parent.write_path(w)?;
+            w.write_str(".")?;
+        }
+        w.write_str(self.name)
+    }
+
+    /// The field of the outermost structure that contains this field.
+    pub fn root(&self) -> &VisitField<'a> {
+        match self.parent {
+            Some(parent) => parent.root(),
+            None => self
+        }
+    }
+
+    /// Length of the dotted path, see `write_path`.
+    pub fn path_len(&self) -> usize {
+        self.name.len() + self.parent.map(|p| p.path_len() + 1).unwrap_or(0)
+    }
+}
+
+/// Receives the fields of a packed structure.
+pub trait Visitor {
+    type Error;
+
+    fn visit_field(&mut self, field: &VisitField) -> Result<(), Self::Error>;
+
+    /// Called after `visit_field` for a nested packed structure, before its fields are visited.
+    fn enter_nested(&mut self, _field: &VisitField) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called after all the fields of a nested structure were visited.
+    fn leave_nested(&mut self, _field: &VisitField) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<V: Visitor + ?Sized> Visitor for &mut V {
+    type Error = V::Error;
+
+    fn visit_field(&mut self, field: &VisitField) -> Result<(), Self::Error> {
+        (**self).visit_field(field)
+    }
+
+    fn enter_nested(&mut self, field: &VisitField) -> Result<(), Self::Error> {
+        (**self).enter_nested(field)
+    }
+
+    fn leave_nested(&mut self, field: &VisitField) -> Result<(), Self::Error> {
+        (**self).leave_nested(field)
+    }
+}
+
+/// Position of the structure that is being visited within the outermost packed bytes.
+pub struct VisitContext<'a> {
+    pub packed: &'a [u8],
+    pub depth: usize,
+    field_end: usize,
+    packed_bits: usize,
+}
+
+impl<'a> VisitContext<'a> {
+    /// The context of the outermost structure.
+    pub fn new(packed: &'a [u8]) -> Self {
+        VisitContext {
+            packed,
+            depth: 0,
+            field_end: packed.len() * 8,
+            packed_bits: packed.len() * 8,
+        }
+    }
+
+    /// The context of a nested structure, packed into `packed_bytes` bytes and
+    /// stored in the field's bits. The structure is aligned to the end of the field.
+    pub fn nested(&self, field: &VisitField, packed_bytes: usize) -> Self {
+        VisitContext {
+            packed: self.packed,
+            depth: self.depth + 1,
+            field_end: field.bits.end + 1,
+            packed_bits: packed_bytes * 8,
+        }
+    }
+
+    /// The bytes of a structure of `packed_bytes` bytes within the outermost packed bytes.
+    pub fn bytes(&self, packed_bytes: usize) -> Range<usize> {
+        let bits = self.translate(0..(packed_bytes * 8 - 1));
+        (bits.start / 8)..(bits.end / 8 + 1)
+    }
+
+    /// Translates the bits of the structure into the outermost packed bytes.
+    pub fn translate(&self, bits: Range<usize>) -> Range<usize> {
+        (bits.start + self.field_end - self.packed_bits)..(bits.end + self.field_end - self.packed_bits)
+    }
+}
+
+/// A structure whose fields can be visited, implemented by `#[derive(PackedStruct)]`.
+pub trait PackedStructVisit: PackedStruct {
+    /// Visit the fields of this structure, which is placed according to the context.
+    fn visit_fields<V: Visitor + ?Sized>(&self, ctx: &VisitContext, parent: Option<&VisitField>, visitor: &mut V) -> Result<(), V::Error>;
+
+    /// Pack the structure and visit all of its fields.
+    fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) -> Result<(), V::Error>
+        where V::Error: From<PackingError>
+    {
+        let packed = self.pack()?;
+        self.visit_fields(&VisitContext::new(packed.as_bytes_slice()), None, visitor)
+    }
+}
+
+/// Wraps a field's value to find its integer value or nested fields. Used by
+/// the generated code, the most specific implementation is picked by auto-ref.
+#[doc(hidden)]
+pub struct FieldValue<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait VisitInteger {
+    fn visit_integer(&self) -> Option<IntegerValue>;
+}
+
+impl<'a, 'b, 'c, T: AsIntegerValue> VisitInteger for &'c &'b FieldValue<'a, T> {
+    #[inline]
+    fn visit_integer(&self) -> Option<IntegerValue> {
+        Some(self.0.as_integer_value())
+    }
+}
+
+#[doc(hidden)]
+pub trait VisitEnumInteger {
+    fn visit_integer(&self) -> Option<IntegerValue>;
+}
+
+impl<'a, 'b, T> VisitEnumInteger for &'b FieldValue<'a, T> where T: PrimitiveEnum, T::Primitive: AsIntegerValue {
+    #[inline]
+    fn visit_integer(&self) -> Option<IntegerValue> {
+        Some(self.0.to_primitive().as_integer_value())
+    }
+}
+
+#[doc(hidden)]
+pub trait VisitNoInteger {
+    fn visit_integer(&self) -> Option<IntegerValue>;
+}
+
+impl<'a, T> VisitNoInteger for FieldValue<'a, T> {
+    #[inline]
+    fn visit_integer(&self) -> Option<IntegerValue> {
+        None
+    }
+}
+
+#[doc(hidden)]
+pub trait VisitNested {
+    fn visit_nested<V: Visitor + ?Sized>(&self, ctx: &VisitContext, field: &VisitField, visitor: &mut V) -> Result<(), V::Error>;
+}
+
+impl<'a, T: PackedStructVisit> VisitNested for FieldValue<'a, T> {
+    fn visit_nested<V: Visitor + ?Sized>(&self, ctx: &VisitContext, field: &VisitField, visitor: &mut V) -> Result<(), V::Error> {
+        let nested = ctx.nested(field, mem::size_of::<T::ByteArray>());
+        visitor.enter_nested(field)?;
+        self.0.visit_fields(&nested, Some(field), visitor)?;
+        visitor.leave_nested(field)
+    }
+}
+
+#[doc(hidden)]
+pub trait VisitNotNested {
+    fn visit_nested<V: Visitor + ?Sized>(&self, ctx: &VisitContext, field: &VisitField, visitor: &mut V) -> Result<(), V::Error>;
+}
+
+impl<'a, 'b, T> VisitNotNested for &'b FieldValue<'a, T> {
+    #[inline]
+    fn visit_nested<V: Visitor + ?Sized>(&self, _ctx: &VisitContext, _field: &VisitField, _visitor: &mut V) -> Result<(), V::Error> {
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub trait VisitReserved {
+    fn visit_reserved(&self) -> bool;
+}
+
+impl<'a, V, B> VisitReserved for FieldValue<'a, ReservedBits<V, B>> {
+    #[inline]
+    fn visit_reserved(&self) -> bool {
+        true
+    }
+}
+
+#[doc(hidden)]
+pub trait VisitNotReserved {
+    fn visit_reserved(&self) -> bool;
+}
+
+impl<'a, 'b, T> VisitNotReserved for &'b FieldValue<'a, T> {
+    #[inline]
+    fn visit_reserved(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,278 @@
+//! Wireshark Lua dissectors for packed structures, to decode captures with
+//! the same layouts as the Rust code.
+//!
+//! Every layout becomes its own protocol, named after the prefix and the
+//! structure, like `acme_header`. Its fields are `ProtoField`s with the
+//! bitmask of their bits, primitive enums show the names of their variants,
+//! and nested structures are subtrees. Little-endian fields are added with
+//! `add_le`. Fields that aren't a run of bits in their bytes, like
+//! little-endian fields of LSB0 structures whose width isn't a multiple of 8,
+//! are shown as bytes. The generated protocols still need to be registered,
+//! for example with `DissectorTable.get("udp.port"):add(5000, acme_header)`.
+//!
+//! ```rust
+//! use packed_struct::prelude::*;
+//! use packed_struct::layout::StructLayout;
+//! use packed_struct::wireshark::LuaDissector;
+//!
+//! #[derive(PackedStruct)]
+//! #[packed_struct(bit_numbering="msb0", endian="msb")]
+//! pub struct Ping {
+//!     #[packed_field(bits="0")]
+//!     reply: bool,
+//!     #[packed_field(bits="1:15")]
+//!     sequence: Integer<u16, packed_bits::Bits15>
+//! }
+//!
+//! let lua = LuaDissector::new("acme").with_layout(StructLayout::of::<Ping>()).to_lua();
+//! assert!(lua.contains("local acme_ping = Proto(\"acme_ping\", \"acme Ping\")"));
+//! assert!(lua.contains("    sequence = ProtoField.uint16(\"acme_ping.sequence\", \"sequence\", base.DEC, nil, 0x7FFF),"));
+//! ```
+
+use crate::internal_prelude::v1::*;
+use crate::export::{is_signed, FieldPlacement, SnakeCase};
+use crate::layout::{FieldInfo, StructLayout};
+
+/// Generates a Lua script with a Wireshark dissector for each layout.
+pub struct LuaDissector {
+    prefix: String,
+    layouts: Vec<StructLayout>
+}
+
+/// Maps the MSB0 bits of a structure to the bits of the outermost structure.
+type BitMapping<'a> = &'a dyn Fn(usize) -> Option<usize>;
+
+impl LuaDissector {
+    /// A dissector generator, the prefix starts the names of the protocols.
+    pub fn new(prefix: &str) -> Self {
+        LuaDissector {
+            prefix: prefix.into(),
+            layouts: Vec::new()
+        }
+    }
+
+    /// Add a protocol for a layout.
+    pub fn with_layout(mut self, layout: StructLayout) -> Self {
+        self.layouts.push(layout);
+        self
+    }
+
+    /// Write the Lua script.
+    pub fn write_lua<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        writeln!(w, "-- Wireshark dissectors of the {} packed structures. Generated, don't edit.", self.prefix)?;
+        for layout in &self.layouts {
+            writeln!(w)?;
+            self.write_protocol(w, layout)?;
+        }
+        Ok(())
+    }
+
+    /// The Lua script, see `write_lua`.
+    pub fn to_lua(&self) -> String {
+        let mut s = String::new();
+        // writing into a string doesn't fail
+        let _ = self.write_lua(&mut s);
+        s
+    }
+
+    fn write_protocol<W: fmt::Write + ?Sized>(&self, w: &mut W, layout: &StructLayout) -> fmt::Result {
+        let proto = ProtoName(&self.prefix, layout.name);
+        let identity = |p: usize| Some(p);
+
+        let description = format!("{} {}", self.prefix, layout.name);
+        writeln!(w, "local {} = Proto(\"{}\", {})", proto, proto, LuaStr(&description))?;
+        writeln!(w)?;
+        writeln!(w, "local {}_fields = {{", proto)?;
+        write_fields(w, &proto, layout, &[], &identity)?;
+        writeln!(w, "}}")?;
+        writeln!(w, "{}.fields = {}_fields", proto, proto)?;
+        writeln!(w)?;
+
+        writeln!(w, "function {}.dissector(buffer, pinfo, tree)", proto)?;
+        writeln!(w, "    if buffer:len() < {} then", layout.packed_bytes)?;
+        writeln!(w, "        return 0")?;
+        writeln!(w, "    end")?;
+        writeln!(w, "    pinfo.cols.protocol = {}.name", proto)?;
+        writeln!(w, "    local subtree = tree:add({}, buffer(0, {}))", proto, layout.packed_bytes)?;
+        write_items(w, &proto, layout, &[], "subtree", &identity)?;
+        writeln!(w, "    return {}", layout.packed_bytes)?;
+        writeln!(w, "end")
+    }
+}
+
+/// The `ProtoField`s of a structure's fields, nested fields included.
+fn write_fields<W: fmt::Write + ?Sized>(w: &mut W, proto: &ProtoName, layout: &StructLayout, path: &[&str], outer: BitMapping) -> fmt::Result {
+    for field in layout.fields {
+        let field_path = FieldPath(path, field.name);
+        if let Some(nested) = layout.nested(field.name) {
+            let inner = |p| nested_bit(layout, field, &nested, p).and_then(outer);
+            write_fields(w, proto, &nested, &[path, &[field.name]].concat(), &inner)?;
+            continue;
+        }
+
+        let placement = place(layout, field, outer);
+        write!(w, "    {} = ProtoField.", LuaKey(field_path))?;
+        let mut args: Vec<String> = vec![format!("\"{}.{:#}\"", proto, field_path), format!("{}", LuaStr(field.name))];
+
+        match placement.window {
+            Some((_, shift)) if field.width <= 64 => {
+                let bits = match placement.bytes {
+                    1 => 8,
+                    2 => 16,
+                    3 => 24,
+                    4 => 32,
+                    _ => 64
+                };
+                let signed = is_signed(field);
+                if field.type_name == "bool" {
+                    write!(w, "bool")?;
+                    args.push(format!("{}", bits));
+                } else {
+                    write!(w, "{}int{}", if signed { "" } else { "u" }, bits)?;
+                    args.push("base.DEC".into());
+                }
+
+                args.push(match field.variants.len() {
+                    0 => "nil".into(),
+                    _ => {
+                        let values: Vec<_> = field.variants.iter().map(|v| format!("[{}] = {}", v.value, LuaStr(v.name))).collect();
+                        format!("{{ {} }}", values.join(", "))
+                    }
+                });
+                if shift > 0 || field.width < placement.bytes * 8 {
+                    args.push(format!("0x{:X}", (((1u128 << field.width) - 1) << shift) as u64));
+                } else {
+                    args.push("nil".into());
+                }
+            },
+            _ => {
+                write!(w, "bytes")?;
+                args.push("base.NONE".into());
+            }
+        }
+
+        if !field.doc.is_empty() {
+            args.push(format!("{}", LuaStr(&field.doc.lines().collect::<Vec<_>>().join(" "))));
+        }
+        while args.last().map(|a| a == "nil").unwrap_or(false) {
+            args.pop();
+        }
+        writeln!(w, "({}),", args.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Adds the structure's fields to the tree, nested structures as subtrees.
+fn write_items<W: fmt::Write + ?Sized>(w: &mut W, proto: &ProtoName, layout: &StructLayout, path: &[&str], tree: &str, outer: BitMapping) -> fmt::Result {
+    for field in layout.fields {
+        let field_path = FieldPath(path, field.name);
+        let placement = place(layout, field, outer);
+        let range = format!("buffer({}, {})", placement.offset, placement.bytes);
+
+        if let Some(nested) = layout.nested(field.name) {
+            let subtree = format!("{}_tree", field_path);
+            let label = format!("{}: {}", field.name, nested.name);
+            writeln!(w, "    local {} = {}:add({}, {})", subtree, tree, range, LuaStr(&label))?;
+            let inner = |p| nested_bit(layout, field, &nested, p).and_then(outer);
+            write_items(w, proto, &nested, &[path, &[field.name]].concat(), &subtree, &inner)?;
+            continue;
+        }
+
+        let add = match placement.window {
+            Some((true, _)) if field.width <= 64 => "add_le",
+            _ => "add"
+        };
+        writeln!(w, "    {}:{}({}_fields{}, {})", tree, add, proto, LuaIndex(field_path), range)?;
+    }
+    Ok(())
+}
+
+/// The placement of a field in the outermost structure.
+fn place(layout: &StructLayout, field: &FieldInfo, outer: BitMapping) -> FieldPlacement {
+    let bits = || field.bit_map(layout.bit_numbering).filter_map(|(v, p)| outer(p).map(|p| (v, p)));
+    FieldPlacement::of(bits, field.width)
+}
+
+/// The MSB0 bit of the structure for a bit of a nested structure. Nested
+/// structures are right-aligned in their fields.
+fn nested_bit(layout: &StructLayout, field: &FieldInfo, nested: &StructLayout, bit: usize) -> Option<usize> {
+    let value_bit = (nested.packed_bytes * 8).checked_sub(bit + 1)?;
+    field.bit_map(layout.bit_numbering).find(|&(v, _)| v == value_bit).map(|(_, p)| p)
+}
+
+/// The protocol's name, also the Lua variable of the protocol.
+struct ProtoName<'a>(&'a str, &'a str);
+
+impl<'a> Display for ProtoName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}_{}", SnakeCase(self.0, false), SnakeCase(self.1, false))
+    }
+}
+
+/// The names of the nested fields and the field, joined with `_`, or with
+/// `.` in the alternate `{:#}` form used for the fields' filter names.
+#[derive(Copy, Clone)]
+struct FieldPath<'a>(&'a [&'a str], &'a str);
+
+impl<'a> Display for FieldPath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = if f.alternate() { '.' } else { '_' };
+        for name in self.0 {
+            write!(f, "{}{}", SnakeCase(name, false), separator)?;
+        }
+        write!(f, "{}", SnakeCase(self.1, false))
+    }
+}
+
+const LUA_KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while"
+];
+
+fn is_lua_keyword(path: FieldPath) -> bool {
+    path.0.is_empty() && LUA_KEYWORDS.contains(&format!("{}", path).as_str())
+}
+
+/// A field's key in the table of fields.
+struct LuaKey<'a>(FieldPath<'a>);
+
+impl<'a> Display for LuaKey<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match is_lua_keyword(self.0) {
+            true => write!(f, "[\"{}\"]", self.0),
+            false => write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// Indexes the table of fields with a field's key.
+struct LuaIndex<'a>(FieldPath<'a>);
+
+impl<'a> Display for LuaIndex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match is_lua_keyword(self.0) {
+            true => write!(f, "[\"{}\"]", self.0),
+            false => write!(f, ".{}", self.0)
+        }
+    }
+}
+
+/// A quoted and escaped Lua string.
+struct LuaStr<'a>(&'a str);
+
+impl<'a> Display for LuaStr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                c if (c as u32) < 0x20 => write!(f, "\\{}", c as u32)?,
+                c => f.write_char(c)?
+            }
+        }
+        f.write_char('"')
+    }
+}
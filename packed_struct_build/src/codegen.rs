@@ -0,0 +1,142 @@
+//! Helpers for writing the generated Rust source.
+
+/// Generated Rust source, written line by line.
+pub struct Source {
+    text: String,
+    indent: usize,
+}
+
+impl Source {
+    pub fn new() -> Self {
+        Source { text: String::new(), indent: 0 }
+    }
+
+    /// Write an indented line, or an empty one.
+    pub fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.text.push_str("    ");
+            }
+            self.text.push_str(line);
+        }
+        self.text.push('\n');
+    }
+
+    /// Write a line that opens a block, like `pub mod uart {`.
+    pub fn open(&mut self, line: &str) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    /// Write a line that closes a block.
+    pub fn close(&mut self, line: &str) {
+        self.indent -= 1;
+        self.line(line);
+    }
+
+    /// Write a doc comment, one line per line of the text, with whitespace collapsed.
+    pub fn doc(&mut self, text: &str) {
+        for line in text.lines() {
+            let line: Vec<_> = line.split_whitespace().collect();
+            if line.is_empty() {
+                continue;
+            }
+            self.line(&format!("/// {}", line.join(" ")));
+        }
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+/// The words of a name, split at non-alphanumeric characters and at lowercase
+/// to uppercase transitions.
+fn words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Names like `INT_ENABLE` become `IntEnable`.
+pub fn camel_case(name: &str) -> String {
+    let mut s = String::new();
+    for word in words(name) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            s.push(first.to_ascii_uppercase());
+            s.extend(chars.map(|c| c.to_ascii_lowercase()));
+        }
+    }
+    identifier(s)
+}
+
+/// Names like `IntEnable` become `int_enable`.
+pub fn snake_case(name: &str) -> String {
+    let s: Vec<_> = words(name).iter().map(|w| w.to_ascii_lowercase()).collect();
+    identifier(s.join("_"))
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
+];
+
+/// Makes the name a valid identifier.
+fn identifier(mut s: String) -> String {
+    if s.is_empty() || s.starts_with(|c: char| c.is_ascii_digit()) {
+        s.insert(0, '_');
+    }
+    if KEYWORDS.contains(&s.as_str()) {
+        s.push('_');
+    }
+    s
+}
+
+/// The smallest unsigned primitive that holds the bits.
+pub fn unsigned_type(width: usize) -> &'static str {
+    match width {
+        0..=8 => "u8",
+        9..=16 => "u16",
+        17..=32 => "u32",
+        33..=64 => "u64",
+        _ => "u128",
+    }
+}
+
+/// The smallest primitive that holds the bits.
+pub fn primitive_type(width: usize, signed: bool) -> String {
+    let unsigned = unsigned_type(width);
+    if signed { unsigned.replacen('u', "i", 1) } else { unsigned.to_string() }
+}
+
+/// The type of an integer field: a primitive if the width matches one, an
+/// `Integer` of a bit width otherwise.
+pub fn integer_type(width: usize, signed: bool) -> String {
+    let primitive = primitive_type(width, signed);
+    match width {
+        8 | 16 | 32 | 64 | 128 => primitive,
+        _ => format!("Integer<{}, packed_bits::Bits{}>", primitive, width),
+    }
+}
@@ -0,0 +1,566 @@
+//! Message structures from CAN DBC databases.
+//!
+//! Every message becomes a `#[derive(PackedStruct)]` structure with a field
+//! per signal, numbered `sawtooth` like the DBC file: the `bits` of a field
+//! are the signal's start bit and the bit at its other end. Intel signals are
+//! little-endian fields, Motorola signals big-endian fields. Signed signals
+//! become signed integers, value tables become `PrimitiveEnum`s, wrapped in
+//! `EnumCatchAll` if they don't cover every value of the signal, and comments
+//! become doc comments. Signals with a factor or an offset get functions that
+//! convert between their raw and physical values.
+//!
+//! Multiplexed signals share their bits with other signals, so they're
+//! skipped, as are messages without signals. Floating point signals are
+//! generated as integers of their raw bits.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::codegen::{camel_case, integer_type, primitive_type, snake_case, Source};
+use crate::Error;
+
+/// Read the DBC file and write the generated Rust source into `out`.
+pub fn generate<P: AsRef<Path>, Q: AsRef<Path>>(dbc: P, out: Q) -> Result<(), Error> {
+    let dbc = fs::read_to_string(dbc)?;
+    fs::write(out, generate_str(&dbc)?)?;
+    Ok(())
+}
+
+/// Generate the Rust source of the DBC database.
+pub fn generate_str(dbc: &str) -> Result<String, Error> {
+    let messages = parse(dbc)?;
+
+    let mut src = Source::new();
+    src.line("// Generated by packed_struct_build from a DBC file, do not edit.");
+    src.line("");
+    src.line("#[allow(unused_imports)]");
+    src.line("use packed_struct::prelude::*;");
+    for message in messages.iter().filter(|m| !m.signals.is_empty()) {
+        message.write(&mut src)?;
+    }
+    Ok(src.into_string())
+}
+
+/// Bit `n` of the sawtooth numbering in MSB0, and back.
+fn msb0(n: usize) -> usize {
+    (n / 8) * 8 + 7 - (n % 8)
+}
+
+struct Message {
+    /// The identifier, with bit 31 set for extended identifiers.
+    id: u32,
+    name: String,
+    size: usize,
+    transmitter: String,
+    comment: String,
+    signals: Vec<Signal>,
+}
+
+impl Message {
+    fn parse(p: &mut Parser) -> Result<Self, Error> {
+        p.keyword("BO_")?;
+        let id = p.number()?;
+        let name = p.word()?;
+        p.punct(':')?;
+        let size = p.number()?;
+        let transmitter = p.word()?;
+
+        Ok(Message { id, name, size, transmitter, comment: String::new(), signals: vec![] })
+    }
+
+    fn signal_mut(&mut self, name: &str) -> Option<&mut Signal> {
+        self.signals.iter_mut().find(|s| s.name == name)
+    }
+
+    fn write(&self, src: &mut Source) -> Result<(), Error> {
+        let struct_name = camel_case(&self.name);
+
+        for signal in &self.signals {
+            signal.write_enum(src, &struct_name);
+        }
+
+        src.line("");
+        src.doc(&self.comment);
+        if self.transmitter != "Vector__XXX" {
+            if !self.comment.is_empty() {
+                src.line("///");
+            }
+            src.line(&format!("/// Sent by `{}`.", self.transmitter));
+        }
+        src.line("#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]");
+        src.line(&format!("#[packed_struct(bit_numbering=\"sawtooth\", size_bytes=\"{}\")]", self.size));
+        src.open(&format!("pub struct {} {{", struct_name));
+        for signal in &self.signals {
+            if !signal.fits(self.size) {
+                return Err(Error::Invalid(format!("signal {} is outside of message {}", signal.name, self.name)));
+            }
+            signal.write(src, &struct_name);
+        }
+        src.close("}");
+
+        src.line("");
+        src.open(&format!("impl {} {{", struct_name));
+        src.line("/// The message's CAN identifier.");
+        src.line(&format!("pub const ID: u32 = 0x{:X};", self.id & 0x1FFF_FFFF));
+        src.line("/// Whether the identifier is a 29-bit extended identifier.");
+        src.line(&format!("pub const EXTENDED: bool = {};", self.id & 0x8000_0000 != 0));
+        for signal in &self.signals {
+            signal.write_physical(src);
+        }
+        src.close("}");
+        Ok(())
+    }
+}
+
+struct Signal {
+    name: String,
+    /// The first bit in the DBC file: the least significant bit of Intel
+    /// signals, the most significant bit of Motorola signals.
+    start: usize,
+    width: usize,
+    little_endian: bool,
+    signed: bool,
+    factor: f64,
+    offset: f64,
+    min: f64,
+    max: f64,
+    unit: String,
+    comment: String,
+    values: Vec<(i64, String)>,
+}
+
+impl Signal {
+    /// Parse a signal, `None` if it's multiplexed.
+    fn parse(p: &mut Parser) -> Result<Option<Self>, Error> {
+        p.keyword("SG_")?;
+        let name = p.word()?;
+        let multiplexed = match p.peek() {
+            Some(Token::Word(_)) => p.word()?.starts_with('m'),
+            _ => false
+        };
+        p.punct(':')?;
+        let start = p.number()?;
+        p.punct('|')?;
+        let width: usize = p.number()?;
+        p.punct('@')?;
+        let (little_endian, signed) = match p.word()?.as_str() {
+            "1+" => (true, false),
+            "1-" => (true, true),
+            "0+" => (false, false),
+            "0-" => (false, true),
+            other => return Err(p.error(&format!("invalid byte order and sign {}", other)))
+        };
+        p.punct('(')?;
+        let factor = p.number()?;
+        p.punct(',')?;
+        let offset = p.number()?;
+        p.punct(')')?;
+        p.punct('[')?;
+        let min = p.number()?;
+        p.punct('|')?;
+        let max = p.number()?;
+        p.punct(']')?;
+        let unit = p.string()?;
+        // the receiving nodes
+        while p.peek().is_some() && !p.at_line_start() {
+            p.next()?;
+        }
+
+        if width == 0 || width > 64 {
+            return Err(Error::Invalid(format!("signal {} has {} bits", name, width)));
+        }
+        if multiplexed {
+            return Ok(None);
+        }
+
+        Ok(Some(Signal {
+            name,
+            start,
+            width,
+            little_endian,
+            signed,
+            factor,
+            offset,
+            min,
+            max,
+            unit,
+            comment: String::new(),
+            values: vec![],
+        }))
+    }
+
+    /// Whether the signal's bits are within the message. Intel signals count
+    /// up from their start bit, Motorola signals count up in MSB0.
+    fn fits(&self, size_bytes: usize) -> bool {
+        let last = if self.little_endian { self.start } else { msb0(self.start) } + self.width - 1;
+        last < size_bytes * 8
+    }
+
+    /// The bit at the other end of the signal, in the DBC's numbering.
+    fn end(&self) -> usize {
+        if self.little_endian {
+            self.start + self.width - 1
+        } else {
+            msb0(msb0(self.start) + self.width - 1)
+        }
+    }
+
+    fn raw_range(&self) -> (i128, i128) {
+        if self.signed {
+            (-(1 << (self.width - 1)), (1 << (self.width - 1)) - 1)
+        } else {
+            (0, (1 << self.width) - 1)
+        }
+    }
+
+    fn is_scaled(&self) -> bool {
+        self.values.is_empty() && (self.factor != 1.0 || self.offset != 0.0)
+    }
+
+    fn enum_name(&self, struct_name: &str) -> String {
+        format!("{}{}", struct_name, camel_case(&self.name))
+    }
+
+    fn write_enum(&self, src: &mut Source, struct_name: &str) {
+        if self.values.is_empty() {
+            return;
+        }
+        src.line("");
+        src.line(&format!("/// Values of the `{}` signal.", self.name));
+        src.line(&format!("#[derive(PrimitiveEnum_{}, Debug, Copy, Clone, PartialEq)]", primitive_type(self.width, self.signed)));
+        src.open(&format!("pub enum {} {{", self.enum_name(struct_name)));
+        let mut names: Vec<String> = vec![];
+        for &(value, ref description) in &self.values {
+            let mut name = camel_case(description);
+            while names.contains(&name) {
+                name.push('_');
+            }
+            src.line(&format!("{} = {},", name, value));
+            names.push(name);
+        }
+        src.close("}");
+    }
+
+    fn write(&self, src: &mut Source, struct_name: &str) {
+        let bits = if self.width == 1 { self.start.to_string() } else { format!("{}..={}", self.start, self.end()) };
+        let mut attributes = format!("bits=\"{}\"", bits);
+        let ty = if !self.values.is_empty() {
+            attributes.push_str(", ty=\"enum\"");
+            let enum_name = self.enum_name(struct_name);
+            if (self.values.len() as u128) < (1u128 << self.width) {
+                format!("EnumCatchAll<{}>", enum_name)
+            } else {
+                enum_name
+            }
+        } else if self.width == 1 && !self.signed {
+            "bool".to_string()
+        } else {
+            integer_type(self.width, self.signed)
+        };
+        if ty != "bool" {
+            attributes.push_str(if self.little_endian { ", endian=\"lsb\"" } else { ", endian=\"msb\"" });
+        }
+
+        src.doc(&self.comment);
+        let mut physical = vec![];
+        if self.is_scaled() {
+            let mut formula = "physical value = raw".to_string();
+            if self.factor != 1.0 {
+                formula.push_str(&format!(" * {}", self.factor));
+            }
+            if self.offset != 0.0 {
+                formula.push_str(&format!(" {} {}", if self.offset < 0.0 { '-' } else { '+' }, self.offset.abs()));
+            }
+            physical.push(formula);
+        }
+        if !self.unit.is_empty() {
+            physical.push(format!("in {}", self.unit));
+        }
+        // the range of the raw values doesn't need to be repeated
+        let (raw_min, raw_max) = self.raw_range();
+        let (a, b) = (raw_min as f64 * self.factor + self.offset, raw_max as f64 * self.factor + self.offset);
+        if self.min < self.max && (self.min, self.max) != (a.min(b), a.max(b)) {
+            physical.push(format!("from {} to {}", self.min, self.max));
+        }
+        if !physical.is_empty() {
+            if !self.comment.is_empty() {
+                src.line("///");
+            }
+            let mut text = physical.join(", ");
+            text[..1].make_ascii_uppercase();
+            src.line(&format!("/// {}.", text));
+        }
+        src.line(&format!("#[packed_field({})]", attributes));
+        src.line(&format!("pub {}: {},", snake_case(&self.name), ty));
+    }
+
+    /// Functions that convert between the raw and the physical value.
+    fn write_physical(&self, src: &mut Source) {
+        if !self.is_scaled() || self.width == 1 {
+            return;
+        }
+
+        let field = snake_case(&self.name);
+        let name = field.trim_end_matches('_');
+        let is_primitive = matches!(self.width, 8 | 16 | 32 | 64 | 128);
+        let raw = if is_primitive { format!("self.{}", field) } else { format!("*self.{}", field) };
+        let unit = if self.unit.is_empty() { String::new() } else { format!(", in {}", self.unit) };
+
+        let mut to_physical = format!("{} as f64", raw);
+        let mut to_raw = "value".to_string();
+        if self.offset != 0.0 {
+            to_raw = format!("({} {} {:?})", to_raw, if self.offset < 0.0 { "+" } else { "-" }, self.offset.abs());
+        }
+        if self.factor != 1.0 {
+            to_physical = format!("{} * {:?}", to_physical, self.factor);
+            to_raw = format!("{} / {:?}", to_raw, self.factor);
+        }
+        if self.offset != 0.0 {
+            to_physical = format!("{} {} {:?}", to_physical, if self.offset < 0.0 { "-" } else { "+" }, self.offset.abs());
+        }
+
+        let (min, max) = self.raw_range();
+        let primitive = primitive_type(self.width, self.signed);
+        let assign = if is_primitive { format!("raw as {}", primitive) } else { format!("(raw as {}).into()", primitive) };
+
+        src.line("");
+        src.line(&format!("/// The physical value of `{}`{}.", field, unit));
+        src.open(&format!("pub fn {}_physical(&self) -> f64 {{", name));
+        src.line(&to_physical);
+        src.close("}");
+        src.line("");
+        src.line(&format!("/// Set `{}` to the raw value closest to the physical value{}.", field, unit));
+        src.line("/// Returns `InvalidValue` if it's out of the signal's range.");
+        src.open(&format!("pub fn set_{}_physical(&mut self, value: f64) -> ::packed_struct::PackingResult<()> {{", name));
+        src.line(&format!("let raw = ({}).round();", to_raw));
+        src.open(&format!("if !({:?}..={:?}).contains(&raw) {{", min as f64, max as f64));
+        src.line("return Err(PackingError::InvalidValue);");
+        src.close("}");
+        src.line(&format!("self.{} = {};", field, assign));
+        src.line("Ok(())");
+        src.close("}");
+    }
+}
+
+/// Parse the messages, with the comments and value tables of them and their signals.
+fn parse(dbc: &str) -> Result<Vec<Message>, Error> {
+    let mut p = Parser { tokens: tokenize(dbc)?, pos: 0 };
+    let mut messages: Vec<Message> = vec![];
+
+    while let Some(token) = p.peek() {
+        match *token {
+            Token::Word(ref w) if w == "BO_" => messages.push(Message::parse(&mut p)?),
+            Token::Word(ref w) if w == "SG_" => {
+                let signal = Signal::parse(&mut p)?;
+                if let (Some(signal), Some(message)) = (signal, messages.last_mut()) {
+                    message.signals.push(signal);
+                }
+            },
+            Token::Word(ref w) if w == "CM_" => parse_comment(&mut p, &mut messages)?,
+            Token::Word(ref w) if w == "VAL_" => parse_value_table(&mut p, &mut messages)?,
+            Token::Word(ref w) if w == "NS_" => {
+                // the list of the new symbols, up to the bit timing
+                p.next()?;
+                while p.peek().is_some() && p.peek() != Some(&Token::Word("BS_".into())) {
+                    p.next()?;
+                }
+            },
+            _ => p.skip_statement()?
+        }
+    }
+
+    // the pseudo message of the signals that aren't sent in any message
+    messages.retain(|m| m.name != "VECTOR__INDEPENDENT_SIG_MSG");
+    Ok(messages)
+}
+
+/// `CM_ BO_ <id> "..";` or `CM_ SG_ <id> <signal> "..";`, other comments are skipped.
+fn parse_comment(p: &mut Parser, messages: &mut [Message]) -> Result<(), Error> {
+    p.keyword("CM_")?;
+    match p.peek() {
+        Some(Token::Word(w)) if w == "BO_" => {
+            p.next()?;
+            let id: u32 = p.number()?;
+            let comment = p.string()?;
+            if let Some(message) = messages.iter_mut().find(|m| m.id == id) {
+                message.comment = comment;
+            }
+        },
+        Some(Token::Word(w)) if w == "SG_" => {
+            p.next()?;
+            let id: u32 = p.number()?;
+            let name = p.word()?;
+            let comment = p.string()?;
+            if let Some(signal) = messages.iter_mut().find(|m| m.id == id).and_then(|m| m.signal_mut(&name)) {
+                signal.comment = comment;
+            }
+        },
+        _ => ()
+    }
+    p.skip_past(';')
+}
+
+/// `VAL_ <id> <signal> (<value> "<name>")* ;`, value tables of environment variables are skipped.
+fn parse_value_table(p: &mut Parser, messages: &mut [Message]) -> Result<(), Error> {
+    p.keyword("VAL_")?;
+    let id: u32 = match p.peek() {
+        Some(Token::Word(w)) if w.parse::<u32>().is_ok() => p.number()?,
+        _ => return p.skip_past(';')
+    };
+    let name = p.word()?;
+
+    let mut values = vec![];
+    while p.peek() != Some(&Token::Punct(';')) {
+        let value: i64 = p.number()?;
+        let description = p.string()?;
+        values.push((value, description));
+    }
+    p.punct(';')?;
+
+    if let Some(signal) = messages.iter_mut().find(|m| m.id == id).and_then(|m| m.signal_mut(&name)) {
+        let (min, max) = signal.raw_range();
+        for (value, description) in values {
+            // values that the signal can't hold can't be variants
+            if (value as i128) < min || (value as i128) > max || signal.values.iter().any(|v| v.0 == value) {
+                continue;
+            }
+            signal.values.push((value, description));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Punct(char),
+}
+
+/// The tokens of the file, with the lines they start on.
+fn tokenize(dbc: &str) -> Result<Vec<(Token, usize)>, Error> {
+    const PUNCT: &str = ":|@(),[];";
+
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = dbc.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => (),
+            '"' => {
+                let start_line = line;
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.peek() == Some(&'"') => text.push(chars.next().unwrap()),
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            text.push(c);
+                        },
+                        None => return Err(Error::Invalid(format!("line {}: unterminated string", start_line)))
+                    }
+                }
+                tokens.push((Token::Str(text), start_line));
+            },
+            c if PUNCT.contains(c) => tokens.push((Token::Punct(c), line)),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '"' || PUNCT.contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map_or(1, |t| t.1)
+    }
+
+    /// The next token is the first one on its line.
+    fn at_line_start(&self) -> bool {
+        self.pos == 0 || self.tokens.get(self.pos).map(|t| t.1) != self.tokens.get(self.pos - 1).map(|t| t.1)
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::Invalid(format!("line {}: {}", self.line(), msg))
+    }
+
+    fn next(&mut self) -> Result<Token, Error> {
+        let token = self.tokens.get(self.pos).ok_or_else(|| self.error("unexpected end of file"))?.0.clone();
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn word(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Word(w) => Ok(w),
+            other => Err(self.error(&format!("expected a name or a number, found {:?}", other)))
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        match self.word()? {
+            ref w if w == keyword => Ok(()),
+            other => Err(self.error(&format!("expected {}, found {}", keyword, other)))
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, Error> {
+        let word = self.word()?;
+        word.parse().map_err(|_| self.error(&format!("invalid number {}", word)))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Str(s) => Ok(s),
+            other => Err(self.error(&format!("expected a string, found {:?}", other)))
+        }
+    }
+
+    fn punct(&mut self, c: char) -> Result<(), Error> {
+        match self.next()? {
+            Token::Punct(p) if p == c => Ok(()),
+            other => Err(self.error(&format!("expected '{}', found {:?}", c, other)))
+        }
+    }
+
+    fn skip_past(&mut self, c: char) -> Result<(), Error> {
+        while self.next()? != Token::Punct(c) {}
+        Ok(())
+    }
+
+    /// Skip a statement that isn't generated, up to the next line that starts with a keyword.
+    fn skip_statement(&mut self) -> Result<(), Error> {
+        self.next()?;
+        while let Some(token) = self.peek() {
+            let is_keyword = matches!(*token, Token::Word(ref w) if w.ends_with('_') && w.chars().all(|c| c.is_ascii_uppercase() || c == '_'));
+            if is_keyword && self.at_line_start() {
+                break;
+            }
+            self.next()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! Build script helpers that generate `packed_struct` definitions from
+//! hardware description files, instead of transcribing them by hand:
+//! register structures from CMSIS-SVD files with the `svd` module, and CAN
+//! message structures from DBC files with the `dbc` module.
+//!
+//! The generated Rust source is written to a file, usually in `OUT_DIR`, and
+//! included into the crate that depends on `packed_struct`.
+//!
+//! ```rust,no_run
+//! // build.rs
+//! use std::env;
+//! use std::path::PathBuf;
+//!
+//! fn main() {
+//!     let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("device.rs");
+//!     packed_struct_build::svd::generate("device.svd", &out).unwrap();
+//!     println!("cargo:rerun-if-changed=device.svd");
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! // src/lib.rs
+//! pub mod device {
+//!     include!(concat!(env!("OUT_DIR"), "/device.rs"));
+//! }
+//! ```
+
+extern crate roxmltree;
+
+use std::fmt;
+use std::io;
+
+mod codegen;
+pub mod dbc;
+pub mod svd;
+
+/// Errors of the generators.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Xml(roxmltree::Error),
+    /// The description file is missing something or has an invalid value.
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "I/O error: {}", e),
+            Error::Xml(ref e) => write!(f, "XML error: {}", e),
+            Error::Invalid(ref msg) => write!(f, "Invalid description: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Xml(ref e) => Some(e),
+            Error::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Self {
+        Error::Xml(e)
+    }
+}
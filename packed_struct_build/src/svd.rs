@@ -0,0 +1,430 @@
+//! Register structures from CMSIS-SVD device descriptions.
+//!
+//! Every peripheral becomes a module with its base address and a
+//! `#[derive(PackedStruct)]` structure per register, numbered `lsb0` like the
+//! data sheet. The packed bytes hold the register's value MSB first, which
+//! `Mmio` reads with `WordOrder::Msb`, the generated `mmio` functions set that
+//! up. Enumerated values become `PrimitiveEnum`s, wrapped in `EnumCatchAll`
+//! if they don't cover every value of the field, and descriptions become doc
+//! comments.
+//!
+//! Register clusters are flattened into their peripheral, with the cluster's
+//! name as a prefix. Register arrays get a single structure, whose `mmio`
+//! function takes the index of the element.
+
+use std::fs;
+use std::path::Path;
+
+use roxmltree::Node;
+
+use crate::codegen::{camel_case, integer_type, snake_case, unsigned_type, Source};
+use crate::Error;
+
+/// Read the SVD file and write the generated Rust source into `out`.
+pub fn generate<P: AsRef<Path>, Q: AsRef<Path>>(svd: P, out: Q) -> Result<(), Error> {
+    let xml = fs::read_to_string(svd)?;
+    fs::write(out, generate_str(&xml)?)?;
+    Ok(())
+}
+
+/// Generate the Rust source of the SVD document.
+pub fn generate_str(xml: &str) -> Result<String, Error> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let device = doc.root_element();
+    if !device.has_tag_name("device") {
+        return Err(Error::Invalid("the root element isn't a <device>".into()));
+    }
+
+    let defaults = RegisterProperties::default().inherit(device)?;
+    let mut peripherals = vec![];
+    if let Some(list) = child(device, "peripherals") {
+        for node in children(list, "peripheral") {
+            peripherals.push(Peripheral::parse(node, &defaults)?);
+        }
+    }
+
+    let mut src = Source::new();
+    src.line(&format!("// Generated by packed_struct_build from the SVD description of {}, do not edit.", required_text(device, "name")?));
+    for peripheral in &peripherals {
+        src.line("");
+        peripheral.write(&mut src, &peripherals)?;
+    }
+    Ok(src.into_string())
+}
+
+/// The properties that registers inherit from their device, peripheral and cluster.
+#[derive(Default, Clone)]
+struct RegisterProperties {
+    size: Option<usize>,
+    access: Option<String>,
+    reset_value: Option<u64>,
+}
+
+impl RegisterProperties {
+    fn inherit(&self, node: Node) -> Result<Self, Error> {
+        Ok(RegisterProperties {
+            size: number(node, "size")?.map(|s| s as usize).or(self.size),
+            access: text(node, "access").map(String::from).or_else(|| self.access.clone()),
+            reset_value: number(node, "resetValue")?.or(self.reset_value),
+        })
+    }
+}
+
+struct Peripheral {
+    name: String,
+    description: String,
+    base_address: u64,
+    derived_from: Option<String>,
+    registers: Vec<Register>,
+}
+
+impl Peripheral {
+    fn parse(node: Node, defaults: &RegisterProperties) -> Result<Self, Error> {
+        let properties = defaults.inherit(node)?;
+        let mut registers = vec![];
+        if let Some(list) = child(node, "registers") {
+            parse_registers(list, &properties, "", 0, None, &mut registers)?;
+        }
+
+        Ok(Peripheral {
+            name: required_text(node, "name")?.to_string(),
+            description: text(node, "description").unwrap_or_default().to_string(),
+            base_address: required_number(node, "baseAddress")?,
+            derived_from: node.attribute("derivedFrom").map(String::from),
+            registers,
+        })
+    }
+
+    fn write(&self, src: &mut Source, peripherals: &[Peripheral]) -> Result<(), Error> {
+        src.doc(&self.description);
+        src.open(&format!("pub mod {} {{", snake_case(&self.name)));
+
+        match self.derived_from {
+            Some(ref base) if self.registers.is_empty() => {
+                if !peripherals.iter().any(|p| p.name == *base) {
+                    return Err(Error::Invalid(format!("peripheral {} is derived from the unknown {}", self.name, base)));
+                }
+                src.line(&format!("pub use super::{}::*;", snake_case(base)));
+                src.line("");
+            },
+            _ => {
+                src.line("#[allow(unused_imports)]");
+                src.line("use packed_struct::prelude::*;");
+                src.line("use packed_struct::mmio::{AccessWidth, Mmio, WordOrder};");
+                src.line("");
+            }
+        }
+
+        src.line("/// Base address of the peripheral.");
+        src.line(&format!("pub const BASE_ADDRESS: usize = 0x{:X};", self.base_address));
+
+        for register in &self.registers {
+            register.write(src)?;
+        }
+
+        src.close("}");
+        Ok(())
+    }
+}
+
+struct Register {
+    name: String,
+    description: String,
+    offset: u64,
+    size: usize,
+    reset_value: u64,
+    /// Number of elements and their address increment, for register arrays.
+    dim: Option<(u64, u64)>,
+    fields: Vec<Field>,
+}
+
+/// Parse the registers and clusters of the list, flattening the clusters.
+fn parse_registers(list: Node, properties: &RegisterProperties, prefix: &str, offset: u64, dim: Option<(u64, u64)>, registers: &mut Vec<Register>) -> Result<(), Error> {
+    for node in list.children().filter(|n| n.has_tag_name("register") || n.has_tag_name("cluster")) {
+        let node_properties = properties.inherit(node)?;
+        let name = format!("{}{}", prefix, required_text(node, "name")?.replace("[%s]", "").replace("%s", ""));
+        let node_offset = offset + required_number(node, "addressOffset")?;
+        let node_dim = match number(node, "dim")? {
+            Some(count) => Some((count, required_number(node, "dimIncrement")?)),
+            None => dim
+        };
+
+        if node.has_tag_name("cluster") {
+            parse_registers(node, &node_properties, &format!("{}_", name), node_offset, node_dim, registers)?;
+        } else {
+            let size = node_properties.size.ok_or_else(|| Error::Invalid(format!("register {} has no size", name)))?;
+            let mut fields = vec![];
+            if let Some(list) = child(node, "fields") {
+                for field in children(list, "field") {
+                    fields.push(Field::parse(field, &name, node_properties.access.as_deref())?);
+                }
+            }
+
+            registers.push(Register {
+                description: text(node, "description").unwrap_or_default().to_string(),
+                offset: node_offset,
+                size,
+                reset_value: node_properties.reset_value.unwrap_or(0),
+                dim: node_dim,
+                fields,
+                name,
+            });
+        }
+    }
+    Ok(())
+}
+
+impl Register {
+    fn write(&self, src: &mut Source) -> Result<(), Error> {
+        let struct_name = camel_case(&self.name);
+        if self.size == 0 || !self.size.is_multiple_of(8) {
+            return Err(Error::Invalid(format!("register {} has a size of {} bits", self.name, self.size)));
+        }
+
+        for field in &self.fields {
+            field.write_enum(src, &struct_name)?;
+        }
+
+        src.line("");
+        src.doc(&self.description);
+        src.line("#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]");
+        src.line(&format!("#[packed_struct(bit_numbering=\"lsb0\", size_bytes=\"{}\", endian=\"msb\")]", self.size / 8));
+        src.open(&format!("pub struct {} {{", struct_name));
+        if self.fields.is_empty() {
+            src.line(&format!("#[packed_field(bits=\"{}:0\")]", self.size - 1));
+            src.line(&format!("pub value: {},", integer_type(self.size, false)));
+        }
+        for field in &self.fields {
+            if field.msb >= self.size {
+                return Err(Error::Invalid(format!("field {} is outside of register {}", field.name, self.name)));
+            }
+            field.write(src, &struct_name);
+        }
+        src.close("}");
+
+        src.line("");
+        src.open(&format!("impl {} {{", struct_name));
+        src.line("/// Offset of the register from the peripheral's base address.");
+        src.line(&format!("pub const ADDRESS_OFFSET: usize = 0x{:X};", self.offset));
+        src.line("/// The register's value after a reset.");
+        src.line(&format!("pub const RESET_VALUE: {} = 0x{:X};", unsigned_type(self.size), self.reset_value));
+
+        let width = match self.size {
+            8 => Some("Bits8"),
+            16 => Some("Bits16"),
+            32 => Some("Bits32"),
+            _ => None
+        };
+        if let Some(width) = width {
+            let (params, address) = match self.dim {
+                Some((count, increment)) => {
+                    src.line("/// Number of elements of the register array.");
+                    src.line(&format!("pub const DIM: usize = {};", count));
+                    src.line("/// Address increment between the elements.");
+                    src.line(&format!("pub const DIM_INCREMENT: usize = 0x{:X};", increment));
+                    ("base_address: usize, index: usize", "base_address + Self::ADDRESS_OFFSET + index * Self::DIM_INCREMENT")
+                },
+                None => ("base_address: usize", "base_address + Self::ADDRESS_OFFSET")
+            };
+
+            src.line("");
+            src.line("/// Volatile access to the register of the peripheral at the base address.");
+            src.line("///");
+            src.line("/// # Safety");
+            src.line("///");
+            src.line("/// The base address has to be the address of the peripheral.");
+            src.open(&format!("pub unsafe fn mmio({}) -> ::packed_struct::PackingResult<Mmio<Self>> {{", params));
+            if self.dim.is_some() {
+                src.open("if index >= Self::DIM {");
+                src.line("return Err(PackingError::InvalidValue);");
+                src.close("}");
+            }
+            src.line(&format!("Ok(Mmio::from_address({})", address));
+            src.line(&format!("    .with_access_width(AccessWidth::{})?", width));
+            src.line("    .with_word_order(WordOrder::Msb))");
+            src.close("}");
+        }
+        src.close("}");
+        Ok(())
+    }
+}
+
+struct Field {
+    name: String,
+    description: String,
+    lsb: usize,
+    msb: usize,
+    access: Option<&'static str>,
+    values: Vec<EnumValue>,
+}
+
+struct EnumValue {
+    name: String,
+    description: String,
+    value: u64,
+}
+
+impl Field {
+    fn parse(node: Node, register: &str, register_access: Option<&str>) -> Result<Self, Error> {
+        let name = required_text(node, "name")?.to_string();
+        let (lsb, msb) = if let Some(range) = text(node, "bitRange") {
+            let range = range.trim_start_matches('[').trim_end_matches(']');
+            let (msb, lsb) = range.split_once(':').ok_or_else(|| Error::Invalid(format!("invalid bit range of field {} of register {}", name, register)))?;
+            (parse_number(lsb)? as usize, parse_number(msb)? as usize)
+        } else if let Some(lsb) = number(node, "lsb")? {
+            (lsb as usize, required_number(node, "msb")? as usize)
+        } else {
+            let offset = required_number(node, "bitOffset")? as usize;
+            let width = number(node, "bitWidth")?.unwrap_or(1) as usize;
+            if width == 0 {
+                return Err(Error::Invalid(format!("field {} of register {} has a width of 0 bits", name, register)));
+            }
+            (offset, offset + width - 1)
+        };
+        if msb < lsb {
+            return Err(Error::Invalid(format!("field {} of register {} has its msb {} below its lsb {}", name, register, msb, lsb)));
+        }
+        let width = msb - lsb + 1;
+
+        let access = match (text(node, "modifiedWriteValues"), text(node, "readAction"), text(node, "access").or(register_access)) {
+            (Some("oneToClear"), _, _) => Some("w1c"),
+            (_, Some("clear"), _) => Some("rc"),
+            (_, _, Some("read-only")) => Some("ro"),
+            (_, _, Some("write-only")) | (_, _, Some("writeOnce")) => Some("wo"),
+            _ => None
+        };
+
+        // the values that can be read, if the field has several sets
+        let value_sets: Vec<_> = children(node, "enumeratedValues").collect();
+        let value_set = value_sets.iter()
+            .find(|s| matches!(text(**s, "usage"), None | Some("read") | Some("read-write")))
+            .or_else(|| value_sets.first());
+
+        let mut values: Vec<EnumValue> = vec![];
+        if let Some(set) = value_set {
+            for value in children(*set, "enumeratedValue") {
+                let parsed = match text(value, "value") {
+                    // don't care bits can't be represented as a single value
+                    Some(v) if v.contains(['x', 'X']) && v.starts_with('#') => continue,
+                    Some(v) => parse_number(v)?,
+                    None => continue
+                };
+                if width < 64 && parsed >> width != 0 {
+                    return Err(Error::Invalid(format!("value 0x{:X} of field {} of register {} doesn't fit in {} bits", parsed, name, register, width)));
+                }
+                if values.iter().any(|v| v.value == parsed) {
+                    continue;
+                }
+                values.push(EnumValue {
+                    name: required_text(value, "name")?.to_string(),
+                    description: text(value, "description").unwrap_or_default().to_string(),
+                    value: parsed,
+                });
+            }
+        }
+
+        Ok(Field {
+            description: text(node, "description").unwrap_or_default().to_string(),
+            name,
+            lsb,
+            msb,
+            access,
+            values,
+        })
+    }
+
+    fn width(&self) -> usize {
+        self.msb - self.lsb + 1
+    }
+
+    fn enum_name(&self, struct_name: &str) -> String {
+        format!("{}{}", struct_name, camel_case(&self.name))
+    }
+
+    fn write_enum(&self, src: &mut Source, struct_name: &str) -> Result<(), Error> {
+        if self.values.is_empty() {
+            return Ok(());
+        }
+        if self.width() > 64 {
+            return Err(Error::Invalid(format!("enumerated field {} is wider than 64 bits", self.name)));
+        }
+
+        src.line("");
+        src.line(&format!("/// Values of the `{}` field.", self.name));
+        src.line(&format!("#[derive(PrimitiveEnum_{}, Debug, Copy, Clone, PartialEq)]", unsigned_type(self.width())));
+        src.open(&format!("pub enum {} {{", self.enum_name(struct_name)));
+        let mut names: Vec<String> = vec![];
+        for value in &self.values {
+            let mut name = camel_case(&value.name);
+            while names.contains(&name) {
+                name.push('_');
+            }
+            src.doc(&value.description);
+            src.line(&format!("{} = 0x{:X},", name, value.value));
+            names.push(name);
+        }
+        src.close("}");
+        Ok(())
+    }
+
+    fn write(&self, src: &mut Source, struct_name: &str) {
+        let bits = if self.lsb == self.msb { self.lsb.to_string() } else { format!("{}:{}", self.msb, self.lsb) };
+        let mut attributes = format!("bits=\"{}\"", bits);
+        let ty = if !self.values.is_empty() {
+            attributes.push_str(", ty=\"enum\"");
+            let enum_name = self.enum_name(struct_name);
+            if (self.values.len() as u128) < (1u128 << self.width()) {
+                format!("EnumCatchAll<{}>", enum_name)
+            } else {
+                enum_name
+            }
+        } else if self.width() == 1 {
+            "bool".to_string()
+        } else {
+            integer_type(self.width(), false)
+        };
+        if let Some(access) = self.access {
+            attributes.push_str(&format!(", access=\"{}\"", access));
+        }
+
+        src.doc(&self.description);
+        src.line(&format!("#[packed_field({})]", attributes));
+        src.line(&format!("pub {}: {},", snake_case(&self.name), ty));
+    }
+}
+
+fn children<'a, 'i: 'a>(node: Node<'a, 'i>, name: &'a str) -> impl Iterator<Item = Node<'a, 'i>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+fn child<'a, 'i: 'a>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text<'a, 'i: 'a>(node: Node<'a, 'i>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+fn required_text<'a, 'i: 'a>(node: Node<'a, 'i>, name: &str) -> Result<&'a str, Error> {
+    text(node, name).ok_or_else(|| Error::Invalid(format!("<{}> is missing the <{}> element", node.tag_name().name(), name)))
+}
+
+fn number(node: Node, name: &str) -> Result<Option<u64>, Error> {
+    text(node, name).map(parse_number).transpose()
+}
+
+fn required_number(node: Node, name: &str) -> Result<u64, Error> {
+    parse_number(required_text(node, name)?)
+}
+
+/// Parse a decimal, `0x` hex or `#` binary number.
+fn parse_number(s: &str) -> Result<u64, Error> {
+    let parsed = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix('#') {
+        u64::from_str_radix(bin, 2)
+    } else {
+        s.parse()
+    };
+    parsed.map_err(|_| Error::Invalid(format!("invalid number {}", s)))
+}
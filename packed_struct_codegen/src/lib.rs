@@ -18,6 +18,7 @@ mod pack_parse_attributes;
 
 mod common;
 mod primitive_enum;
+mod register_map;
 mod utils;
 mod utils_syn;
 
@@ -35,6 +36,16 @@ pub fn derive_packable_bytes(tokens: TokenStream) -> TokenStream {
         .into()
 }
 
+/// The derive macro that generates typed register accessors for a device's register map.
+#[proc_macro_derive(RegisterMap, attributes(register))]
+pub fn derive_register_map(tokens: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(tokens as DeriveInput);
+
+    register_map::derive(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
 /// A derive macro that generates packing and unpacking code for simple enum variants.
 /// It helps with converting your enums into integer types and back, with many other helper
 /// traits.
@@ -52,6 +52,28 @@ pub struct FieldRegular {
     pub bit_range: Range<usize>,
     /// The range that can be used by rust's slices. A single byte: 0..8
     pub bit_range_rust: Range<usize>,
+    pub access: FieldAccess,
+    /// The bit range counts in the byte-reversed structure, for little-endian
+    /// fields of sawtooth-numbered structures.
+    pub lsb_frame: bool,
+}
+
+impl FieldRegular {
+    /// The field's bits in the structure, MSB0.
+    pub fn frame_bits(&self, num_bytes: usize) -> Vec<usize> {
+        self.bit_range_rust
+            .clone()
+            .map(|i| if self.lsb_frame { (num_bytes - 1 - i / 8) * 8 + i % 8 } else { i })
+            .collect()
+    }
+
+    /// The MSB0 range that covers all of the field's bits in the structure.
+    pub fn frame_range(&self, num_bytes: usize) -> Range<usize> {
+        let bits = self.frame_bits(num_bytes);
+        let start = *bits.iter().min().unwrap();
+        let end = *bits.iter().max().unwrap();
+        start..end
+    }
 }
 
 impl Display for FieldRegular {
@@ -75,6 +97,10 @@ pub struct PackStruct<'a> {
     pub fields: Vec<FieldKind>,
     pub num_bytes: usize,
     pub num_bits: usize,
+    pub display: DisplayKind,
+    /// The trait with the atomic per-field methods of `AtomicPacked`.
+    pub atomic_fields: Option<syn::Ident>,
+    pub bit_numbering: BitNumbering,
     pub data_struct: &'a syn::DataStruct,
     pub derive_input: &'a syn::DeriveInput,
 }
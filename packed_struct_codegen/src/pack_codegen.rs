@@ -3,6 +3,7 @@ extern crate syn;
 
 use crate::common::*;
 use crate::pack::*;
+use crate::pack_parse::FieldAccess;
 use crate::pack_codegen_docs::*;
 use crate::utils::*;
 use syn::spanned::Spanned;
@@ -26,26 +27,11 @@ pub fn derive_pack(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream>
                        target: &dyn quote::ToTokens,
                        field: &FieldRegular|
          -> syn::Result<()> {
-            let bits = pack_bits(field);
-
-            let pack = pack_field(src, field);
-            let unpack = unpack_field(field)?;
-
-            let pack_bits = bits.pack;
-            let unpack_bits = bits.unpack;
-
-            pack_fields.push(quote! {
-                {
-                    let packed = { #pack };
-                    #pack_bits
-                }
-            });
+            pack_fields.push(pack_into_target(&quote! { self.#src }, field, num_bytes));
 
+            let unpack = unpack_from_src(field, num_bytes)?;
             unpack_fields.push(quote! {
-                let #target = {
-                    let bytes = { #unpack_bits };
-                    #unpack
-                };
+                let #target = #unpack;
             });
 
             Ok(())
@@ -93,9 +79,11 @@ pub fn derive_pack(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream>
 
     let debug_fmt = if include_debug_codegen() {
         let q = struct_runtime_formatter(parsed)?;
+        let dynamic = struct_dynamic(parsed);
 
         quote! {
             #q
+            #dynamic
 
             impl #impl_generics #name #ty_generics #where_clause {
                 #[allow(dead_code)]
@@ -110,6 +98,11 @@ pub fn derive_pack(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream>
         quote! {}
     };
 
+    let visit = struct_visit(parsed)?;
+    let layout = struct_layout(parsed)?;
+    let access = access_impl(parsed);
+    let atomic_fields = atomic_fields_impl(parsed)?;
+
     let q = quote! {
         #type_documentation
         impl #impl_generics ::packed_struct::PackedStruct for #name #ty_generics #where_clause {
@@ -146,11 +139,289 @@ pub fn derive_pack(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream>
             }
         }
         #debug_fmt
+        #visit
+        #layout
+        #access
+        #atomic_fields
     };
 
     Ok(q)
 }
 
+/// Packs the value of the expression into the field's bits of `target`, the
+/// bytes of the whole structure.
+fn pack_into_target(value: &proc_macro2::TokenStream, field: &FieldRegular, num_bytes: usize) -> proc_macro2::TokenStream {
+    let pack = pack_field(value, field);
+    let pack_bits = pack_bits(field).pack;
+
+    if field.lsb_frame {
+        // the field's bits are contiguous in the byte-reversed structure
+        quote! {
+            {
+                let packed = { #pack };
+                let reversed = {
+                    let mut target = [0 as u8; #num_bytes];
+                    #pack_bits
+                    target
+                };
+                for (t, r) in target.iter_mut().zip(reversed.iter().rev()) {
+                    *t |= *r;
+                }
+            }
+        }
+    } else {
+        quote! {
+            {
+                let packed = { #pack };
+                #pack_bits
+            }
+        }
+    }
+}
+
+/// Unpacks the field from `src`, the bytes of the whole structure.
+fn unpack_from_src(field: &FieldRegular, num_bytes: usize) -> syn::Result<proc_macro2::TokenStream> {
+    let unpack = unpack_field(field)?;
+    let unpack_bits = pack_bits(field).unpack;
+
+    Ok(if field.lsb_frame {
+        quote! {
+            {
+                let bytes = {
+                    let mut reversed: [u8; #num_bytes] = *src;
+                    reversed.reverse();
+                    let src = &reversed;
+                    #unpack_bits
+                };
+                #unpack
+            }
+        }
+    } else {
+        quote! {
+            {
+                let bytes = { #unpack_bits };
+                #unpack
+            }
+        }
+    })
+}
+
+/// Byte mask of the field's bits.
+fn field_bits_mask(num_bytes: usize, field: &FieldRegular) -> Vec<u8> {
+    let mut mask = vec![0; num_bytes];
+    for i in field.frame_bits(num_bytes) {
+        mask[i / 8] |= 1 << (7 - (i % 8));
+    }
+    mask
+}
+
+fn access_impl(parsed: &PackStruct) -> proc_macro2::TokenStream {
+    let name = &parsed.derive_input.ident;
+    let num_bytes = parsed.num_bytes;
+
+    let field_mask = |field: &FieldKind| -> (FieldAccess, Vec<u8>) {
+        match field {
+            FieldKind::Regular { field, .. } => (field.access, field_bits_mask(num_bytes, field)),
+            FieldKind::Array { elements, .. } => {
+                let mut mask = vec![0; num_bytes];
+                for e in elements {
+                    for (m, e) in mask.iter_mut().zip(field_bits_mask(num_bytes, e)) {
+                        *m |= e;
+                    }
+                }
+                (elements[0].access, mask)
+            }
+        }
+    };
+
+    let access_masks: Vec<_> = FieldAccess::ALL.iter().map(|access| {
+        let mut mask = vec![0; num_bytes];
+        for field in &parsed.fields {
+            let (field_access, field_mask) = field_mask(field);
+            if field_access == *access {
+                for (m, f) in mask.iter_mut().zip(field_mask) {
+                    *m |= f;
+                }
+            }
+        }
+
+        let access = access_variant(*access);
+        quote! {
+            #access => [#(#mask),*]
+        }
+    }).collect();
+
+    let mut clear_fns = vec![];
+    for field in &parsed.fields {
+        let (field_access, mask) = field_mask(field);
+        if field_access != FieldAccess::WriteOneToClear {
+            continue;
+        }
+
+        let ident = match field {
+            FieldKind::Regular { ident, .. } | FieldKind::Array { ident, .. } => ident,
+        };
+        let clear_fn = syn::Ident::new(&format!("clear_{}", ident), proc_macro2::Span::call_site());
+        let doc = format!("The value to write into the register to clear the write-1-to-clear field `{}`. \
+            The other write-1-to-clear fields are written as zeroes and are left untouched.", ident);
+
+        clear_fns.push(quote! {
+            #[doc = #doc]
+            pub fn #clear_fn(&self) -> ::packed_struct::PackingResult<[u8; #num_bytes]> {
+                use ::packed_struct::{PackedStruct, PackedStructAccess};
+
+                let current = self.pack()?;
+                let mut packed = self.pack_write(&current)?;
+                let mask: [u8; #num_bytes] = [#(#mask),*];
+                for (p, m) in packed.iter_mut().zip(mask.iter()) {
+                    *p |= m;
+                }
+                Ok(packed)
+            }
+        });
+    }
+
+    let clear_fns = if clear_fns.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #[allow(dead_code)]
+            impl #name {
+                #(#clear_fns)*
+            }
+        }
+    };
+
+    quote! {
+        impl ::packed_struct::PackedStructAccess for #name {
+            fn access_mask(access: ::packed_struct::FieldAccess) -> [u8; #num_bytes] {
+                match access {
+                    #(#access_masks),*
+                }
+            }
+        }
+
+        #clear_fns
+    }
+}
+
+/// The trait named by the `atomic_fields` attribute, with `set_<field>` and
+/// `update_<field>` methods for `AtomicPacked` that only exchange the field's bits.
+fn atomic_fields_impl(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let trait_name = match parsed.atomic_fields {
+        Some(ref t) => t,
+        None => return Ok(quote! {})
+    };
+    let name = &parsed.derive_input.ident;
+    let vis = &parsed.derive_input.vis;
+    let num_bytes = parsed.num_bytes;
+
+    if !parsed.derive_input.generics.params.is_empty() {
+        return Err(syn::Error::new(trait_name.span(), "Atomic fields of generic structures currently aren't supported."));
+    }
+    let atomic_width = match num_bytes {
+        1 => "8",
+        2 => "16",
+        3..=4 => "32",
+        5..=8 => "64",
+        _ => return Err(syn::Error::new(trait_name.span(), "Atomic fields require a structure of 8 bytes or less."))
+    };
+
+    let mut declarations = vec![];
+    let mut methods = vec![];
+    for field in &parsed.fields {
+        let (ident, elements) = match field {
+            FieldKind::Regular { ident, field } => (ident, vec![field]),
+            FieldKind::Array { ident, elements, .. } => (ident, elements.iter().collect())
+        };
+        // unused fields, like reserved bits
+        if ident.to_string().starts_with('_') {
+            continue;
+        }
+        let ty = parsed.data_struct.fields.iter()
+            .find(|f| f.ident.as_ref() == Some(ident))
+            .map(|f| &f.ty)
+            .expect("the field's declaration");
+
+        let mut mask = vec![0; num_bytes];
+        for e in &elements {
+            for (m, e) in mask.iter_mut().zip(field_bits_mask(num_bytes, e)) {
+                *m |= e;
+            }
+        }
+
+        let (pack, unpack) = match field {
+            FieldKind::Regular { field, .. } => (pack_into_target(&quote! { value }, field, num_bytes), unpack_from_src(field, num_bytes)?),
+            FieldKind::Array { elements, .. } => {
+                let pack: Vec<_> = elements.iter().enumerate()
+                    .map(|(i, e)| pack_into_target(&quote! { value[#i] }, e, num_bytes))
+                    .collect();
+                let unpack = elements.iter().map(|e| unpack_from_src(e, num_bytes)).collect::<syn::Result<Vec<_>>>()?;
+                (quote! { #(#pack)* }, quote! { [#(#unpack),*] })
+            }
+        };
+
+        let set_fn = syn::Ident::new(&format!("set_{}", ident), proc_macro2::Span::call_site());
+        let update_fn = syn::Ident::new(&format!("update_{}", ident), proc_macro2::Span::call_site());
+        let set_doc = format!("Set the `{}` field, leaving the other fields to concurrent writers.", ident);
+        let update_doc = format!("Modify the `{}` field with a compare-exchange loop on its bits. \
+            The closure might be called multiple times if other writers interfere. Returns the previous value.", ident);
+
+        declarations.push(quote! {
+            #[doc = #set_doc]
+            fn #set_fn(&self, value: #ty, order: ::packed_struct::atomic::Ordering) -> ::packed_struct::PackingResult<()>;
+
+            #[doc = #update_doc]
+            fn #update_fn<F>(&self, set_order: ::packed_struct::atomic::Ordering, fetch_order: ::packed_struct::atomic::Ordering, f: F) -> ::packed_struct::PackingResult<#ty>
+                where F: FnMut(#ty) -> #ty;
+        });
+
+        methods.push(quote! {
+            #[allow(unused_imports, unused_parens)]
+            fn #set_fn(&self, value: #ty, order: ::packed_struct::atomic::Ordering) -> ::packed_struct::PackingResult<()> {
+                use ::packed_struct::*;
+
+                let mut target = [0 as u8; #num_bytes];
+                #pack
+                self.store_bits(&[#(#mask),*], &target, order);
+                Ok(())
+            }
+
+            #[allow(unused_imports, unused_parens)]
+            fn #update_fn<F>(&self, set_order: ::packed_struct::atomic::Ordering, fetch_order: ::packed_struct::atomic::Ordering, mut f: F) -> ::packed_struct::PackingResult<#ty>
+                where F: FnMut(#ty) -> #ty
+            {
+                use ::packed_struct::*;
+
+                let unpack = |src: &[u8; #num_bytes]| -> ::packed_struct::PackingResult<#ty> {
+                    Ok(#unpack)
+                };
+                let previous = self.fetch_update_bits(&[#(#mask),*], set_order, fetch_order, |src| {
+                    let value = f(unpack(src)?);
+                    let mut target = [0 as u8; #num_bytes];
+                    #pack
+                    Ok(target)
+                })?;
+                unpack(&previous)
+            }
+        });
+    }
+
+    let doc = format!("Atomic access to the fields of `{}`, in an `AtomicPacked`.", name);
+    Ok(quote! {
+        #[cfg(target_has_atomic = #atomic_width)]
+        #[doc = #doc]
+        #vis trait #trait_name {
+            #(#declarations)*
+        }
+
+        #[cfg(target_has_atomic = #atomic_width)]
+        impl #trait_name for ::packed_struct::atomic::AtomicPacked<#name> {
+            #(#methods)*
+        }
+    })
+}
+
 struct PackBitsCopy {
     pack: proc_macro2::TokenStream,
     unpack: proc_macro2::TokenStream,
@@ -262,8 +533,8 @@ fn pack_bits(field: &FieldRegular) -> PackBitsCopy {
     }
 }
 
-fn pack_field(name: &dyn quote::ToTokens, field: &FieldRegular) -> proc_macro2::TokenStream {
-    let mut output = quote! { (self.#name) };
+fn pack_field(value: &proc_macro2::TokenStream, field: &FieldRegular) -> proc_macro2::TokenStream {
+    let mut output = quote! { (#value) };
 
     for wrapper in &field.serialization_wrappers {
         match wrapper {
@@ -3,96 +3,356 @@ use crate::common::*;
 use proc_macro2::Span;
 use quote::{ToTokens};
 use syn::parse_quote;
-use crate::utils::*;
 
 pub fn struct_runtime_formatter(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream> {
     let (impl_generics, ty_generics, where_clause) = parsed.derive_input.generics.split_for_impl();
     let name = &parsed.derive_input.ident;
-    let snake_name = to_snake_case(&name.to_string());
     let stdlib_prefix = collections_prefix();
-    let debug_fields_fn = syn::Ident::new(&format!("debug_fields_{}", snake_name), Span::call_site());
 
     let display_header = format!("{} ({} {})",
         name,
         parsed.num_bytes,
         if parsed.num_bytes == 1 { "byte" } else { "bytes" }
     );
-    
-    let mut debug_fields = vec![];
-    for field in &parsed.fields {
-        match field {
-            &FieldKind::Regular { ref ident, ref field } => {
-                let ref name_str = ident.to_string();
-                let bits: syn::ExprRange = syn::parse_str(&format!("{}..{}", field.bit_range.start, field.bit_range.end))?;
-                
-                debug_fields.push(quote! {
-                    ::packed_struct::debug_fmt::DebugBitField {
-                        name: #name_str.into(),
-                        bits: #bits,
-                        display_value: format!("{:?}", src.#ident).into()
-                    }
-                });
-            },
-            &FieldKind::Array { ref ident, ref elements, .. } => {
-                for (i, field) in elements.iter().enumerate() {
-                    let name_str = format!("{}[{}]", ident.to_string(), i);
-                    let bits: syn::ExprRange = syn::parse_str(&format!("{}..{}", field.bit_range.start, field.bit_range.end))?;
-                    
-                    debug_fields.push(quote! {
-                        ::packed_struct::debug_fmt::DebugBitField {
-                            name: #name_str.into(),
-                            bits: #bits,
-                            display_value: format!("{:?}", src.#ident[#i]).into()
-                        }
-                    });
-                }
-                
-            }
-        }
-    }
 
-    let num_fields = debug_fields.len();
     let num_bytes = parsed.num_bytes;
     let result_ty = result_type();
 
-    let q = quote! {
-        #[doc(hidden)]
-        pub fn #debug_fields_fn(src: &#name) -> [::packed_struct::debug_fmt::DebugBitField<'static>; #num_fields] {
-            [#(#debug_fields),*]
-        }
+    let display = match parsed.display {
+        DisplayKind::None => None,
+        DisplayKind::Table => Some(quote! { ::packed_struct::debug_fmt::PackedStructDisplay::new(self) }),
+        DisplayKind::Compact => Some(quote! { ::packed_struct::debug_fmt::PackedStructDisplay::new(self).compact() }),
+    };
+    let display_impl = match display {
+        Some(display) => quote! {
+            #[allow(unused_imports)]
+            impl #impl_generics #stdlib_prefix::fmt::Display for #name #ty_generics #where_clause {
+                #[allow(unused_imports)]
+                fn fmt(&self, f: &mut #stdlib_prefix::fmt::Formatter) -> #stdlib_prefix::fmt::Result {                
+                    let display = #display;
+                    display.fmt(f)
+                }
+            }
+        },
+        None => quote! {}
+    };
 
+    let q = quote! {
         #[allow(unused_imports)]
         impl #impl_generics ::packed_struct::debug_fmt::PackedStructDebug for #name #ty_generics #where_clause {
             fn fmt_fields(&self, fmt: &mut #stdlib_prefix::fmt::Formatter) -> #result_ty <(), #stdlib_prefix::fmt::Error> {
                 use ::packed_struct::PackedStruct;
                 
-                let fields = #debug_fields_fn(self);
                 let packed: [u8; #num_bytes] = self.pack()?;
-                ::packed_struct::debug_fmt::packable_fmt_fields(fmt, &packed, &fields)
+                ::packed_struct::debug_fmt::packable_fmt_fields(fmt, &packed, &self.debug_fields())
             }
 
             fn packed_struct_display_header() -> &'static str {
                 #display_header
             }
+
+            fn debug_fields(&self) -> #stdlib_prefix::vec::Vec<::packed_struct::debug_fmt::DebugBitField<'static>> {
+                ::packed_struct::debug_fmt::collect_debug_fields(self)
+            }
         }
 
         #[allow(unused_imports)]
-        impl #impl_generics #stdlib_prefix::fmt::Display for #name #ty_generics #where_clause {
-            #[allow(unused_imports)]
-            fn fmt(&self, f: &mut #stdlib_prefix::fmt::Formatter) -> #stdlib_prefix::fmt::Result {                
-                let display = ::packed_struct::debug_fmt::PackedStructDisplay::new(self);
-                display.fmt(f)
+        impl #impl_generics ::packed_struct::debug_fmt::PackedStructDiff for #name #ty_generics #where_clause {
+            fn diff(&self, other: &Self) -> ::packed_struct::PackingResult<::packed_struct::debug_fmt::PackedDiff<'static>> {
+                use ::packed_struct::PackedStruct;
+                use ::packed_struct::debug_fmt::PackedStructDebug;
+
+                let old_packed: [u8; #num_bytes] = self.pack()?;
+                let new_packed: [u8; #num_bytes] = other.pack()?;
+                Ok(::packed_struct::debug_fmt::PackedDiff::new(&old_packed, &self.debug_fields(), &new_packed, &other.debug_fields()))
             }
         }
+
+        #display_impl
     };
     
     Ok(q)
 }
 
+/// Walks the fields for `Visitor`s, available without `alloc` or `std`.
+pub fn struct_visit(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let (impl_generics, ty_generics, where_clause) = parsed.derive_input.generics.split_for_impl();
+    let name = &parsed.derive_input.ident;
+    let num_bytes = parsed.num_bytes;
+
+    let mut fields = vec![];
+    {
+        let mut push_field = |name_str: String, value: proc_macro2::TokenStream, field: &FieldRegular| -> syn::Result<()> {
+            let frame_range = field.frame_range(num_bytes);
+            let bits: syn::ExprRange = syn::parse_str(&format!("{}..{}", frame_range.start, frame_range.end))?;
+            let access = access_variant(field.access);
+            let endianness = match endianness_variant(field) {
+                Some(variant) => quote! { Some(::packed_struct::visit::IntegerEndianness::#variant) },
+                None => quote! { None }
+            };
+            let lsb_frame = if field.lsb_frame {
+                let reversed: syn::ExprRange = syn::parse_str(&format!("{}..{}", field.bit_range_rust.start, field.bit_range_rust.end - 1))?;
+                quote! { Some(::packed_struct::visit::LsbFrame { bits: #reversed, bytes: ctx.bytes(#num_bytes) }) }
+            } else {
+                quote! { None }
+            };
+
+            fields.push(quote! {
+                {
+                    let field = ::packed_struct::visit::VisitField {
+                        name: #name_str,
+                        bits: ctx.translate(#bits),
+                        lsb_frame: #lsb_frame,
+                        endianness: #endianness,
+                        packed: ctx.packed,
+                        value: &#value,
+                        integer: (&&&::packed_struct::visit::FieldValue(&#value)).visit_integer(),
+                        access: #access,
+                        reserved: (&::packed_struct::visit::FieldValue(&#value)).visit_reserved(),
+                        depth: ctx.depth,
+                        parent
+                    };
+                    visitor.visit_field(&field)?;
+                    (&::packed_struct::visit::FieldValue(&#value)).visit_nested(ctx, &field, visitor)?;
+                }
+            });
+            Ok(())
+        };
+
+        for field in &parsed.fields {
+            match field {
+                FieldKind::Regular { ident, field } => {
+                    push_field(ident.to_string(), quote! { self.#ident }, field)?;
+                },
+                FieldKind::Array { ident, elements, .. } => {
+                    for (i, field) in elements.iter().enumerate() {
+                        push_field(format!("{}[{}]", ident, i), quote! { self.#ident[#i] }, field)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::packed_struct::visit::PackedStructVisit for #name #ty_generics #where_clause {
+            #[allow(unused_imports)]
+            fn visit_fields<V: ::packed_struct::visit::Visitor + ?Sized>(&self, ctx: &::packed_struct::visit::VisitContext, parent: ::core::option::Option<&::packed_struct::visit::VisitField>, visitor: &mut V) -> ::core::result::Result<(), V::Error> {
+                use ::packed_struct::visit::{VisitInteger, VisitEnumInteger, VisitNoInteger, VisitNested, VisitNotNested, VisitReserved, VisitNotReserved};
+
+                #(#fields)*
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Field access by name, requires `alloc` or `std`.
+pub fn struct_dynamic(parsed: &PackStruct) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = parsed.derive_input.generics.split_for_impl();
+    let name = &parsed.derive_input.ident;
+
+    let mut get_fields = vec![];
+    let mut set_fields = vec![];
+    let mut push_field = |name_str: String, value: proc_macro2::TokenStream| {
+        let field_type = quote! { (&&&&::packed_struct::dynamic::FieldType::of(&#value)) };
+        get_fields.push(quote! {
+            #name_str => #field_type.get_dynamic(&#value, rest)
+        });
+        set_fields.push(quote! {
+            #name_str => #field_type.set_dynamic(&mut #value, rest, value)
+        });
+    };
+
+    for field in &parsed.fields {
+        match field {
+            FieldKind::Regular { ident, .. } => {
+                push_field(ident.to_string(), quote! { self.#ident });
+            },
+            FieldKind::Array { ident, elements, .. } => {
+                for i in 0..elements.len() {
+                    push_field(format!("{}[{}]", ident, i), quote! { self.#ident[#i] });
+                }
+            }
+        }
+    }
+
+    let uses = quote! {
+        use ::packed_struct::dynamic::{DynamicScalarField, DynamicEnumField, DynamicNestedField, DynamicBytesField, DynamicUnsupportedField};
+    };
+
+    quote! {
+        impl #impl_generics ::packed_struct::dynamic::PackedDynamic for #name #ty_generics #where_clause {
+            #[allow(unused_imports)]
+            fn get_field(&self, name: &str) -> ::packed_struct::PackingResult<::packed_struct::dynamic::Value> {
+                #uses
+
+                let (name, rest) = ::packed_struct::dynamic::split_path(name);
+                match name {
+                    #(#get_fields,)*
+                    _ => Err(::packed_struct::PackingError::InvalidValue)
+                }
+            }
+
+            #[allow(unused_imports)]
+            fn set_field(&mut self, name: &str, value: ::packed_struct::dynamic::Value) -> ::packed_struct::PackingResult<()> {
+                #uses
+
+                let (name, rest) = ::packed_struct::dynamic::split_path(name);
+                match name {
+                    #(#set_fields,)*
+                    _ => Err(::packed_struct::PackingError::InvalidValue)
+                }
+            }
+        }
+    }
+}
+
+/// Static layout metadata, available without `alloc` or `std`.
+pub fn struct_layout(parsed: &PackStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let (impl_generics, ty_generics, where_clause) = parsed.derive_input.generics.split_for_impl();
+    let name = &parsed.derive_input.ident;
+    let struct_name = name.to_string();
+    let num_bits = parsed.num_bits;
+    let num_bytes = parsed.num_bytes;
+    let last_bit = parsed.num_bytes * 8 - 1;
+
+    let bit_numbering = match parsed.bit_numbering {
+        BitNumbering::Msb0 => quote! { ::packed_struct::layout::BitNumbering::Msb0 },
+        BitNumbering::Lsb0 => quote! { ::packed_struct::layout::BitNumbering::Lsb0 },
+        BitNumbering::Sawtooth => quote! { ::packed_struct::layout::BitNumbering::Sawtooth },
+    };
+
+    let mut fields = vec![];
+    let mut nested = vec![];
+    for (field, syn_field) in parsed.fields.iter().zip(parsed.data_struct.fields.iter()) {
+        let doc = field_doc(syn_field);
+        let mut push_field = |name_str: String, field: &FieldRegular| -> syn::Result<()> {
+            let frame_range = field.frame_range(num_bytes);
+            let bits: syn::ExprRange = syn::parse_str(&format!("{}..{}", frame_range.start, frame_range.end))?;
+            let declared_bits = match parsed.bit_numbering {
+                BitNumbering::Msb0 => bits.clone(),
+                BitNumbering::Lsb0 => syn::parse_str(&format!("{}..{}", last_bit - frame_range.end, last_bit - frame_range.start))?,
+                BitNumbering::Sawtooth => {
+                    // the field's first and last bits, converting back from MSB0 is the same mapping
+                    let frame_bits = field.frame_bits(num_bytes);
+                    let sawtooth = |n: usize| (n / 8) * 8 + 7 - (n % 8);
+                    let (a, b) = (sawtooth(frame_bits[0]), sawtooth(frame_bits[frame_bits.len() - 1]));
+                    syn::parse_str(&format!("{}..{}", a.min(b), a.max(b)))?
+                }
+            };
+            let width = field.bit_width;
+            let endianness = match endianness_variant(field) {
+                Some(variant) => quote! { Some(::packed_struct::visit::IntegerEndianness::#variant) },
+                None => quote! { None }
+            };
+            let ty = &field.ty;
+            let type_name = tokens_to_string(ty).replace(' ', "");
+            let is_enum = field.serialization_wrappers.iter().any(|w| matches!(w, SerializationWrapper::PrimitiveEnumWrapper));
+            let variants = if is_enum {
+                quote! { <#ty as ::packed_struct::PrimitiveEnum>::VARIANTS }
+            } else {
+                quote! { &[] }
+            };
+            let access = access_variant(field.access);
+
+            nested.push(quote! {
+                #name_str => (&::packed_struct::layout::LayoutOf::<#ty>(::core::marker::PhantomData)).nested_layout()
+            });
+            fields.push(quote! {
+                ::packed_struct::layout::FieldInfo {
+                    name: #name_str,
+                    bits: #bits,
+                    declared_bits: #declared_bits,
+                    width: #width,
+                    endianness: #endianness,
+                    type_name: #type_name,
+                    variants: #variants,
+                    reserved: {
+                        use ::packed_struct::layout::NotReserved;
+                        ::packed_struct::layout::ReservedFill::<#ty>::FILL
+                    },
+                    access: #access,
+                    doc: #doc
+                }
+            });
+            Ok(())
+        };
+
+        match field {
+            FieldKind::Regular { ident, field } => {
+                push_field(ident.to_string(), field)?;
+            },
+            FieldKind::Array { ident, elements, .. } => {
+                for (i, field) in elements.iter().enumerate() {
+                    push_field(format!("{}[{}]", ident, i), field)?;
+                }
+            }
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::packed_struct::layout::PackedStructLayout for #name #ty_generics #where_clause {
+            const NAME: &'static str = #struct_name;
+            const FIELDS: &'static [::packed_struct::layout::FieldInfo] = &[#(#fields),*];
+            const BIT_NUMBERING: ::packed_struct::layout::BitNumbering = #bit_numbering;
+            const PACKED_BITS: usize = #num_bits;
+            const PACKED_BYTES: usize = #num_bytes;
+
+            #[allow(unused_imports)]
+            fn nested_layout(field: &str) -> ::core::option::Option<::packed_struct::layout::StructLayout> {
+                use ::packed_struct::layout::{NestedLayout, NoNestedLayout};
+
+                match field {
+                    #(#nested,)*
+                    _ => None
+                }
+            }
+        }
+    })
+}
+
+/// The lines of the field's doc comment.
+fn field_doc(field: &syn::Field) -> String {
+    let lines: Vec<_> = field.attrs.iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => Some(s.value().trim().to_string()),
+            _ => None
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Byte order of fields that are wider than a byte, and of little-endian
+/// sawtooth fields of any width, whose declared bits count the other way.
+fn endianness_variant(field: &FieldRegular) -> Option<syn::Ident> {
+    if field.lsb_frame {
+        return Some(syn::Ident::new("Lsb", Span::call_site()));
+    }
+    if field.bit_width <= 8 {
+        return None;
+    }
+
+    field.serialization_wrappers.iter().filter_map(|w| match w {
+        SerializationWrapper::EndiannesWrapper { endian } => {
+            let endian = tokens_to_string(endian);
+            let variant = if endian.starts_with("Lsb") || field.lsb_frame { "Lsb" } else { "Msb" };
+            Some(syn::Ident::new(variant, Span::call_site()))
+        },
+        _ => None
+    }).next()
+}
+
 use std::ops::Range;
 
+use crate::pack_parse::{BitNumbering, DisplayKind, FieldAccess};
 use crate::utils_syn::tokens_to_string;
 
+pub fn access_variant(access: FieldAccess) -> proc_macro2::TokenStream {
+    let variant = syn::Ident::new(access.variant_name(), Span::call_site());
+    quote! { ::packed_struct::FieldAccess::#variant }
+}
+
 
 pub fn type_docs(parsed: &PackStruct) -> proc_macro2::TokenStream {
     let mut doc = quote! {};
@@ -109,12 +369,21 @@ pub fn type_docs(parsed: &PackStruct) -> proc_macro2::TokenStream {
         size_bytes = parsed.num_bytes
     ));
 
+    let show_access = parsed.fields.iter().any(|f| match f {
+        FieldKind::Regular { field, .. } => field.access != FieldAccess::ReadWrite,
+        FieldKind::Array { elements, .. } => elements.iter().any(|e| e.access != FieldAccess::ReadWrite)
+    });
+
     doc_html("<table>\r\n");
-    doc_html("<thead><tr><td>Bit, MSB0</td><td>Name</td><td>Type</td></tr></thead>\r\n");
+    if show_access {
+        doc_html("<thead><tr><td>Bit, MSB0</td><td>Name</td><td>Type</td><td>Access</td></tr></thead>\r\n");
+    } else {
+        doc_html("<thead><tr><td>Bit, MSB0</td><td>Name</td><td>Type</td></tr></thead>\r\n");
+    }
     doc_html("<tbody>\r\n");
 
     {
-        let mut emit_field_docs = |bits: &Range<usize>, field_ident, ty| {
+        let mut emit_field_docs = |bits: &Range<usize>, field_ident, ty, access: FieldAccess| {
 
             let bits_str = {
                 if bits.start == bits.end {
@@ -126,17 +395,21 @@ pub fn type_docs(parsed: &PackStruct) -> proc_macro2::TokenStream {
 
             // todo: friendly integer, reserved types. add LSB/MSB integer info.
 
-            doc_html(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\r\n", bits_str, field_ident, tokens_to_string(ty)));
+            if show_access {
+                doc_html(&format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\r\n", bits_str, field_ident, tokens_to_string(ty), access.as_str()));
+            } else {
+                doc_html(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\r\n", bits_str, field_ident, tokens_to_string(ty)));
+            }
         };
 
         for field in &parsed.fields {
             match field {
                 &FieldKind::Regular { ref ident, ref field } => {
-                    emit_field_docs(&field.bit_range, ident.to_string(), &field.ty);
+                    emit_field_docs(&field.frame_range(parsed.num_bytes), ident.to_string(), &field.ty, field.access);
                 },
                 &FieldKind::Array { ref ident, ref elements, .. } => {
                     for (i, field) in elements.iter().enumerate() {
-                        emit_field_docs(&field.bit_range, format!("{}[{}]", ident.to_string(), i), &field.ty);
+                        emit_field_docs(&field.frame_range(parsed.num_bytes), format!("{}[{}]", ident.to_string(), i), &field.ty, field.access);
                     }
                 }
             }            
@@ -59,11 +59,33 @@ pub fn parse_sub_attributes(
     Ok(r)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The generated `Display` implementation.
+pub enum DisplayKind {
+    None,
+    Table,
+    Compact,
+}
+
+impl DisplayKind {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Some(DisplayKind::None),
+            "table" => Some(DisplayKind::Table),
+            "compact" => Some(DisplayKind::Compact),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 /// https://en.wikipedia.org/wiki/Bit_numbering
 pub enum BitNumbering {
     Lsb0,
     Msb0,
+    /// Bit `n` is bit `n % 8` of byte `n / 8`, counted from the least
+    /// significant bit, as in CAN DBC files.
+    Sawtooth,
 }
 
 impl BitNumbering {
@@ -72,6 +94,7 @@ impl BitNumbering {
         match s.as_str() {
             "lsb0" => Some(BitNumbering::Lsb0),
             "msb0" => Some(BitNumbering::Msb0),
+            "sawtooth" => Some(BitNumbering::Sawtooth),
             _ => None,
         }
     }
@@ -95,6 +118,69 @@ impl IntegerEndianness {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Access semantics of a field within a hardware register.
+pub enum FieldAccess {
+    ReadWrite,
+    ReadOnly,
+    WriteOnly,
+    WriteOneToClear,
+    ReadToClear,
+}
+
+impl FieldAccess {
+    pub const ALL: [FieldAccess; 5] = [
+        FieldAccess::ReadWrite,
+        FieldAccess::ReadOnly,
+        FieldAccess::WriteOnly,
+        FieldAccess::WriteOneToClear,
+        FieldAccess::ReadToClear,
+    ];
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "rw" => Some(FieldAccess::ReadWrite),
+            "ro" => Some(FieldAccess::ReadOnly),
+            "wo" => Some(FieldAccess::WriteOnly),
+            "w1c" => Some(FieldAccess::WriteOneToClear),
+            "rc" => Some(FieldAccess::ReadToClear),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldAccess::ReadWrite => "rw",
+            FieldAccess::ReadOnly => "ro",
+            FieldAccess::WriteOnly => "wo",
+            FieldAccess::WriteOneToClear => "w1c",
+            FieldAccess::ReadToClear => "rc",
+        }
+    }
+
+    /// Can a register with this access be read?
+    pub fn readable(&self) -> bool {
+        *self != FieldAccess::WriteOnly
+    }
+
+    /// Can a register with this access be written?
+    pub fn writable(&self) -> bool {
+        !matches!(self, FieldAccess::ReadOnly | FieldAccess::ReadToClear)
+    }
+
+    /// The variant's name in the runtime library.
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            FieldAccess::ReadWrite => "ReadWrite",
+            FieldAccess::ReadOnly => "ReadOnly",
+            FieldAccess::WriteOnly => "WriteOnly",
+            FieldAccess::WriteOneToClear => "WriteOneToClear",
+            FieldAccess::ReadToClear => "ReadToClear",
+        }
+    }
+}
+
 fn get_builtin_type_bit_width(p: &syn::PathSegment) -> syn::Result<Option<usize>> {
     match p.ident.to_string().as_str() {
         "bool" => Ok(Some(1)),
@@ -236,11 +322,12 @@ fn parse_field(
     mp: &FieldMidPositioning,
     bit_range: &Range<usize>,
     default_endianness: Option<IntegerEndianness>,
+    lsb_frame: bool,
 ) -> syn::Result<FieldKind> {
     match &field.ty {
         syn::Type::Path(_) => {
             return Ok(FieldKind::Regular {
-                field: parse_reg_field(field, &field.ty, bit_range, default_endianness)?,
+                field: parse_reg_field(field, &field.ty, bit_range, default_endianness, lsb_frame)?,
                 ident: field
                     .ident
                     .clone()
@@ -267,6 +354,7 @@ fn parse_field(
                     &type_array.elem,
                     &element_bit_range,
                     default_endianness,
+                    lsb_frame,
                 )?);
             }
             return Ok(FieldKind::Array {
@@ -289,6 +377,7 @@ fn parse_reg_field(
     ty: &syn::Type,
     bit_range: &Range<usize>,
     default_endianness: Option<IntegerEndianness>,
+    lsb_frame: bool,
 ) -> syn::Result<FieldRegular> {
     let mut wrappers = vec![];
 
@@ -321,6 +410,15 @@ fn parse_reg_field(
         our_int_ty || needs_int_wrap
     };
 
+    let access = field_attributes
+        .iter()
+        .filter_map(|a| match a {
+            &PackFieldAttribute::Access(access) => Some(access),
+            _ => None,
+        })
+        .next()
+        .unwrap_or(FieldAccess::ReadWrite);
+
     if is_enum_ty {
         wrappers.push(SerializationWrapper::PrimitiveEnumWrapper);
     }
@@ -354,7 +452,8 @@ fn parse_reg_field(
             default_endianness
         };
 
-        if bit_width <= 8 {
+        // the byte-reversed structure already orders the bytes
+        if bit_width <= 8 || lsb_frame {
             endiannes = Some(IntegerEndianness::Msb);
         }
 
@@ -379,6 +478,8 @@ fn parse_reg_field(
         bit_width: bit_width,
         bit_range: bit_range.clone(),
         bit_range_rust: bit_range.start..(bit_range.end + 1),
+        access,
+        lsb_frame,
     })
 }
 
@@ -474,6 +575,73 @@ impl BitsPositionParsed {
     }
 }
 
+/// The MSB0 range of a field in a sawtooth-numbered structure, and whether the
+/// range counts in the byte-reversed structure. Big-endian (Motorola) fields
+/// start at their most significant bit and continue from bit 7 of the next
+/// byte, so they're contiguous in MSB0. Little-endian (Intel) fields start at
+/// their least significant bit, so they're contiguous in the byte-reversed structure.
+fn sawtooth_range(
+    field: &syn::Field,
+    position: BitsPositionParsed,
+    bit_width: usize,
+    struct_size_bytes: usize,
+    default_endianness: Option<IntegerEndianness>,
+) -> syn::Result<(Range<usize>, bool)> {
+    let field_attributes = PackFieldAttribute::parse_all(&parse_sub_attributes(
+        &field.attrs,
+        "packed_field",
+        "packed_struct",
+    )?);
+
+    let endianness = field_attributes
+        .iter()
+        .filter_map(|a| match a {
+            &PackFieldAttribute::IntEndiannes(e) => Some(e),
+            _ => None,
+        })
+        .next()
+        .or(default_endianness);
+    let little_endian = matches!(endianness, Some(IntegerEndianness::Lsb));
+    let whole_bytes = field_attributes
+        .iter()
+        .any(|a| matches!(a, &PackFieldAttribute::BytePosition(_)));
+
+    if little_endian && matches!(field.ty, syn::Type::Array(_)) {
+        return Err(syn::Error::new(
+            field.span(),
+            "Arrays of little-endian fields aren't supported with sawtooth bit numbering.",
+        ));
+    }
+
+    let msb0 = |n: usize| (n / 8) * 8 + 7 - (n % 8);
+    let (start, end) = match position {
+        BitsPositionParsed::Range(a, b) if little_endian || whole_bytes => (a, b),
+        BitsPositionParsed::Range(a, b) => (msb0(a).min(msb0(b)), msb0(a).max(msb0(b))),
+        BitsPositionParsed::Start(s) if little_endian || whole_bytes => (s, s + bit_width - 1),
+        BitsPositionParsed::Start(s) => (msb0(s), msb0(s) + bit_width - 1),
+        _ => {
+            return Err(syn::Error::new(
+                field.span(),
+                "Sawtooth field positioning requires explicit field positions.",
+            ));
+        }
+    };
+
+    let num_bits = struct_size_bytes * 8;
+    if end >= num_bits {
+        return Err(syn::Error::new(
+            field.span(),
+            "The field's bits are outside of the structure.",
+        ));
+    }
+
+    if little_endian {
+        Ok(((num_bits - 1 - end)..(num_bits - 1 - start), true))
+    } else {
+        Ok((start..end, false))
+    }
+}
+
 pub fn parse_num(s: &str) -> usize {
     let s = s.trim();
 
@@ -539,6 +707,23 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
         })
         .next();
 
+    let display = attributes
+        .iter()
+        .filter_map(|a| match a {
+            PackStructAttribute::Display(d) => Some(*d),
+            _ => None,
+        })
+        .next()
+        .unwrap_or(DisplayKind::Table);
+
+    let atomic_fields = attributes
+        .iter()
+        .filter_map(|a| match a {
+            PackStructAttribute::AtomicFields(t) => Some(t.clone()),
+            _ => None,
+        })
+        .next();
+
     let first_field_is_auto_positioned = {
         if let Some(ref field) = fields.first() {
             let mp = get_field_mid_positioning(field)?;
@@ -552,7 +737,8 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
     {
         let mut prev_bit_range = None;
         for field in &fields {
-            let mp = get_field_mid_positioning(field)?;
+            let mut mp = get_field_mid_positioning(field)?;
+            let mut lsb_frame = false;
             let bits_position = match (bit_positioning, mp.bits_position) {
                 /*(Some(BitNumbering::Lsb0), BitsPositionParsed::Start(_)) => {
                     return Err(syn::Error::new(
@@ -585,6 +771,16 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
                 }*/
                 (None, p @ BitsPositionParsed::Next) => p,
                 (Some(BitNumbering::Msb0), p) => p,
+                (Some(BitNumbering::Sawtooth), p) => {
+                    let struct_size_bytes = struct_size_bytes.ok_or_else(|| syn::Error::new(
+                        field.span(),
+                        "Sawtooth field positioning currently requires explicit struct byte size.",
+                    ))?;
+                    let (range, little_endian) = sawtooth_range(field, p, mp.bit_width, struct_size_bytes, default_int_endianness)?;
+                    mp.bit_width = range.end - range.start + 1;
+                    lsb_frame = little_endian;
+                    BitsPositionParsed::Range(range.start, range.end)
+                }
                 (Some(BitNumbering::Lsb0), mut p) => {
                     if let Some(struct_size_bytes) = struct_size_bytes {
                         p.rev(struct_size_bytes);
@@ -603,7 +799,7 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
             };
             let bit_range = bits_position.get_bits_range(mp.bit_width, &prev_bit_range);
 
-            fields_parsed.push(parse_field(field, &mp, &bit_range, default_int_endianness)?);
+            fields_parsed.push(parse_field(field, &mp, &bit_range, default_int_endianness, lsb_frame)?);
 
             if let Some(byte_width) = bits_position.is_rev() {
                 let mut temp = bits_position.clone();
@@ -643,8 +839,8 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
     {
         let mut bits = vec![None; num_bytes * 8];
         for field in &fields_parsed {
-            let mut find_overlaps = |name: String, range: &Range<usize>| {
-                for i in range.start..(range.end + 1) {
+            let mut find_overlaps = |name: String, field: &FieldRegular| {
+                for i in field.frame_bits(num_bytes) {
                     if let Some(&Some(ref n)) = bits.get(i) {
                         return Err(syn::Error::new(
                             name.span(),
@@ -667,7 +863,7 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
                     ref field,
                     ref ident,
                 } => {
-                    find_overlaps(ident.to_string(), &field.bit_range)?;
+                    find_overlaps(ident.to_string(), field)?;
                 }
                 &FieldKind::Array {
                     ref ident,
@@ -675,7 +871,7 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
                     ..
                 } => {
                     for (i, field) in elements.iter().enumerate() {
-                        find_overlaps(format!("{}[{}]", ident.to_string(), i), &field.bit_range)?;
+                        find_overlaps(format!("{}[{}]", ident.to_string(), i), field)?;
                     }
                 }
             }
@@ -687,5 +883,8 @@ pub fn parse_struct(ast: &syn::DeriveInput) -> syn::Result<PackStruct> {
         fields: fields_parsed,
         num_bytes,
         num_bits,
+        display,
+        atomic_fields,
+        bit_numbering: bit_positioning.unwrap_or(BitNumbering::Msb0),
     })
 }
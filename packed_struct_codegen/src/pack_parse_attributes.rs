@@ -5,7 +5,9 @@ pub enum PackStructAttributeKind {
     SizeBytes,
     //SizeBits,
     DefaultIntEndianness,
-    BitNumbering
+    BitNumbering,
+    Display,
+    AtomicFields
 }
 
 impl PackStructAttributeKind {
@@ -16,7 +18,9 @@ impl PackStructAttributeKind {
             SizeBytes => "size_bytes",
             //SizeBits => "size_bits",
             DefaultIntEndianness => "endian",
-            BitNumbering => "bit_numbering"
+            BitNumbering => "bit_numbering",
+            Display => "display",
+            AtomicFields => "atomic_fields"
         }
     }
 }
@@ -25,7 +29,9 @@ pub enum PackStructAttribute {
     SizeBytes(usize),
     //SizeBits(usize),
     DefaultIntEndianness(IntegerEndianness),
-    BitNumbering(BitNumbering)
+    BitNumbering(BitNumbering),
+    Display(DisplayKind),
+    AtomicFields(syn::Ident)
 }
 
 impl PackStructAttribute {
@@ -44,6 +50,16 @@ impl PackStructAttribute {
             return Ok(PackStructAttribute::SizeBytes(b));
         }
 
+        if name == PackStructAttributeKind::Display.get_attr_name() {
+            let d = DisplayKind::from_str(val).expect("Invalid display attribute value, expected \"none\", \"table\" or \"compact\"");
+            return Ok(PackStructAttribute::Display(d));
+        }
+
+        if name == PackStructAttributeKind::AtomicFields.get_attr_name() {
+            let t = syn::parse_str(val).expect("Invalid atomic_fields attribute value, expected the name of the trait");
+            return Ok(PackStructAttribute::AtomicFields(t));
+        }
+
         /*
         if name == PackStructAttributeKind::SizeBits.get_attr_name() {
             let b = parse_num(val);
@@ -74,7 +90,8 @@ pub enum PackFieldAttributeKind {
     ElementSizeBits,
     SizeBytes,
     SizeBits,
-    Ty
+    Ty,
+    Access
 }
 
 impl PackFieldAttributeKind {
@@ -89,7 +106,8 @@ impl PackFieldAttributeKind {
             SizeBits => "size_bits",
             ElementSizeBytes => "element_size_bytes",
             ElementSizeBits => "element_size_bits",
-            Ty => "ty"
+            Ty => "ty",
+            Access => "access"
         }
     }
 }
@@ -100,7 +118,8 @@ pub enum PackFieldAttribute {
     BytePosition(BitsPositionParsed),
     SizeBits(usize),
     ElementSizeBits(usize),
-    Ty(TyKind)
+    Ty(TyKind),
+    Access(FieldAccess)
 }
 
 pub enum TyKind {
@@ -143,6 +162,11 @@ impl PackFieldAttribute {
             return Ok(PackFieldAttribute::ElementSizeBits(b));
         }
 
+        if name == PackFieldAttributeKind::Access.get_attr_name() {
+            let a = FieldAccess::from_str(val).expect("Invalid field access value, expected \"rw\", \"ro\", \"wo\", \"w1c\" or \"rc\"");
+            return Ok(PackFieldAttribute::Access(a));
+        }
+
         if name == PackFieldAttributeKind::Ty.get_attr_name() {
             match val {
                 "enum" => { return Ok(PackFieldAttribute::Ty(TyKind::Enum)); },
@@ -69,6 +69,16 @@ pub fn derive(
         .collect();
     let all_variants_len = all_variants.len();
 
+    let variant_infos: Vec<_> = v
+        .iter()
+        .map(|x| {
+            let n = x.variant.ident.to_string();
+            let value = if x.negative { -(x.discriminant as i128) } else { x.discriminant as i128 };
+            let value = proc_macro2::Literal::i128_suffixed(value);
+            quote! { ::packed_struct::layout::EnumVariantInfo { name: #n, value: #value } }
+        })
+        .collect();
+
     if prim_type.is_none() {
         let min_ty: Vec<String> = v
             .iter()
@@ -179,6 +189,8 @@ pub fn derive(
         impl ::packed_struct::PrimitiveEnum for #name {
             type Primitive = #prim_type;
 
+            const VARIANTS: &'static [::packed_struct::layout::EnumVariantInfo] = &[ #(#variant_infos),* ];
+
             #[inline]
             fn from_primitive(val: #prim_type) -> Option<Self> {
                 match val {
@@ -0,0 +1,324 @@
+extern crate quote;
+extern crate syn;
+
+use crate::common::result_type;
+use crate::pack_codegen_docs::access_variant;
+use crate::pack_parse::FieldAccess;
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+
+/// Register names that would collide with the generated `read_all` and `write_all`.
+const RESERVED_REGISTER_NAMES: &[&str] = &["all"];
+
+struct Register {
+    ident: syn::Ident,
+    ty: syn::Type,
+    address: u32,
+    access: FieldAccess,
+    span: Span,
+}
+
+/// The `name=value` pairs of the `#[register]` attributes. Unlike the packing
+/// attributes, the values can also be integer literals.
+fn parse_register_attributes(attributes: &[syn::Attribute]) -> syn::Result<Vec<(syn::Ident, syn::Lit)>> {
+    let mut r = vec![];
+
+    for attr in attributes {
+        if attr.path.is_ident("packed_field") {
+            return Err(syn::Error::new(
+                attr.path.span(),
+                "This attribute is not supported here, did you mean \"register\"?",
+            ));
+        }
+        if !attr.path.is_ident("register") {
+            continue;
+        }
+
+        if let syn::Meta::List(list) = attr.parse_meta()? {
+            for nested in &list.nested {
+                if let syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) = nested {
+                    if let Some(key) = nv.path.get_ident() {
+                        r.push((key.clone(), nv.lit.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(r)
+}
+
+/// Register addresses are 32 bit, like the bus addresses of `RegisterBus`.
+fn parse_address(lit: &syn::Lit) -> syn::Result<u32> {
+    let out_of_range = || syn::Error::new(lit.span(), "The register's address doesn't fit in 32 bits.");
+    match lit {
+        syn::Lit::Int(int) => int.base10_parse().map_err(|_| out_of_range()),
+        syn::Lit::Str(s) => {
+            let value = s.value();
+            let value = value.trim();
+            let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => value.parse()
+            };
+            match parsed {
+                Ok(address) => u32::try_from(address).map_err(|_| out_of_range()),
+                Err(_) => Err(syn::Error::new(lit.span(), format!("Invalid register address {:?}.", value)))
+            }
+        },
+        _ => Err(syn::Error::new(lit.span(), "Expected an integer register address."))
+    }
+}
+
+fn parse_registers(ast: &syn::DeriveInput) -> syn::Result<Vec<Register>> {
+    let data_struct = match &ast.data {
+        syn::Data::Struct(data) => data,
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(RegisterMap)] can only be used with braced structs",
+            ))
+        }
+    };
+
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            ast.span(),
+            "Register maps with generic fields currently aren't supported.",
+        ));
+    }
+
+    let mut registers = vec![];
+    for field in &data_struct.fields {
+        let ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| syn::Error::new(field.span(), "Missing ident!"))?;
+
+        if RESERVED_REGISTER_NAMES.contains(&ident.to_string().as_str()) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("A register can't be named {:?}, its functions would collide with read_all and write_all.", ident.to_string()),
+            ));
+        }
+
+        let mut address = None;
+        let mut access = FieldAccess::ReadWrite;
+        for (name, val) in parse_register_attributes(&field.attrs)? {
+            match name.to_string().as_str() {
+                "address" => address = Some(parse_address(&val)?),
+                "access" => {
+                    let parsed = match &val {
+                        syn::Lit::Str(s) => FieldAccess::from_str(&s.value()),
+                        _ => None,
+                    };
+                    let parsed = parsed.filter(|a| {
+                        matches!(a, FieldAccess::ReadOnly | FieldAccess::WriteOnly | FieldAccess::ReadWrite)
+                    });
+                    access = parsed.ok_or_else(|| {
+                        syn::Error::new(
+                            val.span(),
+                            "Invalid register access, expected \"ro\", \"wo\" or \"rw\".",
+                        )
+                    })?;
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        format!("Unknown register attribute {:?}.", name),
+                    ));
+                }
+            }
+        }
+
+        let address = address.ok_or_else(|| {
+            syn::Error::new(
+                field.span(),
+                "Please specify the register's address: #[register(address=0xA0)]",
+            )
+        })?;
+
+        if let Some(other) = registers.iter().find(|r: &&Register| r.address == address) {
+            return Err(syn::Error::new(
+                field.span(),
+                format!("Register {} has the same address 0x{:X} as register {}.", ident, address, other.ident),
+            ));
+        }
+
+        registers.push(Register {
+            ident,
+            ty: field.ty.clone(),
+            address,
+            access,
+            span: field.span(),
+        });
+    }
+
+    Ok(registers)
+}
+
+pub fn derive(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &ast.ident;
+    let registers = parse_registers(ast)?;
+    let result_ty = result_type();
+
+    let mut methods = vec![];
+    let mut infos = vec![];
+
+    for r in &registers {
+        let ident = &r.ident;
+        let ty = &r.ty;
+        let address = r.address;
+        let name_str = ident.to_string();
+
+        let address_const = syn::Ident::new(
+            &format!("{}_ADDRESS", name_str.to_uppercase()),
+            Span::call_site(),
+        );
+        let read_fn = syn::Ident::new(&format!("read_{}", name_str), Span::call_site());
+        let write_fn = syn::Ident::new(&format!("write_{}", name_str), Span::call_site());
+        let modify_fn = syn::Ident::new(&format!("modify_{}", name_str), Span::call_site());
+
+        let address_doc = format!("Address of the `{}` register.", name_str);
+        methods.push(quote! {
+            #[doc = #address_doc]
+            pub const #address_const: u32 = #address;
+        });
+
+        if r.access.readable() {
+            let doc = format!("Read the `{}` register, address 0x{:X}.", name_str, address);
+            methods.push(quote! {
+                #[doc = #doc]
+                pub fn #read_fn<B: ::packed_struct::register_map::RegisterBus>(bus: &mut B) -> #result_ty <#ty, ::packed_struct::register_map::RegisterError<B::Error>> {
+                    ::packed_struct::register_map::RegisterBus::read_register(bus, #address)
+                }
+            });
+        }
+
+        if r.access.writable() {
+            let doc = format!("Write the `{}` register, address 0x{:X}.", name_str, address);
+            methods.push(quote! {
+                #[doc = #doc]
+                pub fn #write_fn<B: ::packed_struct::register_map::RegisterBus>(bus: &mut B, value: &#ty) -> #result_ty <(), ::packed_struct::register_map::RegisterError<B::Error>> {
+                    ::packed_struct::register_map::RegisterBus::write_register(bus, #address, value)
+                }
+            });
+        }
+
+        if r.access.readable() && r.access.writable() {
+            let doc = format!("Read, modify and write back the `{}` register, address 0x{:X}.", name_str, address);
+            methods.push(quote! {
+                #[doc = #doc]
+                pub fn #modify_fn<B, F>(bus: &mut B, f: F) -> #result_ty <#ty, ::packed_struct::register_map::RegisterError<B::Error>>
+                    where B: ::packed_struct::register_map::RegisterBus, F: FnOnce(&mut #ty)
+                {
+                    ::packed_struct::register_map::RegisterBus::modify_register(bus, #address, f)
+                }
+            });
+        }
+
+        let access = access_variant(r.access);
+
+        infos.push(quote! {
+            ::packed_struct::register_map::RegisterInfo {
+                name: #name_str,
+                address: #address,
+                size_bytes: ::packed_struct::register_map::register_size_bytes::<#ty>(),
+                access: #access
+            }
+        });
+    }
+
+    if registers.iter().all(|r| r.access.readable()) {
+        let reads: Vec<_> = registers.iter().map(|r| {
+            let ident = &r.ident;
+            let address = r.address;
+            quote! { #ident: ::packed_struct::register_map::RegisterBus::read_register(bus, #address)? }
+        }).collect();
+
+        methods.push(quote! {
+            /// Read all the registers.
+            pub fn read_all<B: ::packed_struct::register_map::RegisterBus>(bus: &mut B) -> #result_ty <Self, ::packed_struct::register_map::RegisterError<B::Error>> {
+                Ok(#name {
+                    #(#reads),*
+                })
+            }
+        });
+    }
+
+    let writes: Vec<_> = registers.iter().filter(|r| r.access.writable()).map(|r| {
+        let ident = &r.ident;
+        let address = r.address;
+        quote! { ::packed_struct::register_map::RegisterBus::write_register(bus, #address, &self.#ident)?; }
+    }).collect();
+
+    methods.push(quote! {
+        /// Write all the writable registers, in declaration order.
+        pub fn write_all<B: ::packed_struct::register_map::RegisterBus>(&self, bus: &mut B) -> #result_ty <(), ::packed_struct::register_map::RegisterError<B::Error>> {
+            #(#writes)*
+            Ok(())
+        }
+    });
+
+    // The sizes of the registers are only known to the compiler, so overlapping
+    // registers are caught by an assertion at the later register's field.
+    let mut by_address: Vec<_> = registers.iter().collect();
+    by_address.sort_by_key(|r| r.address);
+    let overlap_checks: Vec<_> = by_address.windows(2).map(|pair| {
+        let (lower, upper) = (pair[0], pair[1]);
+        let lower_ty = &lower.ty;
+        let lower_address = lower.address as usize;
+        let upper_address = upper.address as usize;
+        let msg = format!("Register {} at 0x{:X} overlaps register {} at 0x{:X}.", upper.ident, upper.address, lower.ident, lower.address);
+        quote_spanned! { upper.span =>
+            const _: () = assert!(#lower_address + ::packed_struct::register_map::register_size_bytes::<#lower_ty>() <= #upper_address, #msg);
+        }
+    }).collect();
+
+    Ok(quote! {
+        #(#overlap_checks)*
+
+        #[allow(dead_code)]
+        impl #name {
+            #(#methods)*
+        }
+
+        impl ::packed_struct::register_map::RegisterMap for #name {
+            const REGISTERS: &'static [::packed_struct::register_map::RegisterInfo] = &[#(#infos),*];
+        }
+    })
+}
+
+
+#[test]
+fn test_parse_address() {
+    let parse = |s: &str| parse_address(&syn::parse_str(s).unwrap()).ok();
+
+    assert_eq!(Some(0xA0), parse("0xA0"));
+    assert_eq!(Some(16), parse("16"));
+    assert_eq!(Some(0xA0), parse("\"0xA0\""));
+    assert_eq!(Some(u32::MAX), parse("0xFFFF_FFFF"));
+
+    assert_eq!(None, parse("0x1_0000_0000"));
+    assert_eq!(None, parse("\"0x100000000\""));
+    assert_eq!(None, parse("\"ten\""));
+}
+
+#[test]
+fn test_parse_registers_errors() {
+    let parse = |s: &str| parse_registers(&syn::parse_str(s).unwrap()).err().map(|e| e.to_string());
+
+    assert!(parse("struct R { #[register(address=0x10)] a: A, #[register(address=0x12)] b: B }").is_none());
+
+    assert_eq!(Some("Register b has the same address 0x10 as register a.".to_string()),
+        parse("struct R { #[register(address=0x10)] a: A, #[register(address=\"0x10\")] b: B }"));
+    assert_eq!(Some("A register can't be named \"all\", its functions would collide with read_all and write_all.".to_string()),
+        parse("struct R { #[register(address=0x10)] all: A }"));
+    assert_eq!(Some("Invalid register access, expected \"ro\", \"wo\" or \"rw\".".to_string()),
+        parse("struct R { #[register(address=0x10, access=\"w1c\")] a: A }"));
+    assert_eq!(Some("Invalid register address \"ten\".".to_string()),
+        parse("struct R { #[register(address=\"ten\")] a: A }"));
+    assert_eq!(Some("Please specify the register's address: #[register(address=0xA0)]".to_string()),
+        parse("struct R { #[register(access=\"ro\")] a: A }"));
+    assert_eq!(Some("Unknown register attribute Ident(size).".to_string()),
+        parse("struct R { #[register(address=0x10, size=4)] a: A }"));
+}
@@ -26,6 +26,30 @@ pub struct ControlRegister {
     pub sensor_value: i16
 }
 
+/// Status register, address 0xA4.
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct StatusRegister {
+    /// A new reading is available
+    #[packed_field(bits="0")]
+    pub data_ready: bool,
+    /// The reading exceeded the input range
+    #[packed_field(bits="1")]
+    pub overflow: bool,
+    /// Number of readings since the last read of this register
+    #[packed_field(bits="4:7")]
+    pub sample_count: Integer<u8, packed_bits::Bits4>
+}
+
+/// Register map of the fictive sensor
+#[derive(RegisterMap, Debug, Copy, Clone, PartialEq)]
+pub struct SensorRegisters {
+    #[register(address=0xA0, access="rw")]
+    pub control: ControlRegister,
+    #[register(address=0xA4, access="ro")]
+    pub status: StatusRegister
+}
+
 #[derive(PrimitiveEnum, Debug, Copy, Clone, PartialEq)]
 pub enum PowerMode {
     /// The sensor is turned off
@@ -53,4 +77,4 @@ fn test_display() {
     let _unpacked = ControlRegister::unpack(&[0x8B, 0xE7, 0x21, 0xFA]).unwrap();
 
     println!("{}", reg);
-}
\ No newline at end of file
+}
@@ -0,0 +1,10 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+    packed_struct_build::svd::generate("tests/data/device.svd", out.join("device.rs")).unwrap();
+    println!("cargo:rerun-if-changed=tests/data/device.svd");
+    packed_struct_build::dbc::generate("tests/data/vehicle.dbc", out.join("vehicle.rs")).unwrap();
+    println!("cargo:rerun-if-changed=tests/data/vehicle.dbc");
+}
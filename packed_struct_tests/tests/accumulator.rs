@@ -0,0 +1,114 @@
+use packed_struct::prelude::*;
+use packed_struct::PackedAccumulator;
+
+#[derive(PackedStruct, Debug, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Telemetry {
+    #[packed_field(bits="0:3")]
+    channel: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="7")]
+    valid: bool,
+    #[packed_field(bytes="1:2")]
+    reading: u16,
+    #[packed_field(bytes="3")]
+    checksum: u8
+}
+
+#[test]
+fn test_accumulator_frames() {
+    let mut acc = PackedAccumulator::<Telemetry>::new();
+    assert_eq!(4, acc.capacity());
+
+    let stream = [0x31, 0x12, 0x34, 0xAA, 0x21, 0x00, 0x10, 0x55];
+    let mut frames = vec![];
+    for b in &stream {
+        if let Some(t) = acc.push(*b).unwrap() {
+            frames.push(t);
+        }
+    }
+
+    assert_eq!(0, acc.received());
+    assert_eq!(vec![
+        Telemetry { channel: 3.into(), valid: true, reading: 0x1234, checksum: 0xAA },
+        Telemetry { channel: 2.into(), valid: true, reading: 0x0010, checksum: 0x55 }
+    ], frames);
+}
+
+#[test]
+fn test_accumulator_reset() {
+    let mut acc = PackedAccumulator::<Telemetry>::new();
+    assert_eq!(None, acc.push(0xFF).unwrap());
+    assert_eq!(None, acc.push(0xFF).unwrap());
+    assert_eq!(&[0xFF, 0xFF], acc.received_bytes());
+
+    acc.reset();
+    assert_eq!(0, acc.received());
+
+    for b in &[0x01, 0x00, 0x02] {
+        assert_eq!(None, acc.push(*b).unwrap());
+    }
+    let t = acc.push(0x03).unwrap().unwrap();
+    assert_eq!(Telemetry { channel: 0.into(), valid: true, reading: 0x0002, checksum: 0x03 }, t);
+}
+
+#[test]
+fn test_accumulator_timeout() {
+    let mut acc = PackedAccumulator::<Telemetry>::new().with_timeout(3);
+
+    assert!(!acc.tick());
+    acc.push(0x31).unwrap();
+    assert!(!acc.tick());
+    assert!(!acc.tick());
+    acc.push(0x12).unwrap();
+    assert!(!acc.tick());
+    assert!(!acc.tick());
+    assert!(acc.tick());
+    assert_eq!(0, acc.received());
+
+    for b in &[0x31, 0x12, 0x34] {
+        assert_eq!(None, acc.push(*b).unwrap());
+    }
+    assert!(acc.push(0xAA).unwrap().is_some());
+}
+
+#[test]
+fn test_accumulator_frame_length() {
+    // the second nibble of the first byte holds the frame length
+    fn frame_length(received: &[u8]) -> Option<usize> {
+        received.first().map(|b| (b & 0x0F) as usize)
+    }
+
+    let mut acc = PackedAccumulator::<Telemetry>::new().with_frame_length(frame_length);
+
+    assert_eq!(None, acc.push(0x13).unwrap());
+    assert_eq!(None, acc.push(0x12).unwrap());
+    let t = acc.push(0x34).unwrap().unwrap();
+    assert_eq!(Telemetry { channel: 1.into(), valid: true, reading: 0x1234, checksum: 0 }, t);
+
+    assert_eq!(Err(PackingError::BufferSizeMismatch { expected: 4, actual: 9 }), acc.push(0x19));
+    assert_eq!(0, acc.received());
+}
+
+#[test]
+fn test_accumulator_invalid_value() {
+    #[derive(PrimitiveEnum_u8, Clone, Copy, Debug, PartialEq)]
+    pub enum Command {
+        Start = 1,
+        Stop = 2
+    }
+
+    #[derive(PackedStruct, Debug, PartialEq)]
+    pub struct CommandFrame {
+        #[packed_field(size_bytes="1", ty="enum")]
+        command: Command,
+        arg: u8
+    }
+
+    let mut acc = PackedAccumulator::<CommandFrame>::new();
+    acc.push(0x07).unwrap();
+    assert_eq!(Err(PackingError::InvalidValue), acc.push(0x00));
+    assert_eq!(0, acc.received());
+
+    acc.push(0x02).unwrap();
+    assert_eq!(Some(CommandFrame { command: Command::Stop, arg: 5 }), acc.push(0x05).unwrap());
+}
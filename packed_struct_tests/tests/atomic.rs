@@ -0,0 +1,171 @@
+use packed_struct::prelude::*;
+use packed_struct::atomic::AtomicPacked;
+
+use std::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", atomic_fields="FlagsFields")]
+pub struct Flags {
+    #[packed_field(bits="0")]
+    ready: bool,
+    #[packed_field(bits="1")]
+    error: bool,
+    #[packed_field(bits="4:7")]
+    count: Integer<u8, packed_bits::Bits4>
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", endian="msb", atomic_fields="CountersFields")]
+pub struct Counters {
+    #[packed_field(bytes="0")]
+    id: u8,
+    #[packed_field(bytes="1:2")]
+    events: u16
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(endian="msb")]
+pub struct Wide {
+    timestamp: u32,
+    value: u16,
+    channel: u8
+}
+
+static FLAGS: AtomicPacked<Flags> = AtomicPacked::from_atomic(AtomicU8::new(0));
+
+#[test]
+fn test_atomic_load_store() {
+    let flags = Flags { ready: true, error: false, count: 5.into() };
+    let atomic = AtomicPacked::new(&flags).unwrap();
+    assert_eq!(0x85, atomic.as_atomic().load(Ordering::SeqCst));
+    assert_eq!(flags, atomic.load(Ordering::SeqCst).unwrap());
+
+    let flags = Flags { error: true, ..flags };
+    assert_eq!(Flags { error: false, ..flags }, atomic.swap(&flags, Ordering::SeqCst).unwrap());
+    assert_eq!([0xC5], atomic.load_bytes(Ordering::SeqCst));
+
+    atomic.store(&Flags::default(), Ordering::SeqCst).unwrap();
+    assert_eq!(0, atomic.as_atomic().load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_atomic_storage_widths() {
+    let counters = Counters { id: 0x12, events: 0x3456 };
+    let atomic = AtomicPacked::new(&counters).unwrap();
+    let storage: &AtomicU32 = atomic.as_atomic();
+    assert_eq!(0x00123456, storage.load(Ordering::SeqCst));
+    assert_eq!(counters, atomic.load(Ordering::SeqCst).unwrap());
+
+    let wide = Wide { timestamp: 0x01020304, value: 0x0506, channel: 0x07 };
+    let atomic = AtomicPacked::new(&wide).unwrap();
+    assert_eq!(0x0001020304050607, atomic.as_atomic().load(Ordering::SeqCst));
+    assert_eq!(wide, atomic.load(Ordering::SeqCst).unwrap());
+
+    assert_eq!(Wide::default(), AtomicPacked::<Wide>::default().load(Ordering::SeqCst).unwrap());
+}
+
+#[test]
+fn test_atomic_static_field_setters() {
+    FLAGS.set_ready(true, Ordering::SeqCst).unwrap();
+    FLAGS.set_count(9.into(), Ordering::SeqCst).unwrap();
+    let previous = FLAGS.update_ready(Ordering::Release, Ordering::Relaxed, |ready| !ready).unwrap();
+    assert!(previous);
+
+    assert_eq!(Flags { ready: false, error: false, count: 9.into() }, FLAGS.load(Ordering::Acquire).unwrap());
+    assert_eq!("AtomicPacked(Flags { ready: false, error: false, count: 9 })", format!("{:?}", FLAGS));
+}
+
+#[test]
+fn test_atomic_fetch_update_threads() {
+    let atomic = Arc::new(AtomicPacked::<Counters>::default());
+
+    let threads: Vec<_> = (0..4).map(|_| {
+        let atomic = atomic.clone();
+        thread::spawn(move || {
+            for _ in 0..1000 {
+                atomic.fetch_update(Ordering::AcqRel, Ordering::Acquire, |c| c.events += 1).unwrap();
+            }
+        })
+    }).collect();
+
+    atomic.set_id(0x42, Ordering::SeqCst).unwrap();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(Counters { id: 0x42, events: 4000 }, atomic.load(Ordering::SeqCst).unwrap());
+}
+
+#[test]
+fn test_atomic_fields_threads() {
+    let atomic = Arc::new(AtomicPacked::<Counters>::default());
+
+    let threads: Vec<_> = (0..4).map(|i| {
+        let atomic = atomic.clone();
+        thread::spawn(move || {
+            for _ in 0..1000 {
+                if i % 2 == 0 {
+                    atomic.update_events(Ordering::AcqRel, Ordering::Acquire, |e| e + 1).unwrap();
+                } else {
+                    atomic.update_id(Ordering::AcqRel, Ordering::Acquire, |id| id.wrapping_add(1)).unwrap();
+                }
+            }
+        })
+    }).collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(Counters { id: 2000u16 as u8, events: 2000 }, atomic.load(Ordering::SeqCst).unwrap());
+}
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Idle = 0,
+    Run = 1
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="2", atomic_fields="SignalsFields")]
+pub struct Signals {
+    #[packed_field(bits="0:1", ty="enum")]
+    mode: Mode,
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", atomic_fields="TagsFields")]
+pub struct Tags {
+    #[packed_field(bits="0:3")]
+    _reserved: ReservedZero<packed_bits::Bits4>,
+    #[packed_field(bits="4:7")]
+    kind: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bytes="1..=2")]
+    tags: [u8; 2]
+}
+
+#[test]
+fn test_atomic_fields_only_exchange_their_bits() {
+    // the mode bits don't unpack, setting the speed leaves them untouched
+    let atomic = AtomicPacked::<Signals>::from_bytes(&[0x03, 0x00]);
+    atomic.set_speed(0xABC.into(), Ordering::SeqCst).unwrap();
+    assert_eq!([0xC3, 0xAB], atomic.load_bytes(Ordering::SeqCst));
+    assert_eq!(Err(PackingError::InvalidValue), atomic.load(Ordering::SeqCst));
+
+    atomic.set_mode(Mode::Run, Ordering::SeqCst).unwrap();
+    assert_eq!(Signals { mode: Mode::Run, speed: 0xABC.into() }, atomic.load(Ordering::SeqCst).unwrap());
+    assert_eq!(0xABC, *atomic.update_speed(Ordering::SeqCst, Ordering::SeqCst, |s| (*s + 1).into()).unwrap());
+    assert_eq!([0xD1, 0xAB], atomic.load_bytes(Ordering::SeqCst));
+}
+
+#[test]
+fn test_atomic_fields_arrays() {
+    let atomic = AtomicPacked::<Tags>::from_bytes(&[0xF0, 0, 0]);
+    atomic.set_tags([1, 2], Ordering::SeqCst).unwrap();
+    atomic.set_kind(5.into(), Ordering::SeqCst).unwrap();
+    assert_eq!([1, 2], atomic.update_tags(Ordering::SeqCst, Ordering::SeqCst, |[a, b]| [b, a]).unwrap());
+    assert_eq!([0xF5, 2, 1], atomic.load_bytes(Ordering::SeqCst));
+}
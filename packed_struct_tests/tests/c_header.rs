@@ -0,0 +1,128 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::{BitNumbering, PackedStructLayout};
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Idle = 0,
+    Active = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", size_bytes="3", endian="lsb")]
+pub struct Command {
+    /// Requested mode
+    #[packed_field(bits="0:1", ty="enum")]
+    mode: Mode,
+    #[packed_field(bits="2:7")]
+    _reserved: ReservedZero<packed_bits::Bits6>,
+    #[packed_field(bytes="1:2")]
+    offset: i16
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="5")]
+pub struct Signals {
+    #[packed_field(bits="0")]
+    valid: bool,
+    /// Intel, crosses bytes 0 and 1
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bytes="2:4")]
+    command: Command
+}
+
+#[test]
+fn test_c_header() {
+    let expected = r#"/* The layout of the Command packed structure. Generated, don't edit. */
+#ifndef COMMAND_H
+#define COMMAND_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define COMMAND_SIZE 3
+
+typedef enum {
+    COMMAND_MODE_IDLE = 0,
+    COMMAND_MODE_ACTIVE = 2
+} command_mode_t;
+
+/* Requested mode */
+#define COMMAND_MODE_BYTE_OFFSET 0
+#define COMMAND_MODE_BYTES 1
+#define COMMAND_MODE_WIDTH 2
+#define COMMAND_MODE_SHIFT 6
+#define COMMAND_MODE_MASK 0xC0u
+
+static inline command_mode_t command_get_mode(const uint8_t buf[COMMAND_SIZE]) {
+    uint8_t w = buf[0];
+    uint8_t raw = (uint8_t)((w & COMMAND_MODE_MASK) >> COMMAND_MODE_SHIFT);
+    return (command_mode_t)raw;
+}
+
+static inline void command_set_mode(uint8_t buf[COMMAND_SIZE], command_mode_t value) {
+    uint8_t w = buf[0];
+    w = (uint8_t)((w & (uint8_t)~COMMAND_MODE_MASK) | (((uint8_t)value << COMMAND_MODE_SHIFT) & COMMAND_MODE_MASK));
+    buf[0] = (uint8_t)w;
+}
+
+#define COMMAND_RESERVED_BYTE_OFFSET 0
+#define COMMAND_RESERVED_BYTES 1
+#define COMMAND_RESERVED_WIDTH 6
+#define COMMAND_RESERVED_SHIFT 0
+#define COMMAND_RESERVED_MASK 0x3Fu
+
+#define COMMAND_OFFSET_BYTE_OFFSET 1
+#define COMMAND_OFFSET_BYTES 2
+#define COMMAND_OFFSET_WIDTH 16
+/* The mask and shift apply to the little-endian bytes. */
+#define COMMAND_OFFSET_SHIFT 0
+#define COMMAND_OFFSET_MASK 0xFFFFu
+
+static inline int16_t command_get_offset(const uint8_t buf[COMMAND_SIZE]) {
+    uint16_t w = (uint16_t)((uint16_t)buf[1] | (uint16_t)buf[2] << 8);
+    uint16_t raw = (uint16_t)((w & COMMAND_OFFSET_MASK) >> COMMAND_OFFSET_SHIFT);
+    return (int16_t)((int64_t)(raw ^ 0x8000ull) - (int64_t)0x8000ull);
+}
+
+static inline void command_set_offset(uint8_t buf[COMMAND_SIZE], int16_t value) {
+    uint16_t w = (uint16_t)((uint16_t)buf[1] | (uint16_t)buf[2] << 8);
+    w = (uint16_t)((w & (uint16_t)~COMMAND_OFFSET_MASK) | (((uint16_t)value << COMMAND_OFFSET_SHIFT) & COMMAND_OFFSET_MASK));
+    buf[1] = (uint8_t)w;
+    buf[2] = (uint8_t)(w >> 8);
+}
+
+#endif /* COMMAND_H */
+"#;
+    assert_eq!(expected, Command::c_header());
+}
+
+#[test]
+fn test_c_header_sawtooth_and_nested() {
+    let header = Signals::c_header();
+
+    // the Intel signal is a run of bits of the little-endian bytes
+    assert!(header.contains("/* Intel, crosses bytes 0 and 1 */\n#define SIGNALS_SPEED_BYTE_OFFSET 0\n#define SIGNALS_SPEED_BYTES 2\n"));
+    assert!(header.contains("#define SIGNALS_SPEED_SHIFT 4\n#define SIGNALS_SPEED_MASK 0xFFF0u\n"));
+    assert!(header.contains("uint16_t w = (uint16_t)((uint16_t)buf[0] | (uint16_t)buf[1] << 8);"));
+
+    // nested structures are copied as their packed bytes
+    assert!(header.contains("/* The packed bytes of Command. */"));
+    assert!(header.contains("static inline void signals_get_command(const uint8_t buf[SIGNALS_SIZE], uint8_t value[3]) {"));
+    assert!(header.ends_with("#endif /* SIGNALS_H */\n"));
+}
+
+#[test]
+fn test_bit_map() {
+    // value bits 0..3 in byte 0, from bit 4 up, and 4..11 in byte 1
+    let speed: Vec<_> = Signals::field("speed").unwrap().bit_map(BitNumbering::Sawtooth).collect();
+    assert_eq!((0, 3), speed[0]);
+    assert_eq!((3, 0), speed[3]);
+    assert_eq!((4, 15), speed[4]);
+    assert_eq!((11, 8), speed[11]);
+
+    // little-endian bytes, least significant one first
+    let offset: Vec<_> = Command::field("offset").unwrap().bit_map(BitNumbering::Msb0).collect();
+    assert_eq!((7, 8), offset[0]);
+    assert_eq!((15, 16), offset[8]);
+}
@@ -0,0 +1,194 @@
+use packed_struct::prelude::*;
+use packed_struct::csv::{CsvWriteError, PackedCsvReader, PackedCsvWriter};
+use packed_struct::visit::{PackedStructVisit, VisitContext, VisitField, Visitor};
+use packed_struct::PackingResult;
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq, Default)]
+pub enum Mode {
+    #[default]
+    Idle = 0,
+    Active = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Flags {
+    #[packed_field(bits="6")]
+    ready: bool,
+    #[packed_field(bits="7")]
+    error: bool
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Record {
+    #[packed_field(bits="0:3")]
+    channel: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="4:5", ty="enum")]
+    mode: Mode,
+    #[packed_field(bits="6:7")]
+    flags: Flags,
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3:4", endian="msb")]
+    offset: i16,
+    #[packed_field(bytes="5:6")]
+    data: [u8; 2]
+}
+
+fn records() -> Vec<Record> {
+    vec![
+        Record { channel: 3.into(), mode: Mode::Active, flags: Flags { ready: true, error: false }, length: 0x0102, offset: -2, data: [1, 2] },
+        Record { channel: 15.into(), mode: Mode::Idle, flags: Flags { ready: false, error: true }, length: 513, offset: 300, data: [255, 0] },
+    ]
+}
+
+#[test]
+fn test_csv_writer() {
+    let mut writer = PackedCsvWriter::new(String::new());
+    writer.write_records(&records()).unwrap();
+
+    assert_eq!(vec![
+        "channel,mode,flags,length,offset,data[0],data[1]",
+        "3,2,0x2,258,-2,1,2",
+        "15,0,0x1,513,300,255,0",
+    ], writer.into_inner().lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_csv_line_ending() {
+    let mut writer = PackedCsvWriter::new(String::new()).with_line_ending("\n");
+    writer.write_records(&records()).unwrap();
+
+    assert_eq!("channel,mode,flags,length,offset,data[0],data[1]\n3,2,0x2,258,-2,1,2\n15,0,0x1,513,300,255,0\n", writer.into_inner());
+}
+
+/// Fails to pack values above 100.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Percent(u8);
+
+impl PackedStruct for Percent {
+    type ByteArray = [u8; 1];
+
+    fn pack(&self) -> PackingResult<[u8; 1]> {
+        if self.0 > 100 {
+            return Err(PackingError::InvalidValue);
+        }
+        Ok([self.0])
+    }
+
+    fn unpack(src: &[u8; 1]) -> PackingResult<Self> {
+        Ok(Percent(src[0]))
+    }
+}
+
+impl PackedStructVisit for Percent {
+    fn visit_fields<V: Visitor + ?Sized>(&self, _ctx: &VisitContext, _parent: Option<&VisitField>, _visitor: &mut V) -> Result<(), V::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_csv_packing_error() {
+    let mut writer = PackedCsvWriter::new(String::new());
+    assert_eq!(Err(CsvWriteError::Packing(PackingError::InvalidValue)), writer.write_records(&[Percent(50), Percent(150)]));
+
+    let error: Box<dyn std::error::Error> = Box::new(CsvWriteError::Packing(PackingError::InvalidValue));
+    assert_eq!(Some(PackingError::InvalidValue.to_string()), error.source().map(|e| e.to_string()));
+}
+
+#[test]
+fn test_csv_round_trip() {
+    let mut writer = PackedCsvWriter::new(String::new()).with_delimiter(';');
+    writer.write_records(&records()).unwrap();
+    let csv = writer.into_inner();
+
+    let read: Vec<Record> = PackedCsvReader::new(&csv).with_delimiter(';').collect::<Result<_, _>>().unwrap();
+    assert_eq!(records(), read);
+}
+
+#[test]
+fn test_csv_reader_stimuli() {
+    let csv = "\"length\", offset, flags, mode\n\n0x0100, -32768, 0b10, 0\n7, 0x7FFF, 0x1, 0\n";
+    let read: Vec<Record> = PackedCsvReader::new(csv).collect::<Result<_, _>>().unwrap();
+
+    assert_eq!(vec![
+        Record { length: 256, offset: -32768, flags: Flags { ready: true, error: false }, mode: Mode::Idle, ..Default::default() },
+        Record { length: 7, offset: 32767, flags: Flags { ready: false, error: true }, ..Default::default() },
+    ], read);
+    // "true" is 1, which isn't a valid mode
+    assert_eq!(Some(Err(PackingError::InvalidValue)), PackedCsvReader::<Record>::new("mode\ntrue\n").next());
+}
+
+#[test]
+fn test_csv_reader_errors() {
+    fn first(csv: &str) -> Option<Result<Record, PackingError>> {
+        PackedCsvReader::new(csv).next()
+    }
+
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("length,unknown\n1,2\n"));
+    assert_eq!(Some(Err(PackingError::BufferSizeMismatch { expected: 2, actual: 3 })), first("length,offset\n1,2,3\n"));
+    assert_eq!(Some(Err(PackingError::BufferSizeMismatch { expected: 2, actual: 1 })), first("length,offset\n1\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("channel\n16\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("offset\n-32769\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("offset\n32768\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("offset\n0x8000\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("length\n-1\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("length\n65536\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("channel\n-1\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("length\nten\n"));
+    assert_eq!(None, first("length,offset\n"));
+}
+
+/// Signals of a CAN frame, positioned like in a DBC file.
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="4")]
+pub struct Signals {
+    #[packed_field(bits="0")]
+    active: bool,
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="23..=24", endian="msb")]
+    temperature: i16
+}
+
+#[test]
+fn test_csv_sawtooth() {
+    let signals = Signals { active: true, speed: 0xABC.into(), temperature: -2 };
+    let mut writer = PackedCsvWriter::new(String::new());
+    writer.write_record(&signals).unwrap();
+    let csv = writer.into_inner();
+    assert_eq!("active,speed,temperature\r\n1,2748,-2\r\n", csv);
+
+    let read: Vec<Signals> = PackedCsvReader::new(&csv).collect::<Result<_, _>>().unwrap();
+    assert_eq!(vec![signals], read);
+
+    let read: Vec<Signals> = PackedCsvReader::new("speed,active\n0x123,false\n").collect::<Result<_, _>>().unwrap();
+    assert_eq!(vec![Signals { speed: 0x123.into(), ..Default::default() }], read);
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Limits {
+    #[packed_field(bytes="0:1")]
+    v: i16,
+    #[packed_field(bytes="2:3")]
+    u: u16,
+    #[packed_field(bits="32:35")]
+    small: Integer<i8, packed_bits::Bits4>
+}
+
+#[test]
+fn test_csv_reader_ranges() {
+    fn first(csv: &str) -> Option<Result<Limits, PackingError>> {
+        PackedCsvReader::new(csv).next()
+    }
+
+    assert_eq!(Some(Ok(Limits { v: -32768, u: 65535, small: (-8).into() })), first("v,u,small\n-32768,65535,-8\n"));
+    assert_eq!(Some(Ok(Limits { v: 32767, u: 0, small: 7.into() })), first("v,u,small\n32767,0,7\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("v,u\n40000,-1\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("v\n40000\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("u\n-1\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("small\n8\n"));
+    assert_eq!(Some(Err(PackingError::InvalidValue)), first("small\n-9\n"));
+}
@@ -0,0 +1,54 @@
+-- Wireshark dissectors of the acme packed structures. Generated, don't edit.
+
+local acme_header = Proto("acme_header", "acme Header")
+
+local acme_header_fields = {
+    kind = ProtoField.uint8("acme_header.kind", "kind", base.DEC, { [1] = "Data", [2] = "Ack" }, 0xF0),
+    reserved = ProtoField.uint8("acme_header.reserved", "_reserved", base.DEC, nil, 0xF),
+    length = ProtoField.uint16("acme_header.length", "length", base.DEC, nil, nil, "Payload length, in bytes"),
+    flags_ready = ProtoField.bool("acme_header.flags.ready", "ready", 8, nil, 0x1, "The \"ready\" flag"),
+    flags_priority = ProtoField.uint8("acme_header.flags.priority", "priority", base.DEC, nil, 0xF0),
+    checksum_0 = ProtoField.uint8("acme_header.checksum_0", "checksum[0]", base.DEC),
+    checksum_1 = ProtoField.uint8("acme_header.checksum_1", "checksum[1]", base.DEC),
+}
+acme_header.fields = acme_header_fields
+
+function acme_header.dissector(buffer, pinfo, tree)
+    if buffer:len() < 6 then
+        return 0
+    end
+    pinfo.cols.protocol = acme_header.name
+    local subtree = tree:add(acme_header, buffer(0, 6))
+    subtree:add(acme_header_fields.kind, buffer(0, 1))
+    subtree:add(acme_header_fields.reserved, buffer(0, 1))
+    subtree:add_le(acme_header_fields.length, buffer(1, 2))
+    local flags_tree = subtree:add(buffer(3, 1), "flags: Flags")
+    flags_tree:add(acme_header_fields.flags_ready, buffer(3, 1))
+    flags_tree:add(acme_header_fields.flags_priority, buffer(3, 1))
+    subtree:add(acme_header_fields.checksum_0, buffer(4, 1))
+    subtree:add(acme_header_fields.checksum_1, buffer(5, 1))
+    return 6
+end
+
+local acme_status = Proto("acme_status", "acme Status")
+
+local acme_status_fields = {
+    active = ProtoField.bool("acme_status.active", "active", 8, nil, 0x1),
+    speed = ProtoField.uint16("acme_status.speed", "speed", base.DEC, nil, 0xFFF0, "Intel signal, crosses bytes 0 and 1"),
+    temperature = ProtoField.int16("acme_status.temperature", "temperature", base.DEC),
+    ["end"] = ProtoField.uint8("acme_status.end", "end", base.DEC, nil, 0xF),
+}
+acme_status.fields = acme_status_fields
+
+function acme_status.dissector(buffer, pinfo, tree)
+    if buffer:len() < 5 then
+        return 0
+    end
+    pinfo.cols.protocol = acme_status.name
+    local subtree = tree:add(acme_status, buffer(0, 5))
+    subtree:add(acme_status_fields.active, buffer(0, 1))
+    subtree:add_le(acme_status_fields.speed, buffer(0, 2))
+    subtree:add(acme_status_fields.temperature, buffer(2, 2))
+    subtree:add(acme_status_fields["end"], buffer(4, 1))
+    return 5
+end
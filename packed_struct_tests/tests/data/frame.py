@@ -0,0 +1,73 @@
+# Packing of the Frame packed structure. Generated, don't edit.
+
+from dataclasses import dataclass
+
+
+def _signed(value, bits):
+    """Sign-extends a value of the given width."""
+    return value - (1 << bits) if value >> (bits - 1) else value
+
+
+@dataclass
+class Frame:
+    """The Frame packed structure, 8 bytes."""
+
+    SIZE = 8
+
+    active: bool = False
+    # Intel, starts at its least significant bit
+    speed: int = 0
+    temperature: int = 0
+    # Motorola, starts at its most significant bit
+    level: int = 0
+    count: int = 0
+
+    def pack(self) -> bytes:
+        n = 0
+        n |= (int(self.active) & 0x1) << 56
+        value = self.speed
+        n |= (value & 0xF) << 60
+        n |= (value & 0xFF0) << 44
+        n |= (self.temperature & 0xFFFF) << 32
+        n |= (self.level & 0xFFF) << 16
+        value = self.count
+        n |= (value & 0xF) << 12
+        n |= (value & 0xF0) >> 4
+        return n.to_bytes(8, "big")
+
+    @classmethod
+    def unpack(cls, data: bytes) -> "Frame":
+        if len(data) != 8:
+            raise ValueError("Frame is 8 bytes, not {}".format(len(data)))
+        n = int.from_bytes(data, "big")
+        return cls(
+            active=bool(n >> 56 & 0x1),
+            speed=(n >> 60 & 0xF) | (n >> 44 & 0xFF0),
+            temperature=_signed(n >> 32 & 0xFFFF, 16),
+            level=n >> 16 & 0xFFF,
+            count=_signed((n >> 12 & 0xF) | (n << 4 & 0xF0), 8),
+        )
+
+
+# Packed by the Rust code, with the values they unpack to.
+TEST_VECTORS = [
+    (
+        bytes.fromhex("c1abfffe0123600a"),
+        Frame(active=True, speed=2748, temperature=-2, level=291, count=-90),
+    ),
+    (
+        bytes.fromhex("100080000ffff007"),
+        Frame(active=False, speed=1, temperature=-32768, level=4095, count=127),
+    ),
+]
+
+
+def self_test():
+    """Checks that the test vectors unpack to their values and pack back to their bytes."""
+    for data, value in TEST_VECTORS:
+        assert Frame.unpack(data) == value, "unpacking {}".format(data.hex())
+        assert value.pack() == data, "packing {}".format(data.hex())
+
+
+if __name__ == "__main__":
+    self_test()
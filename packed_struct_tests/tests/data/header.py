@@ -0,0 +1,141 @@
+# Packing of the Header packed structure. Generated, don't edit.
+
+import enum
+from dataclasses import dataclass, field
+from typing import List, Union
+
+
+def _signed(value, bits):
+    """Sign-extends a value of the given width."""
+    return value - (1 << bits) if value >> (bits - 1) else value
+
+
+def _enum_or_int(enum_type, value):
+    """The enum's variant with the value, or the value if there's none."""
+    try:
+        return enum_type(value)
+    except ValueError:
+        return value
+
+
+class Kind(enum.IntEnum):
+    DATA = 1
+    ACK = 2
+
+
+class Trim(enum.IntEnum):
+    DOWN = -1
+    NONE = 0
+    UP = 1
+
+
+@dataclass
+class Flags:
+    """The Flags packed structure, 1 byte."""
+
+    SIZE = 1
+
+    # The "ready" flag
+    ready: bool = False
+    priority: int = 0
+
+    def pack(self) -> bytes:
+        n = 0
+        n |= int(self.ready) & 0x1
+        n |= (self.priority & 0xF) << 4
+        return n.to_bytes(1, "big")
+
+    @classmethod
+    def unpack(cls, data: bytes) -> "Flags":
+        if len(data) != 1:
+            raise ValueError("Flags is 1 byte, not {}".format(len(data)))
+        n = int.from_bytes(data, "big")
+        return cls(
+            ready=bool(n & 0x1),
+            priority=n >> 4 & 0xF,
+        )
+
+
+@dataclass
+class Header:
+    """The Header packed structure, 13 bytes."""
+
+    SIZE = 13
+
+    kind: Kind = Kind.DATA
+    # Payload length, in bytes
+    length: int = 0
+    flags: List[Flags] = field(default_factory=lambda: [Flags() for _ in range(2)])
+    delta: int = 0
+    trim: Trim = Trim.NONE
+    mode: Union[Kind, int] = Kind.DATA
+    address: int = 0
+    from_: List[int] = field(default_factory=lambda: [0] * 3)
+
+    def pack(self) -> bytes:
+        n = 0
+        n |= (int(self.kind) & 0xF) << 100
+        n |= 0xF000000000000000000000000
+        value = self.length
+        n |= (value & 0xFF) << 88
+        n |= (value & 0xFF00) << 72
+        n |= (int.from_bytes(self.flags[0].pack(), "big") & 0xFF) << 72
+        n |= (int.from_bytes(self.flags[1].pack(), "big") & 0xFF) << 64
+        n |= (self.delta & 0xFFF) << 52
+        n |= (int(self.trim) & 0x3) << 50
+        n |= (int(self.mode) & 0x3) << 48
+        value = self.address
+        n |= (value & 0xFF) << 40
+        n |= (value & 0xFF00) << 24
+        n |= (value & 0xFF0000) << 8
+        n |= (self.from_[0] & 0xFF) << 16
+        n |= (self.from_[1] & 0xFF) << 8
+        n |= self.from_[2] & 0xFF
+        return n.to_bytes(13, "big")
+
+    @classmethod
+    def unpack(cls, data: bytes) -> "Header":
+        if len(data) != 13:
+            raise ValueError("Header is 13 bytes, not {}".format(len(data)))
+        n = int.from_bytes(data, "big")
+        return cls(
+            kind=Kind(n >> 100 & 0xF),
+            length=(n >> 88 & 0xFF) | (n >> 72 & 0xFF00),
+            flags=[
+                Flags.unpack(data[3:4]),
+                Flags.unpack(data[4:5]),
+            ],
+            delta=_signed(n >> 52 & 0xFFF, 12),
+            trim=Trim(_signed(n >> 50 & 0x3, 2)),
+            mode=_enum_or_int(Kind, n >> 48 & 0x3),
+            address=(n >> 40 & 0xFF) | (n >> 24 & 0xFF00) | (n >> 8 & 0xFF0000),
+            from_=[
+                n >> 16 & 0xFF,
+                n >> 8 & 0xFF,
+                n & 0xFF,
+            ],
+        )
+
+
+# Packed by the Rust code, with the values they unpack to.
+TEST_VECTORS = [
+    (
+        bytes.fromhex("2f34129130ed4fefcdab010203"),
+        Header(kind=Kind.ACK, length=4660, flags=[Flags(ready=True, priority=9), Flags(ready=False, priority=3)], delta=-300, trim=Trim.DOWN, mode=3, address=11259375, from_=[1, 2, 3]),
+    ),
+    (
+        bytes.fromhex("2f3412f1f1ed4defcdab010203"),
+        Header(kind=Kind.ACK, length=4660, flags=[Flags(ready=True, priority=15), Flags(ready=True, priority=15)], delta=-300, trim=Trim.DOWN, mode=Kind.DATA, address=11259375, from_=[1, 2, 3]),
+    ),
+]
+
+
+def self_test():
+    """Checks that the test vectors unpack to their values and pack back to their bytes."""
+    for data, value in TEST_VECTORS:
+        assert Header.unpack(data) == value, "unpacking {}".format(data.hex())
+        assert value.pack() == data, "packing {}".format(data.hex())
+
+
+if __name__ == "__main__":
+    self_test()
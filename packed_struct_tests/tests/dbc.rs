@@ -0,0 +1,77 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::PackedStructLayout;
+
+#[allow(dead_code)]
+mod vehicle {
+    include!(concat!(env!("OUT_DIR"), "/vehicle.rs"));
+}
+
+use vehicle::{DiagResponse, DiagResponseMode, EngineData, EngineDataGear};
+
+const ENGINE_DATA: [u8; 8] = [0x01, 0x7D, 0x05, 0x14, 0x00, 0xC0, 0xB0, 0x0F];
+
+fn engine_data() -> EngineData {
+    EngineData {
+        running: true,
+        engine_speed: 2000.into(),
+        coolant_temp: 1300,
+        gear: EnumCatchAll::Enum(EngineDataGear::Drive),
+        torque: -5
+    }
+}
+
+#[test]
+fn test_signal_bits() {
+    assert_eq!(ENGINE_DATA, engine_data().pack().unwrap());
+    assert_eq!(engine_data(), EngineData::unpack(&ENGINE_DATA).unwrap());
+
+    let diag = DiagResponse::unpack(&[0x02, 0xFF, 0xFF, 0xA0]).unwrap();
+    assert_eq!(EnumCatchAll::Enum(DiagResponseMode::Status), diag.mode);
+    assert_eq!(0xA, *diag.counter);
+}
+
+#[test]
+fn test_physical_values() {
+    let mut data = engine_data();
+    assert_eq!(1000.0, data.engine_speed_physical());
+    assert!((data.coolant_temp_physical() - 90.0).abs() < 1e-9);
+
+    data.set_coolant_temp_physical(-20.0).unwrap();
+    assert_eq!(200, data.coolant_temp);
+    data.set_engine_speed_physical(100.2).unwrap();
+    assert_eq!(200, *data.engine_speed);
+
+    assert_eq!(Err(PackingError::InvalidValue), data.set_engine_speed_physical(2048.0));
+    assert_eq!(Err(PackingError::InvalidValue), data.set_engine_speed_physical(-1.0));
+    assert_eq!(200, *data.engine_speed);
+}
+
+#[test]
+fn test_message_metadata() {
+    assert_eq!((0x64, false), (EngineData::ID, EngineData::EXTENDED));
+    assert_eq!((0x18FF_0015, true), (DiagResponse::ID, DiagResponse::EXTENDED));
+
+    // the multiplexed signals and the unassigned signal are skipped
+    let fields: Vec<_> = DiagResponse::FIELDS.iter().map(|f| f.name).collect();
+    assert_eq!(vec!["mode", "counter"], fields);
+
+    let speed = EngineData::field("engine_speed").unwrap();
+    assert_eq!("Crankshaft speed\n\nPhysical value = raw * 0.5, in rpm.", speed.doc);
+    assert_eq!(4..15, speed.declared_bits);
+    assert_eq!(33..46, EngineData::field("gear").unwrap().declared_bits);
+}
+
+#[test]
+fn test_generate_errors() {
+    use packed_struct_build::dbc::generate_str;
+    use packed_struct_build::Error;
+
+    let outside = "BO_ 1 Short: 1 Node\n SG_ Value : 4|8@1+ (1,0) [0|0] \"\" Node\n";
+    assert!(matches!(generate_str(outside), Err(Error::Invalid(_))));
+
+    let byte_order = "BO_ 1 Message: 1 Node\n SG_ Value : 0|8@2+ (1,0) [0|0] \"\" Node\n";
+    assert!(matches!(generate_str(byte_order), Err(Error::Invalid(ref e)) if e.starts_with("line 2:")));
+
+    let unterminated = "CM_ \"comment;\n";
+    assert!(matches!(generate_str(unterminated), Err(Error::Invalid(_))));
+}
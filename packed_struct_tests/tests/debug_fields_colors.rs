@@ -0,0 +1,94 @@
+use packed_struct::prelude::*;
+use packed_struct::debug_fmt::{DebugBitMap, PackedStructDebug, PackedStructDisplay, ANSI_DIM, ANSI_FIELD_COLORS, ANSI_RESET};
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Flags {
+    #[packed_field(bits="6")]
+    ready: bool,
+    #[packed_field(bits="7")]
+    error: bool
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Control {
+    #[packed_field(bits="0:3")]
+    channel: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="4:5")]
+    _reserved: ReservedOne<packed_bits::Bits2>,
+    #[packed_field(bits="6:7")]
+    flags: Flags
+}
+
+fn colored(color: &str, bits: &str) -> String {
+    bits.chars().map(|b| format!("{}{}{}", color, b, ANSI_RESET)).collect()
+}
+
+#[test]
+fn test_bit_map_colors() {
+    let control = Control { channel: 5.into(), flags: Flags { ready: true, error: false }, ..Default::default() };
+    let packed = control.pack().unwrap();
+    let fields = control.debug_fields();
+    let map = DebugBitMap::new(&packed, &fields).with_line_ending("\n").to_string();
+    let lines: Vec<_> = map.lines().collect();
+
+    let grid = format!("0000: {}{}{}{}",
+        colored(ANSI_FIELD_COLORS[0], "0101"),
+        colored(ANSI_DIM, "11"),
+        colored(ANSI_FIELD_COLORS[1], "1"),
+        colored(ANSI_FIELD_COLORS[2], "0")
+    );
+    assert_eq!(grid, lines[0]);
+
+    assert_eq!(format!("  {}channel    {} | bits   0:3  ", ANSI_FIELD_COLORS[0], ANSI_RESET), lines[1]);
+    assert_eq!(format!("  {}_reserved  {} | bits   4:5  ", ANSI_DIM, ANSI_RESET), lines[2]);
+    assert_eq!(format!("  {}flags.ready{} | bits   6:6  ", ANSI_FIELD_COLORS[1], ANSI_RESET), lines[3]);
+    assert_eq!(format!("  {}flags.error{} | bits   7:7  ", ANSI_FIELD_COLORS[2], ANSI_RESET), lines[4]);
+    assert_eq!(5, lines.len());
+}
+
+#[test]
+fn test_display_with_colors() {
+    let control = Control::default();
+
+    let plain = PackedStructDisplay::new(&control).to_string();
+    assert!(!plain.contains('\x1b'));
+    assert!(!plain.contains("Bit map"));
+
+    let colored = PackedStructDisplay::new(&control).with_raw(false, false, false).with_colors(true).to_string();
+    assert!(colored.contains("Bit map\r\n0000: "), "{}", colored);
+    assert!(colored.ends_with(concat!(
+        "   channel   | bits   0:3   | 0b0000 | \"0\"\r\n",
+        " _reserved   | bits   4:5   | 0b11   | \"Reserved - always 1\"\r\n",
+        "     flags   | bits   6:7   | 0b00   | \"Flags { ready: false, error: false }\"\r\n",
+        "       ready | bits   6:6   | 0b0    | \"false\"\r\n",
+        "       error | bits   7:7   | 0b0    | \"false\"\r\n"
+    )), "{}", colored);
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="2")]
+pub struct Signals {
+    #[packed_field(bits="0")]
+    active: bool,
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>
+}
+
+#[test]
+fn test_bit_map_sawtooth() {
+    let signals = Signals { active: true, speed: 0xABC.into() };
+    let packed = signals.pack().unwrap();
+    let fields = signals.debug_fields();
+    let map = DebugBitMap::new(&packed, &fields).with_line_ending("\n").to_string();
+    let lines: Vec<_> = map.lines().collect();
+
+    let grid = format!("0000: {}{}{} {}",
+        colored(ANSI_FIELD_COLORS[1], "1100"),
+        colored(ANSI_DIM, "000"),
+        colored(ANSI_FIELD_COLORS[0], "1"),
+        colored(ANSI_FIELD_COLORS[1], "10101011")
+    );
+    assert_eq!(grid, lines[0]);
+}
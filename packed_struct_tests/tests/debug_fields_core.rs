@@ -0,0 +1,118 @@
+use packed_struct::prelude::*;
+use packed_struct::debug_fmt::PackedStructFmt;
+
+use core::fmt::{self, Write};
+
+/// A fixed-capacity sink, standing in for a UART.
+struct Uart {
+    buffer: [u8; 512],
+    len: usize
+}
+
+impl Uart {
+    fn new() -> Self {
+        Uart { buffer: [0; 512], len: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buffer[..self.len]).unwrap()
+    }
+}
+
+impl Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buffer.len() {
+            return Err(fmt::Error);
+        }
+        self.buffer[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Idle = 0,
+    Active = 1
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Status {
+    #[packed_field(bits="0", access="w1c")]
+    overrun: bool,
+    #[packed_field(bits="6:7", ty="enum")]
+    mode: Mode,
+    #[packed_field(bytes="1..=2")]
+    samples: [u8; 2],
+    #[packed_field(bytes="3:10")]
+    timestamp: u64
+}
+
+#[test]
+fn test_write_fields_core() {
+    let status = Status {
+        overrun: true,
+        mode: Mode::Active,
+        samples: [7, 8],
+        timestamp: 0xAB
+    };
+
+    let mut uart = Uart::new();
+    status.write_fields(&mut uart).unwrap();
+
+    let lines: Vec<_> = uart.as_str().split("\r\n").collect();
+    assert_eq!(vec![
+        "    overrun | w1c | bits   0:0   | 0b1                | true",
+        "       mode | rw  | bits   6:7   | 0b01               | Active",
+        " samples[0] | rw  | bits   8:15  | 0b00000111         | 7",
+        " samples[1] | rw  | bits  16:23  | 0b00001000         | 8",
+        "  timestamp | rw  | bits  24:87  | 0x00000000000000AB | 171",
+        ""
+    ], lines);
+}
+
+#[test]
+fn test_write_fields_sink_full() {
+    struct Tiny(usize);
+
+    impl Write for Tiny {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if self.0 + s.len() > 16 {
+                return Err(fmt::Error);
+            }
+            self.0 += s.len();
+            Ok(())
+        }
+    }
+
+    let status = Status { overrun: false, mode: Mode::Idle, samples: [0, 0], timestamp: 0 };
+    assert_eq!(Err(fmt::Error), status.write_fields(&mut Tiny(0)));
+}
+
+/// Signals of a CAN frame, the little-endian one runs backwards through the bytes.
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="2")]
+pub struct Signals {
+    #[packed_field(bits="0")]
+    active: bool,
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>
+}
+
+#[test]
+fn test_write_fields_sawtooth() {
+    let signals = Signals { active: true, speed: 0xABC.into() };
+    assert_eq!([0xC1, 0xAB], signals.pack().unwrap());
+
+    let mut uart = Uart::new();
+    signals.write_fields(&mut uart).unwrap();
+
+    let lines: Vec<_> = uart.as_str().split("\r\n").collect();
+    assert_eq!(vec![
+        " active | bits   7:7   | 0b1            | true",
+        "  speed | bits   0:15  | 0b101010111100 | 2748",
+        ""
+    ], lines);
+}
@@ -0,0 +1,154 @@
+use packed_struct::prelude::*;
+use packed_struct::PackingResult;
+use packed_struct::debug_fmt::{DebugBitField, DisplayRadix, PackedStructDebug, PackedStructDisplay};
+use packed_struct::FieldAccess;
+
+use std::fmt;
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Sample {
+    #[packed_field(bits="0")]
+    valid: bool,
+    #[packed_field(bits="4:7")]
+    channel: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bytes="1:2")]
+    value: u16,
+    #[packed_field(bytes="3..=4")]
+    tags: [u8; 2]
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", display="compact")]
+pub struct Flags {
+    #[packed_field(bits="0")]
+    ready: bool,
+    #[packed_field(bits="1")]
+    error: bool
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", display="none")]
+pub struct Temperature {
+    #[packed_field(bytes="0")]
+    celsius: i8
+}
+
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} °C", self.celsius)
+    }
+}
+
+fn sample() -> Sample {
+    Sample { valid: true, channel: 5.into(), value: 300, tags: [1, 2] }
+}
+
+#[test]
+fn test_display_attribute() {
+    assert_eq!("Flags (1 byte): ready=0b1 (true), error=0b0 (false)", Flags { ready: true, error: false }.to_string());
+    assert_eq!("-5 °C", Temperature { celsius: -5 }.to_string());
+
+    // the table formatter is still available
+    let s = Temperature { celsius: -5 }.packed_struct_display_formatter().to_string();
+    assert!(s.contains(" celsius | bits   0:7   | 0b11111011 | \"-5\""), "{}", s);
+}
+
+#[test]
+fn test_display_compact() {
+    let s = sample();
+    let display = PackedStructDisplay::new(&s).compact().with_header(false).with_radix(DisplayRadix::Hex);
+    assert_eq!("valid=0x1 (true), channel=0x5 (5), value=0x012C (300), tags[0]=0x01 (1), tags[1]=0x02 (2)", display.to_string());
+}
+
+#[test]
+fn test_display_options() {
+    let s = sample();
+    let only = ["channel", "tags"];
+    let display = PackedStructDisplay::new(&s)
+        .with_header(false)
+        .with_raw(false, true, false)
+        .with_radix(DisplayRadix::Decimal)
+        .with_line_ending("\n")
+        .with_only_fields(&only);
+
+    assert_eq!("Hex\n[0x85, 0x1, 0x2C, 0x1, 0x2]\n\n channel | bits   4:7   | 5 | \"5\"\n tags[0] | bits  24:31  | 1 | \"1\"\n tags[1] | bits  32:39  | 2 | \"2\"\n", display.to_string());
+
+    let display = PackedStructDisplay::new(&s).with_raw(false, false, false).with_fields(false);
+    assert_eq!("Sample (5 bytes)\r\n\r\n", display.to_string());
+}
+
+/// Implemented by hand, without describing its fields.
+pub struct Level(u8);
+
+impl PackedStruct for Level {
+    type ByteArray = [u8; 1];
+
+    fn pack(&self) -> PackingResult<[u8; 1]> {
+        Ok([self.0])
+    }
+
+    fn unpack(src: &[u8; 1]) -> PackingResult<Self> {
+        Ok(Level(src[0]))
+    }
+}
+
+impl PackedStructDebug for Level {
+    fn fmt_fields(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "level: {}", self.0)
+    }
+
+    fn packed_struct_display_header() -> &'static str {
+        "Level"
+    }
+}
+
+#[test]
+fn test_display_manual_debug() {
+    let level = Level(7);
+    assert!(level.debug_fields().is_empty());
+
+    let display = PackedStructDisplay::new(&level).with_raw(false, true, false).with_line_ending("\n");
+    assert_eq!("Level\n\nHex\n[0x7]\n\nlevel: 7", display.to_string());
+
+    assert_eq!("Level: 07", PackedStructDisplay::new(&level).compact().to_string());
+    assert_eq!("2A", PackedStructDisplay::new(&Level(42)).compact().with_header(false).to_string());
+}
+
+/// Implemented by hand, with its fields built like the derive's.
+pub struct Gauge(u8);
+
+impl PackedStruct for Gauge {
+    type ByteArray = [u8; 1];
+
+    fn pack(&self) -> PackingResult<[u8; 1]> {
+        Ok([self.0])
+    }
+
+    fn unpack(src: &[u8; 1]) -> PackingResult<Self> {
+        Ok(Gauge(src[0]))
+    }
+}
+
+impl PackedStructDebug for Gauge {
+    fn fmt_fields(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "gauge: {}", self.0)
+    }
+
+    fn packed_struct_display_header() -> &'static str {
+        "Gauge"
+    }
+
+    fn debug_fields(&self) -> Vec<DebugBitField<'static>> {
+        vec![
+            DebugBitField::new("level", 0..3, format!("{:?}", self.0 >> 4)).with_access(FieldAccess::ReadOnly),
+            DebugBitField::new("_reserved", 4..7, "0").with_reserved(true)
+        ]
+    }
+}
+
+#[test]
+fn test_display_manual_debug_fields() {
+    let display = PackedStructDisplay::new(&Gauge(0x30)).with_header(false).with_raw(false, false, false).with_line_ending("\n");
+    assert_eq!("     level | ro  | bits   0:3   | 0b0011 | \"3\"\n _reserved | rw  | bits   4:7   | 0b0000 | \"0\"\n", display.to_string());
+}
@@ -0,0 +1,106 @@
+use packed_struct::prelude::*;
+use packed_struct::debug_fmt::PackedStructDisplay;
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct TinyFlags {
+    #[packed_field(bits="4")]
+    flag1: bool,
+    val1: Integer<u8, packed_bits::Bits2>,
+    flag2: bool
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Settings {
+    #[packed_field(bits="0:15", element_size_bits="4")]
+    values: [TinyFlags; 4]
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Header {
+    #[packed_field(bits="0:3")]
+    version: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="4:7")]
+    flags: TinyFlags
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Frame {
+    #[packed_field(bytes="0")]
+    header: Header,
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3:4")]
+    settings: Settings
+}
+
+#[test]
+fn test_nested_array_elements() {
+    let settings = Settings {
+        values: [
+            TinyFlags { flag1: true, val1: 1.into(), flag2: false },
+            TinyFlags { flag1: false, val1: 2.into(), flag2: true },
+            TinyFlags::default(),
+            TinyFlags::default(),
+        ]
+    };
+
+    let s = PackedStructDisplay::new(&settings).with_header(false).with_raw(false, false, false).with_line_ending("\n").to_string();
+    assert_eq!(concat!(
+        " values[0]   | bits   0:3   | 0b1010 | \"TinyFlags { flag1: true, val1: 1, flag2: false }\"\n",
+        "       flag1 | bits   0:0   | 0b1    | \"true\"\n",
+        "        val1 | bits   1:2   | 0b01   | \"1\"\n",
+        "       flag2 | bits   3:3   | 0b0    | \"false\"\n",
+        " values[1]   | bits   4:7   | 0b0101 | \"TinyFlags { flag1: false, val1: 2, flag2: true }\"\n",
+        "       flag1 | bits   4:4   | 0b0    | \"false\"\n",
+        "        val1 | bits   5:6   | 0b10   | \"2\"\n",
+        "       flag2 | bits   7:7   | 0b1    | \"true\"\n",
+        " values[2]   | bits   8:11  | 0b0000 | \"TinyFlags { flag1: false, val1: 0, flag2: false }\"\n",
+        "       flag1 | bits   8:8   | 0b0    | \"false\"\n",
+        "        val1 | bits   9:10  | 0b00   | \"0\"\n",
+        "       flag2 | bits  11:11  | 0b0    | \"false\"\n",
+        " values[3]   | bits  12:15  | 0b0000 | \"TinyFlags { flag1: false, val1: 0, flag2: false }\"\n",
+        "       flag1 | bits  12:12  | 0b0    | \"false\"\n",
+        "        val1 | bits  13:14  | 0b00   | \"0\"\n",
+        "       flag2 | bits  15:15  | 0b0    | \"false\"\n"
+    ), s);
+}
+
+#[test]
+fn test_nested_three_levels() {
+    let frame = Frame {
+        header: Header { version: 2.into(), flags: TinyFlags { flag1: true, val1: 3.into(), flag2: true } },
+        length: 5,
+        settings: Settings {
+            values: [
+                TinyFlags::default(),
+                TinyFlags::default(),
+                TinyFlags::default(),
+                TinyFlags { flag1: true, val1: 0.into(), flag2: true },
+            ]
+        }
+    };
+
+    let s = PackedStructDisplay::new(&frame).with_header(false).with_raw(false, false, false).with_line_ending("\n").to_string();
+    let lines: Vec<_> = s.lines().collect();
+    assert_eq!(24, lines.len());
+    assert_eq!(&[
+        "   header     | bits   0:7   | 0b00101111         | \"Header { version: 2, flags: TinyFlags { flag1: true, val1: 3, flag2: true } }\"",
+        "    version   | bits   0:3   | 0b0010             | \"2\"",
+        "      flags   | bits   4:7   | 0b1111             | \"TinyFlags { flag1: true, val1: 3, flag2: true }\"",
+        "        flag1 | bits   4:4   | 0b1                | \"true\"",
+        "         val1 | bits   5:6   | 0b11               | \"3\"",
+        "        flag2 | bits   7:7   | 0b1                | \"true\"",
+        "   length     | bits   8:23  | 0b0000000000000101 | \"5\"",
+    ], &lines[..7]);
+    assert!(lines[7].starts_with(" settings     | bits  24:39  | 0b0000000000001001 | "), "{}", s);
+    assert_eq!(&[
+        "  values[3]   | bits  36:39  | 0b1001             | \"TinyFlags { flag1: true, val1: 0, flag2: true }\"",
+        "        flag1 | bits  36:36  | 0b1                | \"true\"",
+        "         val1 | bits  37:38  | 0b00               | \"0\"",
+        "        flag2 | bits  39:39  | 0b1                | \"true\"",
+    ], &lines[20..]);
+}
@@ -0,0 +1,80 @@
+use packed_struct::prelude::*;
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Descriptor {
+    #[packed_field(bits="0:3")]
+    kind: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="4:7")]
+    flags: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bytes="1:8")]
+    timestamp: u64,
+    #[packed_field(bits="72:107")]
+    address: Integer<u64, packed_bits::Bits36>,
+    #[packed_field(bits="108:111")]
+    channel: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bytes="14:15")]
+    length: u16,
+    #[packed_field(bytes="16..=19")]
+    tags: [u8; 4]
+}
+
+fn descriptor() -> Descriptor {
+    Descriptor {
+        kind: 0xA.into(),
+        flags: 0x5.into(),
+        timestamp: 0x0123_4567_89AB_CDEF,
+        address: 0x9_8765_4321.into(),
+        channel: 3.into(),
+        length: 512,
+        tags: [1, 2, 3, 4]
+    }
+}
+
+#[test]
+fn test_wide_fields() {
+    let s = descriptor().to_string();
+
+    let table = concat!(
+        "      kind | bits   0:3   | 0b1010             | \"10\"\r\n",
+        "     flags | bits   4:7   | 0b0101             | \"5\"\r\n",
+        " timestamp | bits   8:71  | 0x0123456789ABCDEF | \"81985529216486895\"\r\n",
+        "   address | bits  72:107 | 0x987654321        | \"40926266145\"\r\n",
+        "   channel | bits 108:111 | 0b0011             | \"3\"\r\n",
+        "    length | bits 112:127 | 0b0000001000000000 | \"512\"\r\n",
+        "   tags[0] | bits 128:135 | 0b00000001         | \"1\"\r\n",
+        "   tags[1] | bits 136:143 | 0b00000010         | \"2\"\r\n",
+        "   tags[2] | bits 144:151 | 0b00000011         | \"3\"\r\n",
+        "   tags[3] | bits 152:159 | 0b00000100         | \"4\"\r\n"
+    );
+    assert!(s.ends_with(table), "{}", s);
+}
+
+#[test]
+fn test_hexdump_rows() {
+    let s = descriptor().to_string();
+
+    assert!(s.contains("Hex\r\n0000: A5 01 23 45 67 89 AB CD EF 98 76 54 32 13 02 00\r\n0010: 01 02 03 04\r\n"), "{}", s);
+    assert!(s.contains("Decimal\r\n0000: 165   1  35"), "{}", s);
+    assert!(s.contains("Binary\r\n0000: 10100101 00000001"), "{}", s);
+    assert!(s.contains("\r\n0010: 00000001 00000010 00000011 00000100\r\n"), "{}", s);
+}
+
+#[test]
+fn test_hexdump_line_ending() {
+    let mut s = String::new();
+    packed_struct::debug_fmt::fmt_hexdump(&mut s, &descriptor().pack().unwrap(), "\n").unwrap();
+    assert_eq!("0000: A5 01 23 45 67 89 AB CD EF 98 76 54 32 13 02 00\n0010: 01 02 03 04\n", s);
+}
+
+#[test]
+fn test_small_struct_single_line() {
+    #[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+    pub struct Small {
+        a: u8,
+        b: u8
+    }
+
+    let s = Small { a: 1, b: 0xFF }.to_string();
+    assert!(s.contains("Hex\r\n[0x1, 0xFF]\r\n"), "{}", s);
+}
@@ -0,0 +1,141 @@
+use packed_struct::prelude::*;
+use packed_struct::debug_fmt::{DiffBytesError, PackedStructDiff};
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Idle = 0,
+    Sampling = 1,
+    Streaming = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Config {
+    #[packed_field(bits="0:1", ty="enum")]
+    mode: Mode,
+    #[packed_field(bits="4")]
+    enabled: bool,
+    #[packed_field(bits="5:7")]
+    gain: Integer<u8, packed_bits::Bits3>,
+    #[packed_field(bytes="1:2")]
+    threshold: u16,
+    #[packed_field(bytes="3..=4")]
+    offsets: [u8; 2]
+}
+
+fn config() -> Config {
+    Config {
+        mode: Mode::Sampling,
+        enabled: true,
+        gain: 3.into(),
+        threshold: 1000,
+        offsets: [1, 2]
+    }
+}
+
+#[test]
+fn test_diff_instances() {
+    let old = config();
+    let new = Config { mode: Mode::Streaming, threshold: 1200, offsets: [1, 5], ..old };
+
+    let diff = old.diff(&new).unwrap();
+    let names: Vec<_> = diff.fields.iter().map(|f| f.name.as_ref()).collect();
+    assert_eq!(vec!["mode", "threshold", "offsets[1]"], names);
+
+    let mode = &diff.fields[0];
+    assert_eq!(0..1, mode.bits);
+    assert_eq!("Sampling", mode.old_value);
+    assert_eq!("Streaming", mode.new_value);
+    assert!(diff.contains("threshold"));
+    assert!(!diff.contains("gain"));
+
+    assert!(old.diff(&old).unwrap().is_empty());
+}
+
+#[test]
+fn test_diff_bytes() {
+    let old = config().pack().unwrap();
+    let mut new = old;
+    new[0] |= 0x04;
+    new[4] = 9;
+
+    let diff = Config::diff_bytes(&old, &new).unwrap();
+    let names: Vec<_> = diff.fields.iter().map(|f| f.name.as_ref()).collect();
+    assert_eq!(vec!["gain", "offsets[1]"], names);
+    assert_eq!("3", diff.fields[0].old_value);
+    assert_eq!("7", diff.fields[0].new_value);
+
+    assert_eq!(Err(DiffBytesError::New(PackingError::BufferSizeMismatch { expected: 5, actual: 2 })), Config::diff_bytes(&old, &[0, 0]).map(|_| ()));
+    assert_eq!(Err(DiffBytesError::Old(PackingError::BufferSizeMismatch { expected: 5, actual: 6 })), Config::diff_bytes(&[0; 6], &new).map(|_| ()));
+
+    let mut invalid = old;
+    invalid[0] |= 0xC0;
+    assert_eq!(Err(DiffBytesError::Old(PackingError::InvalidValue)), Config::diff_bytes(&invalid, &new).map(|_| ()));
+    assert_eq!(Err(DiffBytesError::New(PackingError::InvalidValue)), Config::diff_bytes(&old, &invalid).map(|_| ()));
+}
+
+#[test]
+fn test_diff_display() {
+    let old = config();
+    let new = Config { enabled: false, threshold: 1001, ..old };
+
+    let table = old.diff(&new).unwrap().to_string();
+    assert_eq!(concat!(
+        "   enabled | bits   4:4   | 0b1                -> 0b0                | \"true\" -> \"false\"\r\n",
+        " threshold | bits   8:23  | 0b0000001111101000 -> 0b0000001111101001 | \"1000\" -> \"1001\"\r\n"
+    ), table);
+
+    let table = old.diff(&new).unwrap().with_line_ending("\n").to_string();
+    assert_eq!(concat!(
+        "   enabled | bits   4:4   | 0b1                -> 0b0                | \"true\" -> \"false\"\n",
+        " threshold | bits   8:23  | 0b0000001111101000 -> 0b0000001111101001 | \"1000\" -> \"1001\"\n"
+    ), table);
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Flags {
+    #[packed_field(bits="0")]
+    ready: bool,
+    #[packed_field(bits="4:7")]
+    priority: Integer<u8, packed_bits::Bits4>
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Frame {
+    #[packed_field(bytes="0")]
+    kind: u8,
+    #[packed_field(bytes="1:2")]
+    flags: [Flags; 2]
+}
+
+#[test]
+fn test_diff_nested() {
+    let old = Frame { kind: 1, flags: [Flags { ready: false, priority: 2.into() }; 2] };
+    let mut new = old;
+    new.flags[1].ready = true;
+
+    let table = old.diff(&new).unwrap().to_string();
+    assert_eq!(" flags[1].ready | bits  16:16  | 0b0 -> 0b1 | \"false\" -> \"true\"\r\n", table);
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="2")]
+pub struct Signals {
+    #[packed_field(bits="0")]
+    active: bool,
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>
+}
+
+#[test]
+fn test_diff_sawtooth() {
+    let old = Signals { active: false, speed: 0xABC.into() };
+    let new = Signals { active: true, ..old };
+
+    // the bits of the little-endian field are in both bytes, but not in bit 7
+    let diff = old.diff(&new).unwrap();
+    let names: Vec<_> = diff.fields.iter().map(|f| f.name.as_ref()).collect();
+    assert_eq!(vec!["active"], names);
+}
@@ -0,0 +1,145 @@
+use packed_struct::prelude::*;
+use packed_struct::dynamic::{PackedDynamic, Value};
+use packed_struct::visit::IntegerValue;
+use packed_struct::PackingResult;
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq, Default)]
+pub enum Mode {
+    #[default]
+    Idle = 0,
+    Run = 1,
+    Sleep = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Flags {
+    #[packed_field(bits="6")]
+    ready: bool,
+    #[packed_field(bits="7")]
+    error: bool
+}
+
+/// Packs without the derive, so it's only accessible as bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Checksum(u8);
+
+impl PackedStruct for Checksum {
+    type ByteArray = [u8; 1];
+
+    fn pack(&self) -> PackingResult<[u8; 1]> {
+        Ok([self.0])
+    }
+
+    fn unpack(src: &[u8; 1]) -> PackingResult<Self> {
+        Ok(Checksum(src[0]))
+    }
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq, Default)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Frame {
+    #[packed_field(bits="0:1", ty="enum")]
+    mode: Mode,
+    #[packed_field(bits="2:3")]
+    _reserved: ReservedZero<packed_bits::Bits2>,
+    #[packed_field(bits="4:7")]
+    offset: Integer<i8, packed_bits::Bits4>,
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3:5")]
+    values: [u8; 3],
+    #[packed_field(bits="54:55")]
+    flags: Flags,
+    #[packed_field(bytes="7")]
+    checksum: Checksum
+}
+
+#[test]
+fn test_get_fields() {
+    let frame = Frame {
+        mode: Mode::Sleep,
+        offset: (-3).into(),
+        length: 0x1234,
+        values: [1, 2, 3],
+        flags: Flags { ready: true, error: false },
+        checksum: Checksum(0xA5),
+        ..Default::default()
+    };
+
+    assert_eq!(Ok(Value::Enum("Sleep".into())), frame.get_field("mode"));
+    assert_eq!(Ok(Value::Integer(IntegerValue::Signed(-3))), frame.get_field("offset"));
+    assert_eq!(Ok(Value::from(0x1234u16)), frame.get_field("length"));
+    assert_eq!(Ok(Value::from(3u8)), frame.get_field("values[2]"));
+    assert_eq!(Ok(Value::Bool(true)), frame.get_field("flags.ready"));
+    assert_eq!(Ok(Value::Bytes(vec![0xA5])), frame.get_field("checksum"));
+
+    let flags = frame.get_field("flags").unwrap();
+    assert_eq!(Value::Nested(vec![("ready".into(), Value::Bool(true)), ("error".into(), Value::Bool(false))]), flags);
+    assert_eq!("{ ready: true, error: false }", flags.to_string());
+
+    let all = frame.to_value().unwrap();
+    assert_eq!(Some(&Value::from(2u8)), all.field("values[1]"));
+    assert_eq!(Some(&flags), all.field("flags"));
+}
+
+#[test]
+fn test_set_fields() {
+    let mut frame = Frame::default();
+
+    frame.set_field("mode", Value::from("Run")).unwrap();
+    frame.set_field("offset", Value::from(-8i8)).unwrap();
+    frame.set_field("length", Value::from(500u32)).unwrap();
+    frame.set_field("values[1]", Value::from(0x7Fu8)).unwrap();
+    frame.set_field("flags.error", Value::from(1u8)).unwrap();
+    frame.set_field("checksum", Value::Bytes(vec![0x3C])).unwrap();
+
+    assert_eq!(Frame {
+        mode: Mode::Run,
+        offset: (-8).into(),
+        length: 500,
+        values: [0, 0x7F, 0],
+        flags: Flags { ready: false, error: true },
+        checksum: Checksum(0x3C),
+        ..Default::default()
+    }, frame);
+
+    frame.set_field("mode", Value::from(2u8)).unwrap();
+    assert_eq!(Mode::Sleep, frame.mode);
+
+    frame.set_field("flags", Value::Nested(vec![("ready".into(), Value::Bool(true))])).unwrap();
+    assert_eq!(Flags { ready: true, error: true }, frame.flags);
+
+    frame.set_field("flags", Value::Bytes(vec![0])).unwrap();
+    assert_eq!(Flags::default(), frame.flags);
+}
+
+#[test]
+fn test_set_value_round_trip() {
+    let frame = Frame { mode: Mode::Run, length: 7, values: [9, 8, 7], ..Default::default() };
+    let mut copy = Frame::default();
+    copy.set_value(frame.to_value().unwrap()).unwrap();
+    assert_eq!(frame, copy);
+}
+
+#[test]
+fn test_errors() {
+    let mut frame = Frame::default();
+
+    assert_eq!(Err(PackingError::InvalidValue), frame.get_field("missing"));
+    assert_eq!(Err(PackingError::InvalidValue), frame.get_field("values[3]"));
+    assert_eq!(Err(PackingError::InvalidValue), frame.get_field("flags.missing"));
+    assert_eq!(Err(PackingError::InvalidValue), frame.get_field("length.low"));
+
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("offset", Value::from(8u8)));
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("offset", Value::from(-9i8)));
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("length", Value::from(-1i8)));
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("length", Value::from(0x10000u32)));
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("mode", Value::from("Walk")));
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("mode", Value::from(3u8)));
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("flags.ready", Value::from(2u8)));
+    assert_eq!(Err(PackingError::InvalidValue), frame.set_field("values[0]", Value::from("Run")));
+    assert_eq!(Err(PackingError::BufferSizeMismatch { expected: 1, actual: 2 }), frame.set_field("checksum", Value::Bytes(vec![1, 2])));
+
+    assert_eq!(Frame::default(), frame);
+}
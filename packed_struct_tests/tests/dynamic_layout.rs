@@ -0,0 +1,204 @@
+use packed_struct::prelude::*;
+use packed_struct::dynamic::{PackedDynamic, Value};
+use packed_struct::dynamic_layout::{DynamicField, DynamicLayout, DynamicLayoutBuilder};
+use packed_struct::layout::{BitNumbering, PackedStructLayout};
+use packed_struct::visit::IntegerEndianness;
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    Data = 1,
+    Ack = 2,
+    Nack = 3
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="5", endian="lsb")]
+pub struct Message {
+    #[packed_field(bits="39")]
+    urgent: bool,
+    #[packed_field(bits="37:36", ty="enum")]
+    kind: Kind,
+    #[packed_field(bits="35:32")]
+    offset: Integer<i8, packed_bits::Bits4>,
+    #[packed_field(bits="31:20")]
+    sequence: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="15:0")]
+    length: u16
+}
+
+fn message_layout() -> DynamicLayout {
+    DynamicLayoutBuilder::new("Message", 5, BitNumbering::Lsb0)
+        .with_field(DynamicField::new("urgent", 39, 39))
+        .with_field(DynamicField::new("kind", 37, 36).with_variant("Data", 1).with_variant("Ack", 2).with_variant("Nack", 3))
+        .with_field(DynamicField::new("offset", 35, 32).with_signed(true))
+        .with_field(DynamicField::new("sequence", 31, 20).with_endianness(IntegerEndianness::Lsb))
+        .with_field(DynamicField::new("length", 15, 0).with_endianness(IntegerEndianness::Lsb))
+        .build()
+        .unwrap()
+}
+
+fn assert_same_bits_as_derive(layout: &DynamicLayout) {
+    let messages = [
+        Message { urgent: true, kind: Kind::Ack, offset: (-3).into(), sequence: 0xABC.into(), length: 0x1234 },
+        Message { urgent: false, kind: Kind::Nack, offset: 7.into(), sequence: 0x00F.into(), length: 0xFF00 },
+        Message { urgent: true, kind: Kind::Data, offset: (-8).into(), sequence: 0xF00.into(), length: 1 },
+    ];
+
+    for message in &messages {
+        let packed = message.pack().unwrap();
+        let values = layout.unpack(&packed).unwrap();
+        // LSB integers that aren't whole bytes don't survive the round trip, same as with the derive
+        let unpacked = Message::unpack(&packed).unwrap();
+        assert_eq!(unpacked.to_value().unwrap(), values, "{:?}", message);
+        assert_eq!(packed.to_vec(), layout.pack(&values).unwrap(), "{:?}", message);
+    }
+}
+
+#[test]
+fn test_same_bits_as_derive() {
+    assert_same_bits_as_derive(&message_layout());
+}
+
+#[test]
+fn test_from_layout_json() {
+    let layout = DynamicLayout::from_json_str(&Message::layout_json()).unwrap();
+    assert_eq!("Message", layout.name());
+    assert_eq!(5, layout.size_bytes());
+    assert!(layout.field("offset").unwrap().signed);
+    assert_eq!((36, 37), layout.field("kind").unwrap().declared_bits);
+    assert_same_bits_as_derive(&layout);
+}
+
+#[test]
+fn test_unpack_values() {
+    let layout = DynamicLayoutBuilder::new("Sample", 3, BitNumbering::Msb0)
+        .with_field(DynamicField::new("flag", 0, 0))
+        .with_field(DynamicField::new("mode", 2, 3).with_variant("Off", 0))
+        .with_field(DynamicField::new("value", 8, 23))
+        .build()
+        .unwrap();
+
+    let values = layout.unpack(&[0xB0, 0x12, 0x34]).unwrap();
+    assert_eq!(Value::Nested(vec![
+        ("flag".into(), Value::Bool(true)),
+        ("mode".into(), Value::from(3u8)),
+        ("value".into(), Value::from(0x1234u16))
+    ]), values);
+
+    let partial = Value::Nested(vec![("value".into(), Value::from(0xABCDu16)), ("mode".into(), Value::from("Off"))]);
+    assert_eq!(vec![0x00, 0xAB, 0xCD], layout.pack(&partial).unwrap());
+}
+
+#[test]
+fn test_wide_fields_as_bytes() {
+    let layout = DynamicLayoutBuilder::new("Key", 17, BitNumbering::Msb0)
+        .with_field(DynamicField::new("version", 0, 3))
+        .with_field(DynamicField::new("key", 4, 135))
+        .build()
+        .unwrap();
+
+    let mut packed = vec![0; 17];
+    packed[0] = 0x1F;
+    packed[16] = 0x80;
+    let values = layout.unpack(&packed).unwrap();
+
+    let mut key = vec![0; 17];
+    key[0] = 0x0F;
+    key[16] = 0x80;
+    assert_eq!(Some(&Value::Bytes(key)), values.field("key"));
+    assert_eq!(packed, layout.pack(&values).unwrap());
+}
+
+#[test]
+fn test_pack_errors() {
+    let layout = message_layout();
+    let pack = |name: &'static str, value: Value| layout.pack(&Value::Nested(vec![(name.into(), value)]));
+
+    assert_eq!(Err(PackingError::InvalidValue), pack("missing", Value::from(1u8)));
+    assert_eq!(Err(PackingError::InvalidValue), pack("offset", Value::from(8u8)));
+    assert_eq!(Err(PackingError::InvalidValue), pack("offset", Value::from(-9i8)));
+    assert_eq!(Err(PackingError::InvalidValue), pack("sequence", Value::from(0x1000u16)));
+    assert_eq!(Err(PackingError::InvalidValue), pack("sequence", Value::from(-1i8)));
+    assert_eq!(Err(PackingError::InvalidValue), pack("kind", Value::from("Reset")));
+    assert_eq!(Err(PackingError::BufferSizeMismatch { expected: 2, actual: 1 }), pack("length", Value::Bytes(vec![1])));
+    assert_eq!(Err(PackingError::InvalidValue), layout.pack(&Value::from(1u8)));
+    assert_eq!(Err(PackingError::BufferSizeMismatch { expected: 5, actual: 4 }), layout.unpack(&[0; 4]));
+}
+
+#[test]
+fn test_build_errors() {
+    let overlap = DynamicLayoutBuilder::new("Overlap", 1, BitNumbering::Msb0)
+        .with_field(DynamicField::new("a", 0, 4))
+        .with_field(DynamicField::new("b", 4, 7));
+    assert_eq!(Err(PackingError::BitsError), overlap.build());
+
+    let outside = DynamicLayoutBuilder::new("Outside", 1, BitNumbering::Lsb0)
+        .with_field(DynamicField::new("a", 8, 0));
+    assert_eq!(Err(PackingError::BitsError), outside.build());
+
+    let duplicate = DynamicLayoutBuilder::new("Duplicate", 1, BitNumbering::Msb0)
+        .with_field(DynamicField::new("a", 0, 0))
+        .with_field(DynamicField::new("a", 1, 1));
+    assert_eq!(Err(PackingError::InvalidValue), duplicate.build());
+}
+
+#[test]
+fn test_from_json() {
+    let json = r#"{
+        "name": "Message",
+        "size_bytes": 5,
+        "bit_numbering": "lsb0",
+        "fields": [
+            { "name": "urgent", "declared_bits": [39, 39] },
+            { "name": "kind", "declared_bits": [37, 36], "variants": [
+                { "name": "Data", "value": 1 }, { "name": "Ack", "value": 2 }, { "name": "Nack", "value": 3 }
+            ] },
+            { "name": "offset", "declared_bits": [35, 32], "signed": true },
+            { "name": "sequence", "declared_bits": [31, 20], "endianness": "lsb" },
+            { "name": "length", "declared_bits": [15, 0], "endianness": "lsb" }
+        ]
+    }"#;
+
+    assert_eq!(message_layout(), DynamicLayout::from_json_str(json).unwrap());
+
+    let invalid = json.replace("[15, 0]", "[15, 20]");
+    assert!(DynamicLayout::from_json_str(&invalid).is_err());
+
+    let serialized = serde_json::to_string(&message_layout()).unwrap();
+    assert_eq!(message_layout(), DynamicLayout::from_json_str(&serialized).unwrap());
+}
+
+#[test]
+fn test_from_toml() {
+    let toml = r#"
+        name = "Message"
+        size_bytes = 5
+        bit_numbering = "lsb0"
+
+        [[fields]]
+        name = "urgent"
+        declared_bits = [39, 39]
+
+        [[fields]]
+        name = "kind"
+        declared_bits = [37, 36]
+        variants = [{ name = "Data", value = 1 }, { name = "Ack", value = 2 }, { name = "Nack", value = 3 }]
+
+        [[fields]]
+        name = "offset"
+        declared_bits = [35, 32]
+        signed = true
+
+        [[fields]]
+        name = "sequence"
+        declared_bits = [31, 20]
+        endianness = "lsb"
+
+        [[fields]]
+        name = "length"
+        declared_bits = [15, 0]
+        endianness = "lsb"
+    "#;
+
+    assert_eq!(message_layout(), DynamicLayout::from_toml_str(toml).unwrap());
+}
@@ -0,0 +1,158 @@
+use packed_struct::prelude::*;
+use packed_struct::hal::{I2cRegisterDevice, RegisterAddressBytes, RegisterDeviceError, SpiRegisterDevice};
+use packed_struct::register_map::RegisterError;
+
+use embedded_hal::i2c::ErrorKind;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct ControlRegister {
+    #[packed_field(bits="0")]
+    enabled: bool,
+    #[packed_field(bits="4:7")]
+    gain: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bytes="1")]
+    threshold: u8
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct InterruptRegister {
+    #[packed_field(bits="0", access="w1c")]
+    data_ready: bool,
+    #[packed_field(bits="7")]
+    enabled: bool
+}
+
+#[test]
+fn test_i2c_read_write() {
+    let expectations = [
+        I2cTransaction::write_read(0x48, vec![0xA0], vec![0x83, 0x10]),
+        I2cTransaction::transaction_start(0x48),
+        I2cTransaction::write(0x48, vec![0xA0]),
+        I2cTransaction::write(0x48, vec![0x02, 0x20]),
+        I2cTransaction::transaction_end(0x48),
+    ];
+    let mut sensor = I2cRegisterDevice::new(I2cMock::new(&expectations), 0x48);
+
+    let control: ControlRegister = sensor.read(0xA0).unwrap();
+    assert_eq!(ControlRegister { enabled: true, gain: 3.into(), threshold: 0x10 }, control);
+
+    sensor.write(0xA0, &ControlRegister { enabled: false, gain: 2.into(), threshold: 0x20 }).unwrap();
+
+    sensor.release().done();
+}
+
+#[test]
+fn test_i2c_modify_wide_address() {
+    let expectations = [
+        I2cTransaction::write_read(0x1D, vec![0x01, 0x20], vec![0x01, 0x10]),
+        I2cTransaction::transaction_start(0x1D),
+        I2cTransaction::write(0x1D, vec![0x01, 0x20]),
+        I2cTransaction::write(0x1D, vec![0x81, 0x10]),
+        I2cTransaction::transaction_end(0x1D),
+    ];
+    let mut sensor = I2cRegisterDevice::new(I2cMock::new(&expectations), 0x1D).with_register_address_bytes(RegisterAddressBytes::Two);
+
+    let written = sensor.modify(0x0120, |r: &mut ControlRegister| r.enabled = true).unwrap();
+    assert!(written.enabled);
+
+    sensor.release().done();
+}
+
+#[test]
+fn test_i2c_address_too_wide() {
+    // no transactions, the address isn't truncated to its low byte
+    let mut sensor = I2cRegisterDevice::new(I2cMock::new(&[]), 0x48);
+
+    let r: Result<ControlRegister, _> = sensor.read(0x1A0);
+    assert_eq!(Err(RegisterError::Bus(RegisterDeviceError::AddressTooWide { address: 0x1A0, address_bytes: RegisterAddressBytes::One })), r);
+
+    let mut sensor = I2cRegisterDevice::new(sensor.release(), 0x48).with_register_address_bytes(RegisterAddressBytes::Three);
+    let r = sensor.write(0x0100_0000, &ControlRegister { enabled: true, gain: 1.into(), threshold: 0 });
+    assert_eq!(Err(RegisterError::Bus(RegisterDeviceError::AddressTooWide { address: 0x0100_0000, address_bytes: RegisterAddressBytes::Three })), r);
+
+    sensor.release().done();
+}
+
+#[test]
+fn test_i2c_bus_error() {
+    let expectations = [
+        I2cTransaction::write_read(0x48, vec![0xA0], vec![0x00, 0x00]).with_error(ErrorKind::Other),
+    ];
+    let mut sensor = I2cRegisterDevice::new(I2cMock::new(&expectations), 0x48);
+
+    let r: Result<ControlRegister, _> = sensor.read(0xA0);
+    assert_eq!(Err(RegisterError::Bus(RegisterDeviceError::Bus(ErrorKind::Other))), r);
+
+    sensor.release().done();
+}
+
+#[test]
+fn test_spi_read_update() {
+    let expectations = [
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![0x8F]),
+        SpiTransaction::read_vec(vec![0x81]),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![0x0F]),
+        SpiTransaction::write_vec(vec![0x00]),
+        SpiTransaction::transaction_end(),
+    ];
+    let mut sensor = SpiRegisterDevice::new(SpiMock::new(&expectations));
+
+    // the pending data ready bit isn't written back as a one
+    let r = sensor.update(0x0F, |r: &mut InterruptRegister| r.enabled = false).unwrap();
+    assert_eq!(InterruptRegister { data_ready: true, enabled: false }, r);
+
+    sensor.release().done();
+}
+
+#[test]
+fn test_spi_wide_address() {
+    let expectations = [
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![0x81, 0x23, 0x45, 0x67]),
+        SpiTransaction::read_vec(vec![0x01]),
+        SpiTransaction::transaction_end(),
+    ];
+    let mut sensor = SpiRegisterDevice::new(SpiMock::new(&expectations)).with_register_address_bytes(RegisterAddressBytes::Four);
+
+    let r: InterruptRegister = sensor.read(0x0123_4567).unwrap();
+    assert!(r.enabled);
+
+    sensor.release().done();
+
+    let mut sensor = SpiRegisterDevice::new(SpiMock::new(&[])).with_register_address_bytes(RegisterAddressBytes::Two);
+    let r: Result<InterruptRegister, _> = sensor.read(0x1_0000);
+    assert_eq!(Err(RegisterError::Bus(RegisterDeviceError::AddressTooWide { address: 0x1_0000, address_bytes: RegisterAddressBytes::Two })), r);
+    sensor.release().done();
+}
+
+#[test]
+fn test_spi_flags() {
+    let expectations = [
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![0x60]),
+        SpiTransaction::read_vec(vec![0x04, 0x05]),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![0x20]),
+        SpiTransaction::write_vec(vec![0x85, 0x06]),
+        SpiTransaction::transaction_end(),
+    ];
+    let mut sensor = SpiRegisterDevice::new(SpiMock::new(&expectations)).with_flags(0x40, 0x00);
+
+    let mut control: ControlRegister = sensor.read(0x20).unwrap();
+    assert_eq!(ControlRegister { enabled: false, gain: 4.into(), threshold: 5 }, control);
+
+    control.enabled = true;
+    control.gain = 5.into();
+    control.threshold = 6;
+    sensor.write(0x20, &control).unwrap();
+
+    sensor.release().done();
+}
@@ -0,0 +1,75 @@
+use packed_struct::prelude::*;
+use packed_struct::register_map::MemoryBus;
+use packed_struct::{FieldAccess, PackedStructAccess};
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct InterruptStatus {
+    #[packed_field(bits="0", access="w1c")]
+    rx_done: bool,
+    #[packed_field(bits="1", access="w1c")]
+    tx_done: bool,
+    #[packed_field(bits="2:3", access="ro")]
+    fifo_level: Integer<u8, packed_bits::Bits2>,
+    #[packed_field(bits="4:6", access="rw")]
+    irq_enable: [bool; 3],
+    #[packed_field(bits="7", access="rc")]
+    overrun: bool,
+    #[packed_field(bits="8:15", access="wo")]
+    command: u8
+}
+
+#[test]
+fn test_access_masks() {
+    assert_eq!([0b11000000, 0], InterruptStatus::access_mask(FieldAccess::WriteOneToClear));
+    assert_eq!([0b00110000, 0], InterruptStatus::access_mask(FieldAccess::ReadOnly));
+    assert_eq!([0b00001110, 0], InterruptStatus::access_mask(FieldAccess::ReadWrite));
+    assert_eq!([0b00000001, 0], InterruptStatus::access_mask(FieldAccess::ReadToClear));
+    assert_eq!([0, 0xFF], InterruptStatus::access_mask(FieldAccess::WriteOnly));
+}
+
+#[test]
+fn test_pack_write() {
+    let current = [0b11110001, 0x00];
+    let mut status = InterruptStatus::unpack(&current).unwrap();
+    assert!(status.rx_done && status.tx_done && status.overrun);
+
+    status.irq_enable = [true, false, true];
+    status.fifo_level = 0.into();
+    status.overrun = false;
+    status.command = 0x5A;
+
+    // the pending w1c bits aren't written back, ro and rc bits keep the register's contents
+    assert_eq!([0b00111011, 0x5A], status.pack_write(&current).unwrap());
+    // plain packing is unchanged
+    assert_eq!([0b11001010, 0x5A], status.pack().unwrap());
+}
+
+#[test]
+fn test_clear_w1c() {
+    let status = InterruptStatus::unpack(&[0b11101000, 0x00]).unwrap();
+    assert_eq!([0b10101000, 0x00], status.clear_rx_done().unwrap());
+    assert_eq!([0b01101000, 0x00], status.clear_tx_done().unwrap());
+}
+
+#[test]
+fn test_update_register() {
+    let mut bus = MemoryBus::new(4);
+    bus.memory_mut()[2] = 0b11110001;
+
+    let status: InterruptStatus = bus.update_register(2, |s: &mut InterruptStatus| {
+        s.irq_enable = [false, true, false];
+        s.fifo_level = 1.into();
+    }).unwrap();
+    assert_eq!(1, *status.fifo_level);
+    assert_eq!([0b00110101, 0x00], bus.memory()[2..4]);
+}
+
+#[test]
+fn test_access_formatter() {
+    let status = InterruptStatus::unpack(&[0b11110001, 0x00]).unwrap();
+    let s = format!("{}", status);
+    assert!(s.contains("rx_done | w1c | bits   0:0"), "{}", s);
+    assert!(s.contains("fifo_level | ro  | bits   2:3"), "{}", s);
+    assert!(s.contains("command | wo  | bits   8:15"), "{}", s);
+}
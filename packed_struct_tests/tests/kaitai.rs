@@ -0,0 +1,82 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::PackedStructLayout;
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    Data = 1,
+    Ack = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="1")]
+pub struct Flags {
+    /// The "ready" flag
+    #[packed_field(bits="0")]
+    ready: bool,
+    #[packed_field(bits="7:4")]
+    priority: Integer<u8, packed_bits::Bits4>
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Header {
+    #[packed_field(bits="0:3", ty="enum")]
+    kind: Kind,
+    #[packed_field(bits="4:7")]
+    _reserved: ReservedOne<packed_bits::Bits4>,
+    /// Payload length, in bytes
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3:4")]
+    flags: [Flags; 2],
+    #[packed_field(bits="40:51", endian="msb")]
+    delta: Integer<i16, packed_bits::Bits12>,
+    #[packed_field(bits="56:79")]
+    address: Integer<u32, packed_bits::Bits24>
+}
+
+/// Signals of a CAN frame, positioned like in a DBC file.
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="8")]
+pub struct Frame {
+    #[packed_field(bits="0")]
+    active: bool,
+    /// Intel, starts at its least significant bit
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="23..=24", endian="msb")]
+    temperature: i16,
+    /// Motorola, starts at its most significant bit
+    #[packed_field(bits="35..=40", endian="msb")]
+    level: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="52..", endian="lsb")]
+    count: i8
+}
+
+// golden files, update them with the output of ksy() after checking the changes
+
+#[test]
+fn test_ksy() {
+    assert_eq!(include_str!("data/header.ksy"), Header::ksy());
+}
+
+#[test]
+fn test_ksy_little_endian_bits() {
+    assert_eq!(include_str!("data/frame.ksy"), Frame::ksy());
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Padded {
+    #[packed_field(bits="0:3")]
+    _high: ReservedBits<BitOne, packed_bits::Bits4>,
+    #[packed_field(bits="4:7")]
+    _low: ReservedBits<BitZero, packed_bits::Bits4>
+}
+
+#[test]
+fn test_ksy_reserved_fill() {
+    let ksy = Padded::ksy();
+    assert!(ksy.contains("  - id: high\n    type: b4\n    valid: 15\n"), "{}", ksy);
+    assert!(ksy.contains("  - id: low\n    type: b4\n    valid: 0\n"), "{}", ksy);
+}
@@ -0,0 +1,144 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::{BitNumbering, EnumVariantInfo, PackedStructLayout};
+use packed_struct::visit::IntegerEndianness;
+use packed_struct::FieldAccess;
+
+#[derive(PrimitiveEnum_i8, Debug, Copy, Clone, PartialEq)]
+pub enum Gain {
+    Low = -1,
+    Unity = 0,
+    High = 3
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Config {
+    /// Set when the configuration was applied.
+    ///
+    /// Cleared by writing one.
+    #[packed_field(bits="0", access="w1c")]
+    applied: bool,
+    #[packed_field(bits="1:3")]
+    _reserved: ReservedZero<packed_bits::Bits3>,
+    #[packed_field(bits="4:7", ty="enum")]
+    gain: EnumCatchAll<Gain>,
+    #[packed_field(bytes="1:2")]
+    threshold: u16,
+    #[packed_field(bytes="3:4", endian="msb")]
+    offset: Integer<i16, packed_bits::Bits16>,
+    #[packed_field(bytes="5:6")]
+    taps: [u8; 2]
+}
+
+const NUM_FIELDS: usize = Config::FIELDS.len();
+
+#[test]
+fn test_layout_fields() {
+    assert_eq!(7, NUM_FIELDS);
+    assert_eq!(56, Config::PACKED_BITS);
+    assert_eq!(BitNumbering::Msb0, Config::BIT_NUMBERING);
+
+    let names: Vec<_> = Config::FIELDS.iter().map(|f| f.name).collect();
+    assert_eq!(vec!["applied", "_reserved", "gain", "threshold", "offset", "taps[0]", "taps[1]"], names);
+
+    let applied = Config::field("applied").unwrap();
+    assert_eq!(0..0, applied.bits);
+    assert_eq!(0..0, applied.declared_bits);
+    assert_eq!(1, applied.width);
+    assert_eq!("bool", applied.type_name);
+    assert_eq!(FieldAccess::WriteOneToClear, applied.access);
+    assert_eq!("Set when the configuration was applied.\n\nCleared by writing one.", applied.doc);
+    assert_eq!(None, applied.reserved);
+
+    let reserved = Config::field("_reserved").unwrap();
+    assert_eq!(Some(false), reserved.reserved);
+    assert_eq!("ReservedZero<packed_bits::Bits3>", reserved.type_name);
+
+    let gain = Config::field("gain").unwrap();
+    assert_eq!(None, gain.endianness);
+    assert_eq!(&[
+        EnumVariantInfo { name: "Low", value: -1 },
+        EnumVariantInfo { name: "Unity", value: 0 },
+        EnumVariantInfo { name: "High", value: 3 },
+    ], gain.variants);
+
+    let threshold = Config::field("threshold").unwrap();
+    assert_eq!(8..23, threshold.bits);
+    assert_eq!(16, threshold.width);
+    assert_eq!(Some(IntegerEndianness::Lsb), threshold.endianness);
+    assert!(threshold.variants.is_empty());
+    assert_eq!("", threshold.doc);
+
+    assert_eq!(Some(IntegerEndianness::Msb), Config::field("offset").unwrap().endianness);
+    assert_eq!(48..55, Config::field("taps[1]").unwrap().bits);
+    assert_eq!("u8", Config::field("taps[1]").unwrap().type_name);
+    assert!(Config::field("taps").is_none());
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="2", endian="msb")]
+pub struct Lsb0Register {
+    #[packed_field(bits="15:12")]
+    id: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="11:0")]
+    value: Integer<u16, packed_bits::Bits12>
+}
+
+#[test]
+fn test_layout_lsb0() {
+    assert_eq!(BitNumbering::Lsb0, Lsb0Register::BIT_NUMBERING);
+
+    let id = Lsb0Register::field("id").unwrap();
+    assert_eq!(0..3, id.bits);
+    assert_eq!(12..15, id.declared_bits);
+
+    let value = Lsb0Register::field("value").unwrap();
+    assert_eq!(4..15, value.bits);
+    assert_eq!(0..11, value.declared_bits);
+    assert_eq!(Some(IntegerEndianness::Msb), value.endianness);
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Fills {
+    #[packed_field(bits="0:3")]
+    ones: ReservedBits<BitOne, packed_bits::Bits4>,
+    #[packed_field(bits="4:5")]
+    zeroes: ReservedZeroes<packed_bits::Bits2>,
+    #[packed_field(bits="6:7")]
+    last: ReservedOnes<packed_bits::Bits2>
+}
+
+#[test]
+fn test_layout_reserved_fill() {
+    assert_eq!(Some(true), Fills::field("ones").unwrap().reserved);
+    assert_eq!(Some(false), Fills::field("zeroes").unwrap().reserved);
+    assert_eq!(Some(true), Fills::field("last").unwrap().reserved);
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="3")]
+pub struct Wide {
+    #[packed_field(bits="19:0", endian="lsb")]
+    sample: Integer<u32, packed_bits::Bits20>
+}
+
+#[test]
+fn test_bit_map_lsb_partial_bytes() {
+    let bits: Vec<_> = Wide::field("sample").unwrap().bit_map(BitNumbering::Lsb0).collect();
+    assert_eq!(vec![
+        (3, 4), (2, 5), (1, 6), (0, 7),
+        (15, 8), (14, 9), (13, 10), (12, 11), (11, 12), (10, 13), (9, 14), (8, 15),
+        (23, 16), (22, 17), (21, 18), (20, 19), (19, 20), (18, 21), (17, 22), (16, 23)
+    ], bits);
+
+    // the value bits that aren't in the mapping don't survive packing
+    let packed = Wide { sample: 0xABCDE.into() }.pack().unwrap();
+    assert_eq!(0xABC0E, *Wide::unpack(&packed).unwrap().sample);
+
+    // every mapped bit is packed at its position
+    for (value_bit, position) in bits.iter().copied().filter(|&(v, _)| v < 20) {
+        let packed = Wide { sample: (1u32 << value_bit).into() }.pack().unwrap();
+        assert_eq!(0x80 >> (position % 8), packed[position / 8], "value bit {}", value_bit);
+    }
+}
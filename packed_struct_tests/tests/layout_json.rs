@@ -0,0 +1,140 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::{PackedStructLayout, StructLayout};
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    Data = 1,
+    Ack = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="1")]
+pub struct Flags {
+    /// The "ready" flag
+    #[packed_field(bits="0")]
+    ready: bool
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Header {
+    #[packed_field(bits="0:3", ty="enum")]
+    kind: Kind,
+    #[packed_field(bits="4:7")]
+    _reserved: ReservedZero<packed_bits::Bits4>,
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3")]
+    flags: Flags
+}
+
+#[test]
+fn test_layout_json() {
+    let expected = r#"{
+  "name": "Header",
+  "size_bytes": 4,
+  "size_bits": 32,
+  "bit_numbering": "msb0",
+  "fields": [
+    {
+      "name": "kind",
+      "bits": [0, 3],
+      "declared_bits": [0, 3],
+      "width": 4,
+      "endianness": null,
+      "signed": false,
+      "type": "Kind",
+      "access": "rw",
+      "reserved": false,
+      "doc": "",
+      "variants": [{"name": "Data", "value": 1}, {"name": "Ack", "value": 2}],
+      "nested": null
+    },
+    {
+      "name": "_reserved",
+      "bits": [4, 7],
+      "declared_bits": [4, 7],
+      "width": 4,
+      "endianness": null,
+      "signed": false,
+      "type": "ReservedZero<packed_bits::Bits4>",
+      "access": "rw",
+      "reserved": true,
+      "doc": "",
+      "variants": [],
+      "nested": null
+    },
+    {
+      "name": "length",
+      "bits": [8, 23],
+      "declared_bits": [8, 23],
+      "width": 16,
+      "endianness": "lsb",
+      "signed": false,
+      "type": "u16",
+      "access": "rw",
+      "reserved": false,
+      "doc": "",
+      "variants": [],
+      "nested": null
+    },
+    {
+      "name": "flags",
+      "bits": [24, 31],
+      "declared_bits": [24, 31],
+      "width": 8,
+      "endianness": null,
+      "signed": false,
+      "type": "Flags",
+      "access": "rw",
+      "reserved": false,
+      "doc": "",
+      "variants": [],
+      "nested": {
+        "name": "Flags",
+        "size_bytes": 1,
+        "size_bits": 8,
+        "bit_numbering": "lsb0",
+        "fields": [
+          {
+            "name": "ready",
+            "bits": [7, 7],
+            "declared_bits": [0, 0],
+            "width": 1,
+            "endianness": null,
+            "signed": false,
+            "type": "bool",
+            "access": "rw",
+            "reserved": false,
+            "doc": "The \"ready\" flag",
+            "variants": [],
+            "nested": null
+          }
+        ]
+      }
+    }
+  ]
+}"#;
+
+    assert_eq!(expected, Header::layout_json());
+}
+
+#[test]
+fn test_layout_json_parses() {
+    let json: serde_json::Value = serde_json::from_str(&Header::layout_json()).unwrap();
+    assert_eq!("Header", json["name"]);
+    assert_eq!(2, json["fields"][0]["variants"][1]["value"]);
+    assert_eq!("The \"ready\" flag", json["fields"][3]["nested"]["fields"][0]["doc"]);
+}
+
+#[test]
+fn test_nested_layout() {
+    assert!(Header::nested_layout("kind").is_none());
+    assert!(Header::nested_layout("missing").is_none());
+
+    let layout = StructLayout::of::<Header>();
+    let flags = layout.nested("flags").unwrap();
+    assert_eq!("Flags", flags.name);
+    assert_eq!(Flags::FIELDS, flags.fields);
+    assert_eq!(4, Header::PACKED_BYTES);
+}
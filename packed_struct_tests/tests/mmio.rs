@@ -0,0 +1,100 @@
+use packed_struct::prelude::*;
+use packed_struct::mmio::{AccessWidth, Mmio, WordOrder};
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="4")]
+pub struct TimerControl {
+    #[packed_field(bits="0")]
+    enable: bool,
+    #[packed_field(bits="1", access="w1c")]
+    overflow: bool,
+    #[packed_field(bits="15:8")]
+    prescaler: u8,
+    #[packed_field(bits="31:16", endian="msb")]
+    reload: u16
+}
+
+#[test]
+fn test_mmio_byte_access() {
+    let mut memory = vec![0u8; 4];
+    let reg = unsafe { Mmio::<TimerControl>::from_ptr(memory.as_mut_ptr()) };
+
+    reg.write(&TimerControl { enable: true, overflow: false, prescaler: 0x12, reload: 0xABCD }).unwrap();
+    assert_eq!(vec![0xAB, 0xCD, 0x12, 0x01], memory);
+
+    memory[3] = 0x03;
+    let read = reg.read().unwrap();
+    assert!(read.enable && read.overflow);
+    assert_eq!(0xABCD, read.reload);
+}
+
+#[test]
+fn test_mmio_word_access() {
+    let mut memory = vec![0u32; 2];
+    let reg = unsafe { Mmio::<TimerControl>::from_ptr(memory.as_mut_ptr() as *mut u8) }
+        .with_access_width(AccessWidth::Bits32).unwrap()
+        .with_word_order(WordOrder::Msb);
+
+    reg.write(&TimerControl { enable: true, overflow: false, prescaler: 0x12, reload: 0xABCD }).unwrap();
+    assert_eq!(0xABCD_1201, memory[0]);
+    assert_eq!(0, memory[1]);
+
+    memory[0] = 0x0001_0203;
+    let read = reg.modify(|r| r.prescaler += 1).unwrap();
+    assert_eq!(0x03, read.prescaler);
+    assert_eq!(0x0001_0303, memory[0]);
+
+    let reg = reg.with_word_order(WordOrder::Lsb);
+    assert_eq!([0x03, 0x03, 0x01, 0x00], reg.read_bytes());
+
+    let reg = reg.with_word_order(WordOrder::Memory);
+    assert_eq!(0x0001_0303_u32.to_ne_bytes(), reg.read_bytes());
+}
+
+#[test]
+fn test_mmio_half_word_access() {
+    let mut memory = vec![0u16; 2];
+    let reg = unsafe { Mmio::<TimerControl>::from_ptr(memory.as_mut_ptr() as *mut u8) }
+        .with_access_width(AccessWidth::Bits16).unwrap()
+        .with_word_order(WordOrder::Msb);
+
+    reg.write_bytes(&[0x11, 0x22, 0x33, 0x44]);
+    assert_eq!(vec![0x1122, 0x3344], memory);
+}
+
+#[test]
+fn test_mmio_update_w1c() {
+    let mut memory = vec![0u32];
+    let reg = unsafe { Mmio::<TimerControl>::from_ptr(memory.as_mut_ptr() as *mut u8) }
+        .with_access_width(AccessWidth::Bits32).unwrap()
+        .with_word_order(WordOrder::Msb);
+
+    memory[0] = 0x0000_0003;
+    reg.update(|r| r.prescaler = 0x40).unwrap();
+    // the pending overflow flag isn't written back
+    assert_eq!(0x0000_4001, memory[0]);
+}
+
+#[test]
+fn test_mmio_misaligned() {
+    let mut memory = vec![0u32; 2];
+    let ptr = unsafe { (memory.as_mut_ptr() as *mut u8).add(1) };
+    let reg = unsafe { Mmio::<TimerControl>::from_ptr(ptr) };
+    assert_eq!(
+        Err(PackingError::InvalidValue),
+        reg.with_access_width(AccessWidth::Bits32).map(|_| ())
+    );
+
+    #[derive(PackedStruct, Debug, PartialEq)]
+    pub struct ThreeBytes {
+        a: u8,
+        b: u8,
+        c: u8
+    }
+
+    let reg = unsafe { Mmio::<ThreeBytes>::from_ptr(memory.as_mut_ptr() as *mut u8) };
+    assert_eq!(
+        Err(PackingError::BufferModMismatch { actual_size: 3, modulo_required: 2 }),
+        reg.with_access_width(AccessWidth::Bits16).map(|_| ())
+    );
+}
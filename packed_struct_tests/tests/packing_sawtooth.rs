@@ -0,0 +1,78 @@
+use packed_struct::prelude::*;
+use packed_struct::dynamic::PackedDynamic;
+use packed_struct::dynamic_layout::{DynamicField, DynamicLayout, DynamicLayoutBuilder};
+use packed_struct::layout::{BitNumbering, PackedStructLayout};
+use packed_struct::visit::IntegerEndianness;
+
+/// Signals of a CAN frame, positioned like in a DBC file.
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="8")]
+pub struct Frame {
+    #[packed_field(bits="0")]
+    active: bool,
+    /// Intel, starts at its least significant bit
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>,
+    /// Motorola, starts at its most significant bit
+    #[packed_field(bits="23..=24", endian="msb")]
+    temperature: i16,
+    #[packed_field(bits="35..=40", endian="msb")]
+    level: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="52..", endian="lsb")]
+    count: u8
+}
+
+const PACKED: [u8; 8] = [0xC1, 0xAB, 0xFF, 0xFE, 0x01, 0x23, 0xA0, 0x05];
+
+fn frame() -> Frame {
+    Frame {
+        active: true,
+        speed: 0xABC.into(),
+        temperature: -2,
+        level: 0x123.into(),
+        count: 0x5A
+    }
+}
+
+#[test]
+fn test_sawtooth_packing() {
+    assert_eq!(PACKED, frame().pack().unwrap());
+    assert_eq!(frame(), Frame::unpack(&PACKED).unwrap());
+}
+
+#[test]
+fn test_sawtooth_layout() {
+    assert_eq!(BitNumbering::Sawtooth, Frame::BIT_NUMBERING);
+
+    let speed = Frame::field("speed").unwrap();
+    assert_eq!(4..15, speed.declared_bits);
+    assert_eq!(0..15, speed.bits);
+    assert_eq!(Some(IntegerEndianness::Lsb), speed.endianness);
+
+    let level = Frame::field("level").unwrap();
+    assert_eq!(35..40, level.declared_bits);
+    assert_eq!(36..47, level.bits);
+    assert_eq!(12, level.width);
+
+    assert_eq!(52..59, Frame::field("count").unwrap().declared_bits);
+}
+
+#[test]
+fn test_sawtooth_dynamic_layout() {
+    let layout = DynamicLayoutBuilder::new("Frame", 8, BitNumbering::Sawtooth)
+        .with_field(DynamicField::new("active", 0, 0))
+        .with_field(DynamicField::new("speed", 4, 15).with_endianness(IntegerEndianness::Lsb))
+        .with_field(DynamicField::new("temperature", 23, 24).with_signed(true))
+        .with_field(DynamicField::new("level", 35, 40))
+        .with_field(DynamicField::new("count", 52, 59).with_endianness(IntegerEndianness::Lsb))
+        .build()
+        .unwrap();
+
+    let values = layout.unpack(&PACKED).unwrap();
+    assert_eq!(frame().to_value().unwrap(), values);
+    assert_eq!(PACKED.to_vec(), layout.pack(&values).unwrap());
+
+    let from_json = DynamicLayout::from_json_str(&Frame::layout_json()).unwrap();
+    assert_eq!(values, from_json.unpack(&PACKED).unwrap());
+    assert_eq!(PACKED.to_vec(), from_json.pack(&values).unwrap());
+}
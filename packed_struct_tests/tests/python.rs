@@ -0,0 +1,161 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::{PackedStructLayout, StructLayout};
+use packed_struct::python::PythonModule;
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    Data = 1,
+    Ack = 2
+}
+
+#[derive(PrimitiveEnum_i8, Debug, Copy, Clone, PartialEq)]
+pub enum Trim {
+    Down = -1,
+    None = 0,
+    Up = 1
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="1")]
+pub struct Flags {
+    /// The "ready" flag
+    #[packed_field(bits="0")]
+    ready: bool,
+    #[packed_field(bits="7:4")]
+    priority: Integer<u8, packed_bits::Bits4>
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Header {
+    #[packed_field(bits="0:3", ty="enum")]
+    kind: Kind,
+    #[packed_field(bits="4:7")]
+    _reserved: ReservedOne<packed_bits::Bits4>,
+    /// Payload length, in bytes
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3:4")]
+    flags: [Flags; 2],
+    #[packed_field(bits="40:51", endian="msb")]
+    delta: Integer<i16, packed_bits::Bits12>,
+    #[packed_field(bits="52:53", ty="enum")]
+    trim: Trim,
+    #[packed_field(bits="54:55", ty="enum")]
+    mode: EnumCatchAll<Kind>,
+    #[packed_field(bits="56:79")]
+    address: Integer<u32, packed_bits::Bits24>,
+    #[packed_field(bytes="10:12")]
+    from: [u8; 3]
+}
+
+/// Signals of a CAN frame, positioned like in a DBC file.
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="8")]
+pub struct Frame {
+    #[packed_field(bits="0")]
+    active: bool,
+    /// Intel, starts at its least significant bit
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="23..=24", endian="msb")]
+    temperature: i16,
+    /// Motorola, starts at its most significant bit
+    #[packed_field(bits="35..=40", endian="msb")]
+    level: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="52..", endian="lsb")]
+    count: i8
+}
+
+fn header() -> Header {
+    Header {
+        kind: Kind::Ack,
+        _reserved: Default::default(),
+        length: 0x1234,
+        flags: [Flags { ready: true, priority: 9.into() }, Flags { ready: false, priority: 3.into() }],
+        delta: (-300).into(),
+        trim: Trim::Down,
+        mode: EnumCatchAll::CatchAll(3),
+        address: 0xABCDEF.into(),
+        from: [1, 2, 3]
+    }
+}
+
+fn frame() -> Frame {
+    Frame { active: true, speed: 0xABC.into(), temperature: -2, level: 0x123.into(), count: -90 }
+}
+
+// golden files, update them with the output of the modules after checking
+// the changes, and run them with python3 to check the test vectors
+
+#[test]
+fn test_python() {
+    let python = PythonModule::new(StructLayout::of::<Header>())
+        .with_test_vector(&header()).unwrap()
+        .with_test_vector(&Header { flags: [Flags { ready: true, priority: 15.into() }; 2], mode: EnumCatchAll::Enum(Kind::Data), ..header() }).unwrap()
+        .to_python();
+    assert_eq!(include_str!("data/header.py"), python);
+}
+
+#[test]
+fn test_python_sawtooth() {
+    let python = PythonModule::new(StructLayout::of::<Frame>())
+        .with_test_vector(&frame()).unwrap()
+        .with_test_vector(&Frame { active: false, speed: 1.into(), temperature: i16::MIN, level: 0xFFF.into(), count: 127 }).unwrap()
+        .to_python();
+    assert_eq!(include_str!("data/frame.py"), python);
+}
+
+#[test]
+fn test_python_without_test_vectors() {
+    let python = Flags::python();
+    assert!(python.starts_with("# Packing of the Flags packed structure. Generated, don't edit.\n\nfrom dataclasses import dataclass\n\n\n@dataclass\nclass Flags:\n"));
+    assert!(!python.contains("TEST_VECTORS"));
+}
+
+#[test]
+fn test_python_test_vector_size() {
+    let result = PythonModule::new(StructLayout::of::<Flags>()).with_test_vector(&header());
+    assert_eq!(Some(PackingError::BufferSizeMismatch { expected: 1, actual: 13 }), result.err());
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Padded {
+    #[packed_field(bits="0:3")]
+    _high: ReservedBits<BitOne, packed_bits::Bits4>,
+    #[packed_field(bits="4:7")]
+    value: Integer<u8, packed_bits::Bits4>
+}
+
+#[test]
+fn test_python_reserved_fill() {
+    let python = PythonModule::new(StructLayout::of::<Padded>())
+        .with_test_vector(&Padded { _high: Default::default(), value: 5.into() }).unwrap()
+        .to_python();
+    assert!(python.contains("        n = 0\n        n |= 0xF0\n        n |= self.value & 0xF\n"), "{}", python);
+    assert!(python.contains("bytes.fromhex(\"f5\")"), "{}", python);
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="4")]
+pub struct Shadowing {
+    #[packed_field(bits="3:0")]
+    field: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="7:4")]
+    int: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="15:8")]
+    flags: [Flags; 1],
+    #[packed_field(bits="31:16", endian="lsb")]
+    sample: Integer<u16, packed_bits::Bits12>
+}
+
+#[test]
+fn test_python_shadowed_names() {
+    let python = PythonModule::new(StructLayout::of::<Shadowing>())
+        .with_test_vector(&Shadowing { field: 1.into(), int: 2.into(), flags: [Flags { ready: true, priority: 3.into() }], sample: 0xABC.into() }).unwrap()
+        .to_python();
+    assert!(python.contains("    field_: int = 0\n    int_: int = 0\n    flags: List[Flags] = field(default_factory="), "{}", python);
+    assert!(python.contains("        n |= self.field_ & 0xF\n"), "{}", python);
+    assert!(python.contains("Shadowing(field_=1, int_=2, flags=[Flags(ready=True, priority=3)], sample=2748)"), "{}", python);
+}
@@ -0,0 +1,161 @@
+use packed_struct::prelude::*;
+use packed_struct::register_map::{BusTransaction, MemoryBus, RegisterMap};
+use packed_struct::FieldAccess;
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct ControlRegister {
+    #[packed_field(bits="0:1", ty="enum")]
+    pub power_mode: PowerMode,
+    #[packed_field(bits="3:7")]
+    pub voltage_milli_volts: Integer<u8, packed_bits::Bits5>,
+    #[packed_field(bits="8")]
+    pub standby_led_enabled: bool,
+    #[packed_field(bits="9:12")]
+    pub gain_stages: [bool; 4],
+    #[packed_field(bits="13:15")]
+    pub _reserved: ReservedOnes<packed_bits::Bits3>,
+    #[packed_field(bits="16:31", endian="lsb")]
+    pub sensor_value: i16
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct StatusRegister {
+    #[packed_field(bits="0")]
+    pub data_ready: bool,
+    #[packed_field(bits="1")]
+    pub overflow: bool,
+    #[packed_field(bits="4:7")]
+    pub sample_count: Integer<u8, packed_bits::Bits4>
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+pub struct CommandRegister {
+    pub opcode: u8
+}
+
+#[derive(PrimitiveEnum, Debug, Copy, Clone, PartialEq)]
+pub enum PowerMode {
+    Off = 0,
+    Standby = 1,
+    LowPower = 2,
+    On = 3
+}
+
+#[derive(RegisterMap, Debug, Copy, Clone, PartialEq)]
+pub struct SensorRegisters {
+    #[register(address=0xA0, access="rw")]
+    pub control: ControlRegister,
+    #[register(address=0xA4, access="ro")]
+    pub status: StatusRegister
+}
+
+#[derive(RegisterMap, Debug, Copy, Clone, PartialEq)]
+pub struct CommandRegisters {
+    #[register(address=0x10, access="ro")]
+    pub status: StatusRegister,
+    #[register(address=0x11, access="wo")]
+    pub command: CommandRegister,
+    #[register(address=0x12)]
+    pub threshold: CommandRegister
+}
+
+#[test]
+fn test_register_map() {
+    let mut bus = MemoryBus::new(0x100);
+    bus.memory_mut()[0xA0..0xA4].copy_from_slice(&[0x8B, 0xE7, 0x21, 0xFA]);
+    bus.memory_mut()[0xA4] = 0xC5;
+
+    let control = SensorRegisters::read_control(&mut bus).unwrap();
+    assert_eq!(PowerMode::LowPower, control.power_mode);
+    assert_eq!(-1503, control.sensor_value);
+
+    SensorRegisters::modify_control(&mut bus, |r| r.power_mode = PowerMode::On).unwrap();
+    assert_eq!(&[0xCB, 0xE7, 0x21, 0xFA], &bus.memory()[0xA0..0xA4]);
+    assert_eq!(BusTransaction::Write { address: 0xA0, bytes: vec![0xCB, 0xE7, 0x21, 0xFA] }, bus.transactions()[2]);
+
+    let all = SensorRegisters::read_all(&mut bus).unwrap();
+    assert_eq!(PowerMode::On, all.control.power_mode);
+    assert_eq!(StatusRegister { data_ready: true, overflow: true, sample_count: 5.into() }, all.status);
+
+    assert_eq!(0xA0, SensorRegisters::CONTROL_ADDRESS);
+    assert_eq!(0xA4, SensorRegisters::STATUS_ADDRESS);
+    assert_eq!(2, SensorRegisters::REGISTERS.len());
+    assert_eq!(4, SensorRegisters::REGISTERS[0].size_bytes);
+    assert_eq!(FieldAccess::ReadOnly, SensorRegisters::REGISTERS[1].access);
+}
+
+#[test]
+fn test_write_all_skips_read_only() {
+    let mut bus = MemoryBus::new(0x100);
+    bus.memory_mut()[0xA4] = 0xC5;
+
+    let registers = SensorRegisters {
+        control: ControlRegister::unpack(&[0x8B, 0xE7, 0x21, 0xFA]).unwrap(),
+        status: StatusRegister { data_ready: false, overflow: false, sample_count: 0.into() }
+    };
+    registers.write_all(&mut bus).unwrap();
+
+    assert_eq!(&[BusTransaction::Write { address: 0xA0, bytes: vec![0x8B, 0xE7, 0x21, 0xFA] }], bus.transactions());
+    assert_eq!(0xC5, bus.memory()[0xA4]);
+
+    let registers = CommandRegisters {
+        status: StatusRegister { data_ready: true, overflow: true, sample_count: 15.into() },
+        command: CommandRegister { opcode: 0x5A },
+        threshold: CommandRegister { opcode: 0x33 }
+    };
+    bus.clear_transactions();
+    registers.write_all(&mut bus).unwrap();
+    assert_eq!(&[
+        BusTransaction::Write { address: 0x11, bytes: vec![0x5A] },
+        BusTransaction::Write { address: 0x12, bytes: vec![0x33] },
+    ], bus.transactions());
+    assert_eq!(&[0x00, 0x5A, 0x33], &bus.memory()[0x10..0x13]);
+}
+
+// Stand-ins with the names of the generated functions. An inherent function takes
+// precedence over a trait's, so these are only called when the register map lacks them.
+struct Missing;
+
+trait MissingFunctions {
+    fn read_command(_: &mut MemoryBus) -> Missing { Missing }
+    fn write_status(_: &mut MemoryBus, _: &StatusRegister) -> Missing { Missing }
+    fn modify_status(_: &mut MemoryBus, _: fn(&mut StatusRegister)) -> Missing { Missing }
+    fn modify_command(_: &mut MemoryBus, _: fn(&mut CommandRegister)) -> Missing { Missing }
+    fn read_all(_: &mut MemoryBus) -> Missing { Missing }
+}
+
+impl MissingFunctions for CommandRegisters {}
+
+#[test]
+fn test_access_limits_functions() {
+    let mut bus = MemoryBus::new(0x20);
+    let status = StatusRegister { data_ready: true, overflow: false, sample_count: 1.into() };
+
+    let Missing = CommandRegisters::read_command(&mut bus);
+    let Missing = CommandRegisters::write_status(&mut bus, &status);
+    let Missing = CommandRegisters::modify_status(&mut bus, |_| ());
+    let Missing = CommandRegisters::modify_command(&mut bus, |_| ());
+    let Missing = CommandRegisters::read_all(&mut bus);
+
+    // the functions that are generated still work
+    CommandRegisters::read_status(&mut bus).unwrap();
+    CommandRegisters::write_command(&mut bus, &CommandRegister { opcode: 1 }).unwrap();
+    CommandRegisters::modify_threshold(&mut bus, |t| t.opcode = 2).unwrap();
+    assert!(!bus.transactions().is_empty());
+    assert!(bus.transactions().iter().all(|t| match t {
+        BusTransaction::Read { address, .. } => *address != 0x11,
+        BusTransaction::Write { address, .. } => *address != 0x10,
+    }));
+}
+
+#[test]
+fn test_register_info_access() {
+    let access: Vec<_> = CommandRegisters::REGISTERS.iter().map(|r| r.access).collect();
+    assert_eq!(vec![FieldAccess::ReadOnly, FieldAccess::WriteOnly, FieldAccess::ReadWrite], access);
+    assert!(CommandRegisters::REGISTERS[0].access.is_readable());
+    assert!(!CommandRegisters::REGISTERS[0].access.is_writable());
+    assert!(!CommandRegisters::REGISTERS[1].access.is_readable());
+    assert!(CommandRegisters::REGISTERS[2].access.is_writable());
+}
@@ -0,0 +1,98 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::PackedStructLayout;
+use packed_struct::FieldAccess;
+
+#[allow(dead_code)]
+mod device {
+    include!(concat!(env!("OUT_DIR"), "/device.rs"));
+}
+
+use device::timer0::{Cc, Ctrl, CtrlMode, CtrlPolarity, EvtRoute, Status};
+
+#[test]
+fn test_register_bits() {
+    let ctrl = Ctrl {
+        en: true,
+        mode: EnumCatchAll::Enum(CtrlMode::Periodic),
+        prescaler: 0x3F,
+        intflag: false,
+        polarity: CtrlPolarity::High
+    };
+    assert_eq!(0x0002_3F05u32.to_be_bytes(), ctrl.pack().unwrap());
+
+    let reset = Ctrl::unpack(&Ctrl::RESET_VALUE.to_be_bytes()).unwrap();
+    assert_eq!(0x0A, reset.prescaler);
+    assert_eq!(EnumCatchAll::Enum(CtrlMode::Off), reset.mode);
+
+    let unknown_mode = Ctrl::unpack(&0x0000_000Cu32.to_be_bytes()).unwrap();
+    assert_eq!(EnumCatchAll::CatchAll(6), unknown_mode.mode);
+
+    let status = Status::unpack(&0x8ABCu16.to_be_bytes()).unwrap();
+    assert_eq!(0xABC, *status.count);
+    assert!(status.running);
+    assert!(!status.overrun);
+}
+
+#[test]
+fn test_register_metadata() {
+    assert_eq!(0x4000_1000, device::timer0::BASE_ADDRESS);
+    assert_eq!(0x4000_2000, device::timer1::BASE_ADDRESS);
+    assert_eq!(0x4, Status::ADDRESS_OFFSET);
+    assert_eq!(0x24, EvtRoute::ADDRESS_OFFSET);
+    assert_eq!((0x10, 4, 4), (Cc::ADDRESS_OFFSET, Cc::DIM, Cc::DIM_INCREMENT));
+
+    assert_eq!("Clock divider, minus one", Ctrl::field("prescaler").unwrap().doc);
+    assert_eq!(FieldAccess::WriteOneToClear, Ctrl::field("intflag").unwrap().access);
+    assert_eq!(FieldAccess::ReadToClear, Status::field("overrun").unwrap().access);
+    assert_eq!(FieldAccess::ReadOnly, Status::field("running").unwrap().access);
+    assert_eq!(FieldAccess::ReadWrite, Ctrl::field("en").unwrap().access);
+
+    // the derived peripheral shares the register structures
+    let _: device::timer1::Ctrl = Ctrl::unpack(&[0; 4]).unwrap();
+}
+
+#[test]
+fn test_register_mmio() {
+    let mut memory = vec![0u32; 16];
+    let base = memory.as_mut_ptr() as usize;
+
+    unsafe {
+        Ctrl::mmio(base).unwrap().modify(|c| c.prescaler = 0x12).unwrap();
+        Cc::mmio(base, 2).unwrap().write(&Cc { type_: 0xDEAD_BEEF }).unwrap();
+        assert_eq!(Err(PackingError::InvalidValue), Cc::mmio(base, 4).map(|_| ()));
+    }
+
+    assert_eq!(0x1200, memory[0]);
+    assert_eq!(0xDEAD_BEEF, memory[4 + 2]);
+}
+
+#[test]
+fn test_generate_errors() {
+    use packed_struct_build::svd::generate_str;
+    use packed_struct_build::Error;
+
+    assert!(matches!(generate_str("<device"), Err(Error::Xml(_))));
+    assert!(matches!(generate_str("<peripherals/>"), Err(Error::Invalid(_))));
+
+    let missing_address = "<device><name>D</name><peripherals><peripheral><name>P</name></peripheral></peripherals></device>";
+    assert!(matches!(generate_str(missing_address), Err(Error::Invalid(_))));
+
+    let field = |field: &str| generate_str(&format!(
+        "<device><name>D</name><peripherals><peripheral><name>P</name><baseAddress>0</baseAddress><registers>\
+            <register><name>CTRL</name><addressOffset>0</addressOffset><size>32</size><fields><field>{}</field></fields></register>\
+        </registers></peripheral></peripherals></device>", field));
+    let invalid = |xml: &str| match field(xml) {
+        Err(Error::Invalid(msg)) => msg,
+        other => panic!("{:?}", other.map(|_| ()))
+    };
+
+    assert!(field("<name>EN</name><bitOffset>0</bitOffset><bitWidth>1</bitWidth>").is_ok());
+    assert_eq!("field EN of register CTRL has a width of 0 bits", invalid("<name>EN</name><bitOffset>4</bitOffset><bitWidth>0</bitWidth>"));
+    assert_eq!("field EN of register CTRL has its msb 2 below its lsb 5", invalid("<name>EN</name><bitRange>[2:5]</bitRange>"));
+    assert_eq!("field EN of register CTRL has its msb 0 below its lsb 1", invalid("<name>EN</name><lsb>1</lsb><msb>0</msb>"));
+    assert_eq!("value 0x4 of field MODE of register CTRL doesn't fit in 2 bits", invalid(
+        "<name>MODE</name><bitOffset>0</bitOffset><bitWidth>2</bitWidth><enumeratedValues>\
+            <enumeratedValue><name>A</name><value>3</value></enumeratedValue>\
+            <enumeratedValue><name>B</name><value>4</value></enumeratedValue>\
+        </enumeratedValues>"));
+}
@@ -0,0 +1,54 @@
+use packed_struct::prelude::*;
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="msb")]
+pub struct Frame {
+    #[packed_field(bits="0")]
+    valid: bool,
+    #[packed_field(bits="4:7")]
+    kind: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3")]
+    crc: u8
+}
+
+#[test]
+fn test_hex_round_trip() {
+    let frame = Frame::from_hex_str("8B E7 21 FA").unwrap();
+    assert_eq!(Frame { valid: true, kind: 0xB.into(), length: 0xE721, crc: 0xFA }, frame);
+    assert_eq!("8B E7 21 FA", frame.to_hex_string().unwrap());
+}
+
+#[test]
+fn test_hex_tolerated_formats() {
+    let expected = Frame::from_hex_str("8B E7 21 FA").unwrap();
+
+    for s in &["8be721fa", "0x8B, 0xE7, 0x21, 0xFA", "0X8BE7_21FA", "  8B\tE7\n21 FA  ", "8BE7 21FA"] {
+        assert_eq!(expected, Frame::from_hex_str(s).unwrap(), "{}", s);
+    }
+}
+
+#[test]
+fn test_hex_errors() {
+    assert_eq!(Err(PackingError::BufferSizeMismatch { expected: 4, actual: 3 }), Frame::from_hex_str("8B E7 21"));
+    assert_eq!(Err(PackingError::BufferSizeMismatch { expected: 4, actual: 5 }), Frame::from_hex_str("8B E7 21 FA 00"));
+    assert_eq!(Err(PackingError::InvalidValue), Frame::from_hex_str("8B E7 21 FG"));
+    assert_eq!(Err(PackingError::InvalidValue), Frame::from_hex_str("8B E7 21 F A"));
+}
+
+#[test]
+fn test_bit_string_round_trip() {
+    let frame = Frame { valid: false, kind: 3.into(), length: 0x0102, crc: 0x80 };
+    let bits = frame.to_bit_string().unwrap();
+    assert_eq!("0b0000_0011 0b0000_0001 0b0000_0010 0b1000_0000", bits);
+    assert_eq!(frame, Frame::from_bit_str(&bits).unwrap());
+    assert_eq!(frame, Frame::from_bit_str("00000011_00000001 0000001010000000").unwrap());
+}
+
+#[test]
+fn test_bit_string_errors() {
+    assert_eq!(Err(PackingError::BufferSizeMismatch { expected: 32, actual: 31 }),
+        Frame::from_bit_str("0b0000_0011 0b0000_0001 0b0000_0010 0b1000_000"));
+    assert_eq!(Err(PackingError::InvalidValue), Frame::from_bit_str("0b0000_0012"));
+}
@@ -0,0 +1,164 @@
+use packed_struct::prelude::*;
+use packed_struct::debug_fmt::PackedStructFmt;
+use packed_struct::visit::{IntegerValue, PackedStructVisit, VisitField, Visitor};
+
+use std::fmt::Write;
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Idle = 0,
+    Active = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0")]
+pub struct Flags {
+    #[packed_field(bits="4")]
+    ready: bool,
+    #[packed_field(bits="5:7", ty="enum")]
+    mode: Mode
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Packet {
+    #[packed_field(bits="0:3")]
+    version: Integer<u8, packed_bits::Bits4>,
+    #[packed_field(bits="4:7")]
+    flags: Flags,
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3:4", endian="msb")]
+    offset: i16,
+    #[packed_field(bytes="5:6")]
+    payload: [u8; 2]
+}
+
+fn packet() -> Packet {
+    Packet {
+        version: 3.into(),
+        flags: Flags { ready: true, mode: Mode::Active },
+        length: 0x0102,
+        offset: -2,
+        payload: [0xAA, 0x55]
+    }
+}
+
+/// Writes one CSV line per field.
+struct Csv(String);
+
+impl Visitor for Csv {
+    type Error = std::fmt::Error;
+
+    fn visit_field(&mut self, field: &VisitField) -> Result<(), Self::Error> {
+        field.write_path(&mut self.0)?;
+        write!(self.0, ",{},{},{:?},{},", field.bits.start, field.bits.end, field.endianness, field.raw_bits())?;
+        match field.integer {
+            Some(i) => write!(self.0, "{}", i)?,
+            None => write!(self.0, "{:?}", field.value)?
+        }
+        self.0.push('\n');
+        Ok(())
+    }
+}
+
+#[test]
+fn test_visit_csv() {
+    let mut csv = Csv(String::new());
+    packet().visit(&mut csv).unwrap();
+
+    assert_eq!(vec![
+        "version,0,3,None,0b0011,3",
+        "flags,4,7,None,0b1010,Flags { ready: true, mode: Active }",
+        "flags.ready,4,4,None,0b1,1",
+        "flags.mode,5,7,None,0b010,2",
+        "length,8,23,Some(Lsb),0b0000001000000001,258",
+        "offset,24,39,Some(Msb),0b1111111111111110,-2",
+        "payload[0],40,47,None,0b10101010,170",
+        "payload[1],48,55,None,0b01010101,85",
+    ], csv.0.lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_visit_integer_values() {
+    struct Integers(Vec<(&'static str, Option<IntegerValue>, Option<u128>)>);
+
+    impl Visitor for Integers {
+        type Error = PackingError;
+
+        fn visit_field(&mut self, field: &VisitField) -> Result<(), PackingError> {
+            self.0.push((field.name, field.integer, field.raw_value()));
+            Ok(())
+        }
+    }
+
+    let mut integers = Integers(vec![]);
+    packet().visit(&mut integers).unwrap();
+
+    assert_eq!(("flags", None, Some(0b1010)), integers.0[1]);
+    assert_eq!(("offset", Some(IntegerValue::Signed(-2)), Some(0xFFFE)), integers.0[5]);
+    assert_eq!(-2, integers.0[5].1.unwrap().as_i128());
+}
+
+#[test]
+fn test_visit_nested_events() {
+    struct Events(Vec<String>);
+
+    impl Visitor for Events {
+        type Error = PackingError;
+
+        fn visit_field(&mut self, field: &VisitField) -> Result<(), PackingError> {
+            self.0.push(format!("{}{}", " ".repeat(field.depth), field.name));
+            Ok(())
+        }
+
+        fn enter_nested(&mut self, field: &VisitField) -> Result<(), PackingError> {
+            self.0.push(format!("enter {}", field.name));
+            Ok(())
+        }
+
+        fn leave_nested(&mut self, field: &VisitField) -> Result<(), PackingError> {
+            self.0.push(format!("leave {}", field.name));
+            Ok(())
+        }
+    }
+
+    let mut events = Events(vec![]);
+    packet().visit(&mut events).unwrap();
+
+    assert_eq!(vec![
+        "version", "flags", "enter flags", " ready", " mode", "leave flags",
+        "length", "offset", "payload[0]", "payload[1]"
+    ], events.0);
+}
+
+#[test]
+fn test_visit_stops_on_error() {
+    struct FirstTwo(usize);
+
+    impl Visitor for FirstTwo {
+        type Error = &'static str;
+
+        fn visit_field(&mut self, _field: &VisitField) -> Result<(), &'static str> {
+            self.0 += 1;
+            if self.0 == 2 { Err("full") } else { Ok(()) }
+        }
+    }
+
+    let packed = packet().pack().unwrap();
+    let ctx = packed_struct::visit::VisitContext::new(&packed);
+    let mut visitor = FirstTwo(0);
+    assert_eq!(Err("full"), packet().visit_fields(&ctx, None, &mut visitor));
+    assert_eq!(2, visitor.0);
+}
+
+#[test]
+fn test_write_fields_nested_paths() {
+    let mut s = String::new();
+    packet().write_fields(&mut s).unwrap();
+
+    let lines: Vec<_> = s.split("\r\n").collect();
+    assert_eq!("     version | bits   0:3   | 0b0011             | 3", lines[0]);
+    assert_eq!(" flags.ready | bits   4:4   | 0b1                | true", lines[2]);
+    assert_eq!("  flags.mode | bits   5:7   | 0b010              | Active", lines[3]);
+}
@@ -0,0 +1,62 @@
+use packed_struct::prelude::*;
+use packed_struct::layout::StructLayout;
+use packed_struct::wireshark::LuaDissector;
+
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    Data = 1,
+    Ack = 2
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="1")]
+pub struct Flags {
+    /// The "ready" flag
+    #[packed_field(bits="0")]
+    ready: bool,
+    #[packed_field(bits="7:4")]
+    priority: Integer<u8, packed_bits::Bits4>
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="msb0", endian="lsb")]
+pub struct Header {
+    #[packed_field(bits="0:3", ty="enum")]
+    kind: Kind,
+    #[packed_field(bits="4:7")]
+    _reserved: ReservedZero<packed_bits::Bits4>,
+    /// Payload length,
+    /// in bytes
+    #[packed_field(bytes="1:2")]
+    length: u16,
+    #[packed_field(bytes="3")]
+    flags: Flags,
+    #[packed_field(bytes="4:5")]
+    checksum: [u8; 2]
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone, PartialEq)]
+#[packed_struct(bit_numbering="sawtooth", size_bytes="5")]
+pub struct Status {
+    #[packed_field(bits="0")]
+    active: bool,
+    /// Intel signal, crosses bytes 0 and 1
+    #[packed_field(bits="4..=15", endian="lsb")]
+    speed: Integer<u16, packed_bits::Bits12>,
+    #[packed_field(bits="23..=24", endian="msb")]
+    temperature: i16,
+    #[packed_field(bits="32..=35")]
+    end: Integer<u8, packed_bits::Bits4>
+}
+
+fn dissector() -> LuaDissector {
+    LuaDissector::new("acme")
+        .with_layout(StructLayout::of::<Header>())
+        .with_layout(StructLayout::of::<Status>())
+}
+
+#[test]
+fn test_lua_dissector() {
+    // a golden file, update it with the output of to_lua() after checking the changes
+    assert_eq!(include_str!("data/acme_dissector.lua"), dissector().to_lua());
+}